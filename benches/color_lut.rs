@@ -0,0 +1,66 @@
+//! Compares coloring a height field through a [`ColorLut`] with blending every height by
+//! [`Gradient::lerp_color`]. Run with `cargo bench --bench color_lut`.
+
+use std::time::{Duration, Instant};
+
+use map_generation::{
+    generator::{self, GenParams},
+    gradient::{ColorLut, Gradient},
+};
+
+const WIDTH: u32 = 1920;
+const HEIGHT: u32 = 1080;
+const RUNS: usize = 10;
+
+/// Shortest of [`RUNS`] runs of `color`.
+fn best_of<F: FnMut()>(mut color: F) -> Duration {
+    (0..RUNS)
+        .map(|_| {
+            let start = Instant::now();
+            color();
+            start.elapsed()
+        })
+        .min()
+        .unwrap()
+}
+
+fn main() {
+    let params = GenParams::new(12345, WIDTH, HEIGHT, 0.2, 0.1);
+    let mut heights = vec![0.0; generator::pixel_count(WIDTH, HEIGHT).unwrap()];
+    generator::generate_heightmap(&mut heights, &params).unwrap();
+    let mut image = vec![0; heights.len() * 3];
+
+    let gradient = Gradient::default();
+    let lerp = best_of(|| {
+        generator::color_heights_with(
+            &mut image,
+            &heights,
+            &|height| gradient.lerp_color(height),
+            Some(1),
+            generator::DEFAULT_MIN_THREAD_CHUNK,
+        )
+    });
+    println!("{WIDTH}x{HEIGHT}, single thread, best of {RUNS}");
+    println!("lerp_color       {:>8.2} ms", lerp.as_secs_f64() * 1e3);
+
+    for size in [256, 1024, 4096] {
+        let setup = Instant::now();
+        let lut = ColorLut::new(&gradient, size);
+        let setup = setup.elapsed();
+        let lookup = best_of(|| {
+            generator::color_heights_with(
+                &mut image,
+                &heights,
+                &|height| lut.color(height),
+                Some(1),
+                generator::DEFAULT_MIN_THREAD_CHUNK,
+            )
+        });
+        println!(
+            "ColorLut {size:>5}   {:>8.2} ms, {:.1}x faster, built in {:.3} ms",
+            lookup.as_secs_f64() * 1e3,
+            lerp.as_secs_f64() / lookup.as_secs_f64(),
+            setup.as_secs_f64() * 1e3
+        );
+    }
+}
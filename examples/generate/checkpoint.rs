@@ -0,0 +1,90 @@
+use std::{
+    fs::File,
+    io::{BufReader, BufWriter, ErrorKind, Read, Write},
+};
+
+use map_generation::{
+    generator::{ClampCounts, GenParams},
+    gradient::Gradient,
+    hasher::mixed_hash,
+    heightfield::HeightField,
+};
+
+/// Start of every checkpoint file, bumped whenever the layout changes.
+const MAGIC: &[u8; 8] = b"ITGCKPT1";
+
+/// Identifies the height field generated from `params`.
+///
+/// Covers every parameter which changes the heights, but not those which only change how fast they
+/// are computed. The band limits of `gradient` are included, as band noise and
+/// [`GenParams::fast`] classify heights by them. The crate version is included as well, as
+/// generation itself may change between versions.
+pub fn key(params: &GenParams, gradient: &Gradient) -> u64 {
+    let params = GenParams {
+        thread_count: None,
+        min_thread_chunk: 0,
+        timeout: None,
+        ..*params
+    };
+    // Debug prints floats exactly, so parameters differing in any bit get different keys
+    mixed_hash(&(
+        env!("CARGO_PKG_VERSION"),
+        format!("{params:?}"),
+        format!("{:?}", gradient.terrain_limits),
+    ))
+}
+
+/// Loads the height field stored at `path` if it was generated with the parameters of `key`.
+///
+/// Returns None if the file does not exist, belongs to other parameters or is not a checkpoint.
+pub fn load(path: &str, key: u64) -> Result<Option<(HeightField, ClampCounts)>, std::io::Error> {
+    let file = match File::open(path) {
+        Ok(file) => file,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(err) => return Err(err),
+    };
+    let mut reader = BufReader::new(file);
+
+    let mut header = [0; 48];
+    if reader.read_exact(&mut header).is_err() || &header[..8] != MAGIC {
+        return Ok(None);
+    }
+    let u64_at = |idx: usize| u64::from_le_bytes(header[idx..idx + 8].try_into().unwrap());
+    let u32_at = |idx: usize| u32::from_le_bytes(header[idx..idx + 4].try_into().unwrap());
+    if u64_at(8) != key {
+        return Ok(None);
+    }
+    let (width, height) = (u32_at(16), u32_at(20));
+    let clamped = ClampCounts {
+        low: u64_at(24) as usize,
+        high: u64_at(32) as usize,
+        total: u64_at(40) as usize,
+    };
+
+    let mut bytes = Vec::new();
+    reader.read_to_end(&mut bytes)?;
+    let data = bytes
+        .chunks_exact(8)
+        .map(|chunk| f64::from_le_bytes(chunk.try_into().unwrap()))
+        .collect();
+    // a truncated file does not hold one height per pixel and is generated again
+    Ok(HeightField::from_vec(data, width, height).map(|heights| (heights, clamped)))
+}
+
+/// Stores `heights` at `path`, to be loaded again by [`load`] with the same `key`.
+pub fn save(
+    path: &str,
+    key: u64,
+    heights: &HeightField,
+    clamped: ClampCounts,
+) -> Result<(), std::io::Error> {
+    let mut writer = BufWriter::new(File::create(path)?);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&key.to_le_bytes())?;
+    writer.write_all(&heights.width.to_le_bytes())?;
+    writer.write_all(&heights.height.to_le_bytes())?;
+    for count in [clamped.low, clamped.high, clamped.total] {
+        writer.write_all(&(count as u64).to_le_bytes())?;
+    }
+    map_generation::export::write_raw_f64(&mut writer, &heights.data)
+}
@@ -1,15 +1,351 @@
-use std::{fmt::Display, fs::File, io::BufReader};
+use std::{collections::BTreeMap, fmt::Display, fs::File, io::BufReader, str::FromStr};
 
 use clap::Parser;
+use image::{imageops::FilterType, ImageFormat, Rgb};
+use map_generation::{
+    export::{self, BoundingBox},
+    generator::{self, Mirror, NoiseCurve, NoiseShape, OctaveBlend},
+    gradient::{Gradient, TerrainKind},
+    hasher::JitterRng,
+    palette::Palette,
+    postprocess::{self, DepthCurve, IslandShape, Stamp},
+    ramp::ColorRamp,
+};
 use rand::distributions::{Alphanumeric, DistString};
 
+/// Placeholders a name template may contain.
+pub const NAME_PLACEHOLDERS: [&str; 5] =
+    ["{seed}", "{width}", "{height}", "{index}", "{timestamp}"];
+
 const DEFAULT_WIDTH: u32 = 1920;
 const DEFAULT_HEIGHT: u32 = 1080;
+const DEFAULT_PREVIEW_SCALE: u32 = 1;
 const DEFAULT_NOISE_STRENGTH: f64 = 0.25;
 const DEFAULT_HEIGHT_OFFSET: f64 = 0.0;
+const DEFAULT_BLEND: f64 = 0.5;
+const DEFAULT_CONTRAST: f64 = 1.0;
+const DEFAULT_BIAS: f64 = 1.0;
+const DEFAULT_PEAK_RARITY: f64 = 0.0;
+const DEFAULT_OCTAVE_JITTER: f64 = 0.0;
+const DEFAULT_CONTOUR_INTERVAL: f64 = 0.05;
+const DEFAULT_CONTOUR_COLOR: [u8; 3] = [110, 75, 45];
+const DEFAULT_CONTOUR_WIDTH: f64 = 1.0;
+const DEFAULT_SVG_FILL_BANDS: bool = false;
+const DEFAULT_TILESET_SOURCE: &str = "tileset.tsx";
+const DEFAULT_TILESET_TILE_SIZE: u32 = 16;
+const DEFAULT_CHECK_PLANET: bool = false;
+const DEFAULT_PREWARM_THREADPOOL: bool = false;
+const DEFAULT_CHECK_PLANET_THRESHOLD: f64 = 2.0;
+const DEFAULT_FLATTEN_WATER: bool = false;
+const DEFAULT_OCEANS_ONLY_CONNECTED: bool = false;
+const DEFAULT_LAKE_COLOR: [u8; 3] = [64, 150, 200];
+const DEFAULT_DRY_COLOR: [u8; 3] = [215, 190, 125];
+const DEFAULT_WET_COLOR: [u8; 3] = [72, 111, 56];
+const DEFAULT_REGION_BORDER_COLOR: [u8; 3] = [40, 40, 40];
+const DEFAULT_REGIONS_INCLUDE_WATER: bool = false;
+const DEFAULT_GRID_COLOR: [u8; 3] = [0, 0, 0];
+const DEFAULT_GRID_OPACITY: f64 = 0.5;
+const DEFAULT_GRID_LABELS: bool = false;
+const DEFAULT_SNOW_FALLOFF: f64 = 0.15;
+const DEFAULT_SEAMLESS: bool = false;
+const DEFAULT_ROTATE: f64 = 0.0;
+const DEFAULT_EQUAL_AREA_PROJECTION: bool = false;
+const DEFAULT_ANTIMERIDIAN_WRAP: bool = false;
+const DEFAULT_COLOR_INTERPOLATION_ACROSS_ALL_BANDS: bool = false;
+const DEFAULT_BAND_BLEND_WIDTH: f64 = 1.0;
+const DEFAULT_CRS: u16 = 4326;
+const DEFAULT_LAND_RATIO_TOLERANCE: f64 = 0.005;
+const DEFAULT_DIFF_AMPLIFY: f64 = 1.0;
+const DEFAULT_BAND_REPORT: bool = false;
+const DEFAULT_SEED_DISPLAY: bool = false;
+const DEFAULT_SHEET_CELL_SIZE: [u32; 2] = [320, 180];
+const DEFAULT_PARALLEL_TILES: u32 = 1;
+const DEFAULT_SEARCH_TOP: u32 = 10;
+const DEFAULT_TARGET_LAND: f64 = 0.4;
+const DEFAULT_TARGET_MOUNTAINS: f64 = 0.05;
+const DEFAULT_TARGET_LANDMASS: f64 = 0.3;
+const DEFAULT_SEARCH_WEIGHTS: [f64; 3] = [1.0, 1.0, 1.0];
+const DEFAULT_HISTOGRAM_BUCKETS: u32 = 256;
+const DEFAULT_BAKE_GRADIENT: bool = false;
+const DEFAULT_LUT_SIZE: u32 = 256;
+const DEFAULT_REPRODUCIBLE: bool = false;
+const DEFAULT_FAST: bool = false;
+/// Seed used by reproducible renders which do not set one, instead of a random one.
+const REPRODUCIBLE_SEED: &str = "reproducible";
+const DEFAULT_AA: u32 = 1;
+const DEFAULT_OPEN: bool = false;
 const DEFAULT_OUTPUT: &str = "output.png";
 const DEFAULT_DUMP_CONFIG: bool = false;
-const DEFAULT_VERBOSE: bool = false;
+const DEFAULT_VERBOSE: u8 = 0;
+const DEFAULT_STDIN_CONFIGS: bool = false;
+const DEFAULT_WATCH: bool = false;
+const DEFAULT_Z_SCALE: f64 = 100.0;
+const DEFAULT_DOWNSCALE_FILTER: DownscaleFilter = DownscaleFilter::Box;
+
+/// Haze color low terrain is blended towards, see `--depth-tint`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug)]
+pub struct DepthTint {
+    pub color: [u8; 3],
+    pub strength: f64,
+}
+
+/// Filter the colors of an antialiased image are downscaled with, see `--downscale-filter`.
+///
+/// `box` averages the samples of each pixel while coloring them and is the fastest. The others
+/// resize the fully colored samples afterwards: `nearest` picks a single sample per pixel and
+/// aliases like an image without antialiasing, `triangle` blends neighboring pixels in smoothly,
+/// `catmull-rom` keeps edges crisper and `lanczos3` is the sharpest but slowest.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum DownscaleFilter {
+    Nearest,
+    Box,
+    Triangle,
+    CatmullRom,
+    Lanczos3,
+}
+
+impl DownscaleFilter {
+    /// Filter of the `image` crate to resize with, `None` for averaging the samples of each pixel.
+    pub fn filter_type(self) -> Option<FilterType> {
+        match self {
+            DownscaleFilter::Nearest => Some(FilterType::Nearest),
+            DownscaleFilter::Box => None,
+            DownscaleFilter::Triangle => Some(FilterType::Triangle),
+            DownscaleFilter::CatmullRom => Some(FilterType::CatmullRom),
+            DownscaleFilter::Lanczos3 => Some(FilterType::Lanczos3),
+        }
+    }
+}
+
+impl FromStr for DownscaleFilter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "nearest" => Ok(DownscaleFilter::Nearest),
+            "box" => Ok(DownscaleFilter::Box),
+            "triangle" => Ok(DownscaleFilter::Triangle),
+            "catmull-rom" => Ok(DownscaleFilter::CatmullRom),
+            "lanczos3" => Ok(DownscaleFilter::Lanczos3),
+            _ => Err(format!(
+                "{s} is not a downscale filter. Available are: nearest, box, triangle, catmull-rom, lanczos3"
+            )),
+        }
+    }
+}
+
+/// Product of the generated map written by `--output-multiple`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum OutputKind {
+    /// the colored image, as written to the output path otherwise
+    Color,
+    /// the height field as 16 bit grayscale image
+    Height,
+    /// tangent space normal map of the height field
+    Normal,
+}
+
+impl OutputKind {
+    pub fn description(self) -> &'static str {
+        match self {
+            OutputKind::Color => "color map",
+            OutputKind::Height => "16 bit heightmap",
+            OutputKind::Normal => "normal map",
+        }
+    }
+}
+
+impl FromStr for OutputKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "color" => Ok(OutputKind::Color),
+            "height" => Ok(OutputKind::Height),
+            "normal" => Ok(OutputKind::Normal),
+            _ => Err(format!(
+                "{s} is not an output kind. Available are: color, height, normal"
+            )),
+        }
+    }
+}
+
+/// Products and the paths they are written to, see `--output-multiple`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(transparent)]
+pub struct OutputList(pub Vec<(OutputKind, String)>);
+
+/// Bundle of settings for a kind of world, see `--theme`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Theme {
+    /// mostly ocean with scattered islands
+    WaterWorld,
+    /// large dry landmasses with few wet spots
+    Desert,
+    /// ice caps reaching far towards the equator
+    Arctic,
+}
+
+impl Theme {
+    /// Settings of the theme. Unset fields are left to the arguments, the config file and the
+    /// defaults.
+    fn overrides(self) -> Config {
+        let mut config = Config::parse_from(["generate"]);
+        match self {
+            Theme::WaterWorld => {
+                config.bias = Some(1.4);
+                config.palette = Some(Palette::WaterWorld);
+            }
+            Theme::Desert => {
+                config.bias = Some(0.8);
+                config.palette = Some(Palette::Desert);
+                config.moisture_threshold = Some(0.7);
+                config.dry_color = Some([222, 184, 120]);
+                config.wet_color = Some([110, 140, 70]);
+            }
+            Theme::Arctic => {
+                config.palette = Some(Palette::Arctic);
+                config.snow_line = Some(0.85);
+                config.snow_falloff = Some(0.3);
+            }
+        }
+        config
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "water-world" => Ok(Theme::WaterWorld),
+            "desert" => Ok(Theme::Desert),
+            "arctic" => Ok(Theme::Arctic),
+            _ => Err(format!(
+                "{s} is not a theme. Available are: water-world, desert, arctic"
+            )),
+        }
+    }
+}
+
+/// Band of a gradient given in the config, see `Config::gradient`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+pub struct BandSpec {
+    /// upper limit of the band, it starts where the band below it ends
+    pub limit: f64,
+    pub color: [u8; 3],
+    /// defaults to `band-<index>`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+}
+
+impl BandSpec {
+    /// Gradient made of `bands`, which may be given in any order.
+    pub fn gradient(bands: &[BandSpec]) -> Gradient {
+        let mut gradient = Gradient::empty();
+        for (idx, band) in bands.iter().enumerate() {
+            let name = band.name.clone().unwrap_or_else(|| format!("band-{idx}"));
+            gradient.insert_band(band.limit, Rgb(band.color), name);
+        }
+        gradient
+    }
+}
+
+/// Noise strength per terrain kind, see `--band-noise-strength`. Keys are terrain kind names.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+#[serde(transparent)]
+pub struct BandNoise(pub BTreeMap<String, f64>);
+
+impl BandNoise {
+    /// Noise strength of each terrain kind, indexed by `TerrainKind as usize`.
+    pub fn strengths(&self) -> Result<[Option<f64>; TerrainKind::COUNT], String> {
+        let mut strengths = [None; TerrainKind::COUNT];
+        for (name, strength) in &self.0 {
+            let kind: TerrainKind = name.parse()?;
+            let entry = strengths
+                .get_mut(kind as usize)
+                .ok_or_else(|| format!("{name} has no noise strength."))?;
+            if *strength < 0.0 {
+                return Err("Noise strength must not be negative!".to_string());
+            }
+            *entry = Some(*strength);
+        }
+        Ok(strengths)
+    }
+}
+
+/// File format of `--tileset-index`, derived from the extension of its path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TileFormat {
+    Csv,
+    Tmx,
+}
+
+impl TileFormat {
+    pub fn from_path(path: &str) -> Option<Self> {
+        let extension = std::path::Path::new(path).extension()?.to_str()?;
+        match extension.to_ascii_lowercase().as_str() {
+            "csv" => Some(TileFormat::Csv),
+            "tmx" => Some(TileFormat::Tmx),
+            _ => None,
+        }
+    }
+}
+
+/// Tile ID per terrain kind, see `--tile-ids` and `--tile-transitions`. Keys are terrain kind names.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug, Default)]
+#[serde(transparent)]
+pub struct TileIds(pub BTreeMap<String, u32>);
+
+impl TileIds {
+    /// ID of each terrain kind, indexed by `TerrainKind as usize`.
+    pub fn ids(&self) -> Result<[Option<u32>; TerrainKind::COUNT], String> {
+        let mut ids = [None; TerrainKind::COUNT];
+        for (name, id) in &self.0 {
+            let kind: TerrainKind = name.parse()?;
+            let entry = ids
+                .get_mut(kind as usize)
+                .ok_or_else(|| format!("{name} has no tile."))?;
+            *entry = Some(*id);
+        }
+        Ok(ids)
+    }
+}
+
+/// Noise strength over elevation as `[elevation, strength]` points, see `--noise-strength-curve`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(transparent)]
+pub struct NoiseCurveSpec(pub Vec<[f64; 2]>);
+
+impl NoiseCurveSpec {
+    pub fn curve(&self) -> Result<NoiseCurve, String> {
+        let points: Vec<(f64, f64)> = self
+            .0
+            .iter()
+            .map(|[elevation, strength]| (*elevation, *strength))
+            .collect();
+        NoiseCurve::new(&points)
+    }
+}
+
+/// Remap of heights as `[height, new height]` points, see `--depth-curve`.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Debug)]
+#[serde(transparent)]
+pub struct DepthCurveSpec(pub Vec<[f64; 2]>);
+
+impl DepthCurveSpec {
+    pub fn curve(&self) -> Result<DepthCurve, String> {
+        let points: Vec<(f64, f64)> = self
+            .0
+            .iter()
+            .map(|[height, new_height]| (*height, *new_height))
+            .collect();
+        DepthCurve::new(&points)
+    }
+}
 
 /// Program to generate maps and save them as png images.
 #[derive(serde::Serialize, serde::Deserialize, Parser, Clone, Debug)]
@@ -23,6 +359,14 @@ pub struct Config {
     #[arg(long)]
     pub seed: Option<String>,
 
+    /// Seed of a second map blended with the first one
+    #[arg(long)]
+    pub seed_b: Option<String>,
+
+    /// Share of the second map in the result. 0 is only the map of `seed`, 1 only the one of `seed_b`
+    #[arg(long, value_parser= blend_in_range)]
+    pub blend: Option<f64>,
+
     /// Width of image
     #[arg(long, value_parser= clap::value_parser!(u32).range(1..))]
     pub width: Option<u32>,
@@ -31,36 +375,658 @@ pub struct Config {
     #[arg(long, value_parser= clap::value_parser!(u32).range(1..))]
     pub height: Option<u32>,
 
+    /// Aspect ratio as `W:H`. Together with `long_edge` it determines width and height, which
+    /// take precedence over `width` and `height`
+    #[arg(long, value_parser = parse_aspect, requires = "long_edge")]
+    pub aspect: Option<[u32; 2]>,
+
+    /// Length of the longer image side in pixels, see `aspect`
+    #[arg(long, value_parser= clap::value_parser!(u32).range(1..), requires = "aspect")]
+    pub long_edge: Option<u32>,
+
+    /// Divide width and height by this factor for a quick, lower resolution preview of the same map
+    #[arg(long, value_parser= clap::value_parser!(u32).range(1..))]
+    pub preview_scale: Option<u32>,
+
     /// Strength of white noise applied to Perlin noise
     #[arg(long, value_parser= noise_strength_in_range)]
     pub noise_strength: Option<f64>,
 
-    /// Base height at which to start while generating
-    #[arg(long, value_parser= base_height_in_range)]
+    /// Noise strength of single terrain kinds as `KIND=STRENGTH,...`, e.g. `mountains=0.5,water=0`.
+    /// Other kinds use `--noise-strength`
+    #[arg(long, value_parser = parse_band_noise)]
+    pub band_noise_strength: Option<BandNoise>,
+
+    /// Noise strength following a curve over elevation, as ascending `ELEVATION:STRENGTH,...`
+    /// points, e.g. `0.6:0.1,0.8:0.4,1:0.2`. Strengths between points are interpolated. Replaces
+    /// `--noise-strength`, `--band-noise-strength` still takes precedence
+    #[arg(long, value_parser = parse_noise_curve)]
+    pub noise_strength_curve: Option<NoiseCurveSpec>,
+
+    /// Remap heights before they are classified, as ascending `HEIGHT:NEW_HEIGHT,...` points
+    /// between 0 and 1. E.g. `0.45:0.3,0.6:0.6` spreads the ocean over the deep water and water
+    /// bands to show seafloor variation, while the coast stays where it is. Heights between points
+    /// are interpolated, 0 and 1 stay. Independent of `--contrast`, which shapes the generated
+    /// heights
+    #[arg(long, alias = "height-scale-nonlinear", value_parser = parse_depth_curve)]
+    pub depth_curve: Option<DepthCurveSpec>,
+
+    /// Number of distinct values the white noise is made of. Lower values give coarser grain
+    #[arg(long, value_parser= clap::value_parser!(u32).range(1..))]
+    pub noise_granularity: Option<u32>,
+
+    /// Largest white noise value before it is multiplied by the noise strength
+    #[arg(long, value_parser= noise_magnitude_in_range)]
+    pub noise_magnitude: Option<f64>,
+
+    /// Distribution of the white noise values: uniform, triangular or bell. Triangular and bell
+    /// favor values around half the magnitude, which gives softer grain
+    #[arg(long)]
+    pub noise_shape: Option<NoiseShape>,
+
+    /// Feature to place into the terrain as `SHAPE:X:Y:RADIUS:STRENGTH`, e.g. `cone:400:300:80:0.3`
+    /// for a mountain. Shapes are cone, dome and crater. The position and radius are in pixels of
+    /// the image, the strength is the height added at the center, or at the rim of a crater, and
+    /// may be negative. May be given several times, overlapping stamps add up
+    #[arg(
+        long = "stamp",
+        value_name = "STAMP",
+        value_parser = parse_stamp,
+        allow_hyphen_values = true
+    )]
+    pub stamps: Option<Vec<Stamp>>,
+
+    /// Base level at which to start while generating. `--base-height` and `base_height` in config
+    /// files are accepted as deprecated aliases
+    #[serde(alias = "base_height")]
+    #[arg(long, alias = "base-height", value_parser= level_in_range)]
     pub base_level: Option<f64>,
 
+    /// Share of land to tune the base level for, replacing `--base-level`. The level is searched
+    /// on low resolution samples of `--search-size` until their share of shore and higher terrain
+    /// is within `--land-ratio-tolerance` of it. Seeds with more land than the target even at base
+    /// level 0 get a level close to 0
+    #[arg(long, value_parser= level_in_range)]
+    pub land_ratio_target: Option<f64>,
+
+    /// Largest difference between the share of land and `--land-ratio-target` the tuned base level
+    /// may leave
+    #[arg(long, value_parser= land_ratio_tolerance_in_range)]
+    pub land_ratio_tolerance: Option<f64>,
+
+    /// Spread heights out from (above 1) or pull them towards (below 1) the middle level.
+    /// Applied after base level and white noise
+    #[arg(long, value_parser= contrast_in_range)]
+    pub contrast: Option<f64>,
+
+    /// Exponent applied to heights after contrast. Above 1 favors low, below 1 high elevations
+    #[arg(long, value_parser= bias_in_range)]
+    pub bias: Option<f64>,
+
+    /// Make high elevations progressively rarer by reshaping the land above the lowlands
+    /// exponentially, after contrast and bias. 0 leaves the heights unchanged, larger values thin
+    /// out the peaks more
+    #[arg(long, value_parser = peak_rarity_in_range)]
+    pub peak_rarity: Option<f64>,
+
+    /// Antialias terrain borders by averaging FACTOR x FACTOR samples per pixel. Generation takes
+    /// FACTOR^2 times as long
+    #[arg(long, value_parser= clap::value_parser!(u32).range(1..=16))]
+    pub aa: Option<u32>,
+
+    /// Filter to downscale the colors of an antialiased image with: box (fastest, averages the
+    /// samples of each pixel), nearest (aliased), triangle (smooth), catmull-rom (crisp) or lanczos3
+    /// (sharpest, slowest)
+    #[arg(long)]
+    pub downscale_filter: Option<DownscaleFilter>,
+
+    /// Color heights with a continuous color map instead of terrain bands: grayscale, viridis, terrain
+    #[arg(long)]
+    pub color_ramp: Option<ColorRamp>,
+
+    /// Built-in colors of the terrain bands: default, deuteranopia, protanopia, water-world, desert,
+    /// arctic. Deuteranopia and protanopia are easier to tell apart with red-green color blindness
+    #[arg(long)]
+    pub palette: Option<Palette>,
+
+    /// Blend the colors continuously through the centers of all bands instead of between
+    /// neighboring bands only. Has no effect on color ramps
+    #[arg(long, num_args = 0, default_missing_value = "true")]
+    pub color_interpolation_across_all_bands: Option<bool>,
+
+    /// Share of the way from a band center to its limit over which the band blends into its
+    /// neighbor, from 0 for hard edges to 1 for blending all the way from the center. Has no effect
+    /// on color ramps or with --color-interpolation-across-all-bands
+    #[arg(long, value_parser = band_blend_width_in_range)]
+    pub band_blend_width: Option<f64>,
+
+    /// Preset of settings for a kind of world: water-world (mostly ocean), desert (dry land, sandy
+    /// colors) or arctic (ice caps, cold colors). Explicitly given settings take precedence
+    #[arg(long)]
+    pub theme: Option<Theme>,
+
+    /// Approximate heights far from band limits for faster generation. Large areas of one band,
+    /// like open oceans, lose their fine detail, coastlines stay the same. Small images gain
+    /// little. Has no effect on wrapping maps and with --equal-area-projection
+    #[arg(long, num_args = 0, default_missing_value = "true")]
+    pub fast: Option<bool>,
+
+    /// Bands replacing the default gradient, only available in config files. Each band is given
+    /// as `{ "limit": 0.5, "color": [0, 64, 106], "name": "sea" }`, the name is optional
+    #[arg(skip)]
+    pub gradient: Option<Vec<BandSpec>>,
+
+    /// Color with a table of colors precomputed from the gradient instead of interpolating every
+    /// pixel. Faster for large images, but heights only get the color of the closest entry
+    #[arg(long, num_args = 0, default_missing_value = "true")]
+    pub bake_gradient: Option<bool>,
+
+    /// Number of entries of the color table used by `--bake-gradient`
+    #[arg(long, value_parser = clap::value_parser!(u32).range(2..=65536))]
+    pub lut_size: Option<u32>,
+
+    /// Color the terrain bands with the dominant colors of this image, assigned from dark to
+    /// bright. The heights at which bands start stay at their defaults
+    #[arg(long)]
+    pub palette_from_image: Option<String>,
+
+    /// Flatten all water below the shore to the sea level, so it is colored uniformly
+    #[arg(long, num_args = 0, default_missing_value = "true")]
+    pub flatten_water: Option<bool>,
+
+    /// Level water is flattened to by `flatten_water`
+    #[arg(long, value_parser= level_in_range)]
+    pub sea_level: Option<f64>,
+
+    /// Color water which is not connected to the image border as lakes instead of ocean
+    #[arg(long, num_args = 0, default_missing_value = "true")]
+    pub oceans_only_connected: Option<bool>,
+
+    /// Color of lakes, as `r,g,b` or `#rrggbb`
+    #[arg(long, value_parser = parse_color)]
+    pub lake_color: Option<[u8; 3]>,
+
+    /// Color shores and flat land by a moisture field: below this level with `--dry-color`, above
+    /// with `--wet-color`
+    #[arg(long, value_parser = level_in_range)]
+    pub moisture_threshold: Option<f64>,
+
+    /// Shade slopes by the direction they face in this many discrete brightness steps, for a
+    /// cel-shaded look. 1 disables shading
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub flat_shading_levels: Option<u32>,
+
+    /// Divide the map into this many regions with borders, like a political map
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub voronoi_regions: Option<u32>,
+
+    /// Color of the borders between regions, as `r,g,b` or `#rrggbb`
+    #[arg(long, value_parser = parse_color)]
+    pub region_border_color: Option<[u8; 3]>,
+
+    /// Divide water into regions as well instead of only land
+    #[arg(long, num_args = 0, default_missing_value = "true")]
+    pub regions_include_water: Option<bool>,
+
+    /// Color of dry shores and flat land, as `r,g,b` or `#rrggbb`
+    #[arg(long, value_parser = parse_color)]
+    pub dry_color: Option<[u8; 3]>,
+
+    /// Color of wet shores and flat land, as `r,g,b` or `#rrggbb`
+    #[arg(long, value_parser = parse_color)]
+    pub wet_color: Option<[u8; 3]>,
+
+    /// Cover terrain above this height at the equator with snow. Towards the poles at the top and
+    /// bottom edges the snow line drops by `snow_falloff`
+    #[arg(long, value_parser= level_in_range)]
+    pub snow_line: Option<f64>,
+
+    /// How much lower the snow line is at the poles than at the equator
+    #[arg(long, value_parser= level_in_range)]
+    pub snow_falloff: Option<f64>,
+
+    /// Reduce the final image to at most this many colors for a posterized look
+    #[arg(long, value_parser= clap::value_parser!(u32).range(1..=256))]
+    pub color_quantize: Option<u32>,
+
+    /// Tint low terrain towards a haze color, as `COLOR:STRENGTH`, e.g. `#c8d2e6:0.5`. Pixels are
+    /// blended towards the color by `STRENGTH * (1 - height)`
+    #[arg(long, value_parser = parse_depth_tint)]
+    pub depth_tint: Option<DepthTint>,
+
+    /// Draw a coordinate grid over the final image with lines every this many pixels
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub grid_overlay: Option<u32>,
+
+    /// Color of the grid lines, as `r,g,b` or `#rrggbb`
+    #[arg(long, value_parser = parse_color)]
+    pub grid_color: Option<[u8; 3]>,
+
+    /// Opacity of the grid lines, 0 makes them invisible
+    #[arg(long, value_parser = opacity_in_range)]
+    pub grid_opacity: Option<f64>,
+
+    /// Label the grid lines with their pixel coordinates at the top and left edges
+    #[arg(long, num_args = 0, default_missing_value = "true")]
+    pub grid_labels: Option<bool>,
+
+    /// Generate an image which wraps around at its edges and can be tiled without seams
+    #[arg(long, num_args = 0, default_missing_value = "true")]
+    pub seamless: Option<bool>,
+
+    /// Wrap the world around after `WIDTHxHEIGHT` pixels, making it a torus. The image shows the
+    /// part of the world starting at its top left corner
+    #[arg(long, value_parser = parse_size)]
+    pub world_size: Option<[u32; 2]>,
+
+    /// Wrap only the left and right edges around, so continents continue across them like on a
+    /// planet while the top and bottom edges stay the poles
+    #[arg(long, num_args = 0, default_missing_value = "true")]
+    pub antimeridian_wrap: Option<bool>,
+
+    /// Size in pixels of the largest features. Keeps the map the same when changing the image
+    /// size, a larger image then shows more of it instead of scaling it up
+    #[arg(long, value_parser = feature_scale_in_range)]
+    pub feature_scale: Option<f64>,
+
+    /// Make the terrain symmetric by mirroring one half or quadrant: none, horizontal, vertical,
+    /// quad
+    #[arg(long)]
+    pub mirror: Option<Mirror>,
+
+    /// How the octaves of the noise are combined: add (sum), max (smooth terrain with creases) or
+    /// multiply (features concentrated where octaves agree). Max and multiply are rescaled to
+    /// roughly the range of the sum. `--fast` has no effect unless add is used
+    #[arg(long)]
+    pub octave_blend: Option<OctaveBlend>,
+
+    /// Shift each octave of the noise by up to this many noise lattice cells, derived from the
+    /// seed, so the octaves do not line up into faint grid patterns. 0.5 is usually enough, 0
+    /// disables it
+    #[arg(long, value_parser = octave_jitter_in_range)]
+    pub octave_jitter: Option<f64>,
+
+    /// Generator deriving the white noise and octave jitter from the seed: seed-hash (the seed
+    /// hashed for each pixel and octave) or split-mix (SplitMix64). Both are deterministic, but
+    /// give different noise
+    #[arg(long)]
+    pub jitter_rng: Option<JitterRng>,
+
+    /// Lower the terrain towards the edges to form an island: ellipse stretches it to the image,
+    /// circle keeps it round on non-square images
+    #[arg(long)]
+    pub island_shape: Option<IslandShape>,
+
+    /// Rotate the terrain pattern about the image center by this angle in degrees
+    #[arg(long, allow_negative_numbers = true)]
+    pub rotate: Option<f64>,
+
+    /// Sample the noise more sparsely towards the top and bottom edges, so features have roughly
+    /// equal area on a globe. Only useful if the image is used as an equirectangular map
+    #[arg(long, num_args = 0, default_missing_value = "true")]
+    pub equal_area_projection: Option<bool>,
+
+    /// Open the saved image in the default image viewer
+    #[arg(long, num_args = 0, default_missing_value = "true")]
+    pub open: Option<bool>,
+
     /// Path to configuration JSON file
     #[serde(skip_deserializing)]
     #[arg(short = 'i', long)]
     pub config_file: Option<String>,
 
-    /// Output path to save image at
+    /// Warn and continue with the command line arguments if the config file cannot be parsed,
+    /// instead of failing
+    #[serde(skip)]
+    #[arg(long, action=clap::ArgAction::SetTrue)]
+    pub lenient_config: Option<bool>,
+
+    /// Color this 8 or 16 bit grayscale heightmap instead of generating one, black being level 0
+    /// and white level 1. The image size is taken from it, post-processing and overlays still
+    /// apply
+    #[arg(long)]
+    pub input_heightmap: Option<String>,
+
+    /// Cache the generated height field in this file. If it holds the field of the same seed and
+    /// generation parameters, the field is loaded instead of generated again, so changes to colors,
+    /// post-processing and overlays render quickly. Otherwise it is replaced by the new field.
+    /// Not used by contact sheets and images rendered band by band with `--limit-memory`
+    #[arg(long)]
+    pub checkpoint_heightmap: Option<String>,
+
+    /// Output path to save image at. `-` writes the image to stdout, which requires `--format`
     #[arg(short, long)]
     pub output_path: Option<String>,
 
+    /// Write several products of the same generated map instead of the output path, given as
+    /// `KIND:PATH` list, e.g. `color:map.png,height:h.png,normal:n.png`. Kinds are color (the
+    /// image), height (16 bit heightmap) and normal (normal map, its relief scaled by `--z-scale`)
+    #[arg(long, value_parser = parse_output_multiple)]
+    pub output_multiple: Option<OutputList>,
+
+    /// Directory images are saved in, created if missing. The file name is taken from the output
+    /// path or composed with `--name-template`
+    #[arg(long)]
+    pub output_dir: Option<String>,
+
+    /// File name composed from the placeholders `{seed}`, `{width}`, `{height}`, `{index}` (the
+    /// position of the image in a batch, starting at 1) and `{timestamp}` (seconds since the Unix
+    /// epoch), e.g. `{seed}_{width}x{height}.png`. Replaces the file name of the output path
+    #[arg(long, value_parser = parse_name_template)]
+    pub name_template: Option<String>,
+
+    /// Image format given as file extension, e.g. `png`. Derived from the output path if not set
+    #[arg(long, value_parser = parse_format)]
+    pub format: Option<String>,
+
+    /// Output path to additionally save the image tiled 3x3 at, to check `--seamless` for seams
+    #[arg(long)]
+    pub tile_preview: Option<String>,
+
+    /// Output path to additionally save a bar chart of the height distribution at, with the band
+    /// limits marked in the color of the band above them
+    #[arg(long)]
+    pub histogram: Option<String>,
+
+    /// Directory to additionally save a black and white mask of each terrain kind in, e.g.
+    /// `flat_land.png`, white where the terrain is of that kind
+    #[arg(long)]
+    pub export_bands: Option<String>,
+
+    /// Number of bars of the histogram
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..=4096))]
+    pub histogram_buckets: Option<u32>,
+
+    /// Output path to additionally save a grid of tile IDs at, one per pixel, for tile based game
+    /// engines. Written as CSV if the path ends with `.csv` and as map of the Tiled editor if it
+    /// ends with `.tmx`
+    #[arg(long)]
+    pub tileset_index: Option<String>,
+
+    /// Tile ID of each terrain kind as `KIND=ID,...`, e.g. `water=0,shore=1`. Other kinds use
+    /// their index, from 0 for deep water to 7 for mountain tops
+    #[arg(long, value_parser = parse_tile_ids)]
+    pub tile_ids: Option<TileIds>,
+
+    /// First ID of a block of 16 transition tiles of terrain kinds as `KIND=ID,...`, e.g.
+    /// `shore=16`. Cells of these kinds use the tile at the offset of the lower neighbors in the
+    /// block, adding 1 for the one above, 2 right, 4 below and 8 left, so shore tiles can show
+    /// where the water is
+    #[arg(long, value_parser = parse_tile_ids)]
+    pub tile_transitions: Option<TileIds>,
+
+    /// Tileset referenced by Tiled maps of `--tileset-index`, relative to the map
+    #[arg(long)]
+    pub tileset_source: Option<String>,
+
+    /// Width and height of the tiles of Tiled maps of `--tileset-index` in pixels
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub tileset_tile_size: Option<u32>,
+
+    /// Output path to additionally save the height field at as single band float GeoTIFF,
+    /// georeferenced by `--bbox` and `--crs` for GIS software like QGIS
+    #[arg(long, alias = "export-geotiff")]
+    pub geotiff: Option<String>,
+
+    /// Area covered by the GeoTIFF as `MIN_LON,MIN_LAT,MAX_LON,MAX_LAT`, in the units of `--crs`.
+    /// The whole earth if not set
+    #[arg(long, value_parser = parse_bbox, allow_hyphen_values = true)]
+    pub bbox: Option<[f64; 4]>,
+
+    /// Coordinate reference system of the GeoTIFF as EPSG code, e.g. `EPSG:4326` or `3857`. Codes
+    /// from 4000 to 4999 are taken as geographic systems in degrees, all others as projected
+    #[arg(long, value_parser = parse_crs)]
+    pub crs: Option<u16>,
+
+    /// Output path to additionally save the terrain as Wavefront OBJ mesh at
+    #[arg(long)]
+    pub obj: Option<String>,
+
+    /// Distance in pixels between two vertices of the OBJ mesh. Picked based on image size if not set
+    #[arg(long, value_parser= clap::value_parser!(u32).range(1..))]
+    pub mesh_step: Option<u32>,
+
+    /// Output path to additionally save a vector topographic map at, with contour lines traced
+    /// from the height field as SVG paths
+    #[arg(long)]
+    pub emit_svg: Option<String>,
+
+    /// Height difference between neighboring contour lines of the SVG map
+    #[arg(long, value_parser = contour_interval_in_range)]
+    pub contour_interval: Option<f64>,
+
+    /// Color of the contour lines of the SVG map, as `r,g,b` or `#rrggbb`
+    #[arg(long, value_parser = parse_color)]
+    pub contour_color: Option<[u8; 3]>,
+
+    /// Width of the contour lines of the SVG map in pixels
+    #[arg(long, value_parser = contour_width_in_range)]
+    pub contour_width: Option<f64>,
+
+    /// Fill the area of each terrain band of the SVG map with the color of the band, below the
+    /// contour lines
+    #[arg(long, num_args = 0, default_missing_value = "true")]
+    pub svg_fill_bands: Option<bool>,
+
+    /// Largest distance in pixels by which the lines of the SVG map may deviate from the traced
+    /// contours, to keep the file small. Picked based on image size if not set
+    #[arg(long, value_parser = svg_simplify_in_range)]
+    pub svg_simplify: Option<f64>,
+
+    /// Factor applied to heights of the OBJ mesh
+    #[arg(long)]
+    pub z_scale: Option<f64>,
+
+    /// Output path to additionally save the raw height field at as little-endian f32 values
+    #[arg(long)]
+    pub raw: Option<String>,
+
+    /// Output path to additionally save the raw height field at as little-endian f64 values
+    #[arg(long)]
+    pub raw_f64: Option<String>,
+
+    /// Write a grayscale map of the local roughness within this radius next to the output, named
+    /// `<output>_roughness.png`. Flat areas are dark
+    #[arg(long)]
+    pub roughness: Option<u32>,
+
+    /// Output path to additionally save statistics about the generated map at as JSON
+    #[arg(long)]
+    pub stats_json: Option<String>,
+
+    /// Abort generation if it takes longer than this many seconds. Checked once per row, so
+    /// the timing is approximate. No image is written on timeout
+    #[arg(long, value_parser= timeout_in_range)]
+    pub timeout: Option<f64>,
+
+    /// Keep the memory needed for the image below this many megabytes. Images which would need
+    /// more are rendered band by band and streamed into a PNG file, without post-processing,
+    /// overlays and additional outputs, which need the whole image
+    #[arg(long, value_parser = clap::value_parser!(u64).range(1..))]
+    pub limit_memory: Option<u64>,
+
     /// Number of threads created to generate image
     #[arg(short='j', long, value_parser= thread_count_in_range)]
     pub thread_count: Option<usize>,
 
+    /// Start the generation threads once and reuse them for every image, instead of starting new
+    /// ones for each. Saves time when rendering many small images, e.g. with `--seed-increment`,
+    /// `--seed-list` or `--stdin-configs`. The pool has `--thread-count` threads
+    #[serde(skip)]
+    #[arg(long, action=clap::ArgAction::SetTrue)]
+    pub prewarm_threadpool: Option<bool>,
+
+    /// Fewest pixels each thread generates. Small images are generated by fewer threads, down to a
+    /// single one, as starting more threads would take longer than they save
+    #[arg(long, value_parser= clap::value_parser!(u32).range(1..))]
+    pub min_thread_chunk: Option<u32>,
+
     /// Output path to save image at
     #[serde(skip_serializing)]
     #[arg(short, long, action=clap::ArgAction::SetTrue)]
     pub dump_config: Option<bool>,
 
-    /// Print information about generation after it is done
+    /// Print the bands of the gradient used for coloring: index, name, lower and upper limit,
+    /// center and color at the center, separated by tabs
     #[serde(skip_serializing)]
-    #[arg(short, long, action=clap::ArgAction::SetTrue)]
-    pub verbose: Option<bool>,
+    #[arg(long, action=clap::ArgAction::SetTrue)]
+    pub band_report: Option<bool>,
+
+    /// Print the seed together with the number it is hashed to and the part of it the noise is
+    /// seeded with, so maps of random seeds can be reproduced
+    #[serde(skip_serializing)]
+    #[arg(long, action=clap::ArgAction::SetTrue)]
+    pub seed_display: Option<bool>,
+
+    /// Check after rendering that the map continues smoothly across the edges it wraps around,
+    /// e.g. the antimeridian of `--antimeridian-wrap`. The steepest step across each seam is
+    /// compared to the steepest steps between neighboring columns or rows elsewhere, without white
+    /// noise, and rendering fails if it is more than `--check-planet-threshold` times as steep
+    #[serde(skip_serializing)]
+    #[arg(long, action=clap::ArgAction::SetTrue)]
+    pub check_planet: Option<bool>,
+
+    /// Largest allowed ratio of the steepest step across a seam to the steepest step elsewhere,
+    /// see `--check-planet`
+    #[arg(long, value_parser = check_planet_threshold_in_range)]
+    pub check_planet_threshold: Option<f64>,
+
+    /// Print information about generation after it is done. Pass twice (`-vv`) to also print
+    /// how long each phase of the render took
+    #[serde(skip_serializing)]
+    #[arg(short, long, action=clap::ArgAction::Count)]
+    pub verbose: Option<u8>,
+
+    /// Read one JSON config per line from stdin and generate an image for each. Arguments passed
+    /// on the command line override the values of every line
+    #[serde(skip)]
+    #[arg(long, action=clap::ArgAction::SetTrue)]
+    pub stdin_configs: Option<bool>,
+
+    /// Render the image again whenever the config file changes, until Ctrl-C is pressed. Errors
+    /// in the edited config are reported and the next change is awaited. With `--open` the viewer
+    /// is opened once, most viewers pick up the new image on their own
+    #[serde(skip)]
+    #[arg(long, alias = "preview-on-change", action=clap::ArgAction::SetTrue)]
+    pub watch: Option<bool>,
+
+    /// Render one image per line of this file, each line holding a name and a seed. Images are
+    /// named after the line and saved next to the output path. Blank lines and `#` comments are
+    /// skipped
+    #[serde(skip)]
+    #[arg(long)]
+    pub seed_list: Option<String>,
+
+    /// Render this many seeds counting up from the seed into a contact sheet saved at the output
+    /// path. Numeric seeds are incremented, others get `-1`, `-2`, ... appended. Cells are
+    /// labeled with their index, the seed of each index is printed
+    #[serde(skip)]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub seed_increment: Option<u32>,
+
+    /// Number of cells per row of the contact sheet. Defaults to a roughly square grid
+    #[serde(skip)]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub sheet_columns: Option<u32>,
+
+    /// Size of each cell of the contact sheet as `WIDTHxHEIGHT`
+    #[serde(skip)]
+    #[arg(long, value_parser = parse_size)]
+    pub sheet_cell_size: Option<[u32; 2]>,
+
+    /// Render the image as separate tiles of `WIDTHxHEIGHT` pixels instead of one image, saved next
+    /// to the output path as `<output>_<column>_<row>`. Post-processing and overlays are not
+    /// applied to tiles
+    #[serde(skip)]
+    #[arg(long, value_parser = parse_size)]
+    pub export_tiles: Option<[u32; 2]>,
+
+    /// Number of tiles of `--export-tiles` rendered at the same time, each on a single thread then
+    #[serde(skip)]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub parallel_tiles: Option<u32>,
+
+    /// Render this many seeds counting up from the seed at low resolution, score each against the
+    /// search targets and print the best ones instead of saving an image
+    #[serde(skip)]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub seed_space_search: Option<u32>,
+
+    /// Number of best seeds printed by the seed search
+    #[serde(skip)]
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub search_top: Option<u32>,
+
+    /// Size each seed is rendered at by the seed search as `WIDTHxHEIGHT`. Keeps the aspect ratio
+    /// of the image by default, with the longer side 128 pixels
+    #[serde(skip)]
+    #[arg(long, value_parser = parse_size)]
+    pub search_size: Option<[u32; 2]>,
+
+    /// Share of land the seed search aims for
+    #[serde(skip)]
+    #[arg(long, value_parser = level_in_range)]
+    pub target_land: Option<f64>,
+
+    /// Share of mountains and mountain tops the seed search aims for
+    #[serde(skip)]
+    #[arg(long, value_parser = level_in_range)]
+    pub target_mountains: Option<f64>,
+
+    /// Share of the image covered by the largest connected landmass the seed search aims for
+    #[serde(skip)]
+    #[arg(long, value_parser = level_in_range)]
+    pub target_landmass: Option<f64>,
+
+    /// Weights of the land, mountain and landmass targets in the score of the seed search, as
+    /// `LAND,MOUNTAINS,LANDMASS`. A weight of 0 ignores the target
+    #[serde(skip)]
+    #[arg(long, value_parser = parse_search_weights)]
+    pub search_weights: Option<[f64; 3]>,
+
+    /// Tint the area of each generation thread differently to debug how the image is split
+    #[serde(skip)]
+    #[arg(long, hide = true, action=clap::ArgAction::SetTrue)]
+    pub checkerboard_debug: Option<bool>,
+
+    /// Write a CPU flamegraph of the generation phase to this SVG file. Requires building with
+    /// the `profiling` feature
+    #[serde(skip)]
+    #[arg(long)]
+    pub profile: Option<String>,
+
+    /// Print how the pixel at `X,Y` is computed, from the noise of each octave to its color, and
+    /// exit without rendering the image. Post-processing is not applied
+    #[serde(skip)]
+    #[arg(long, value_parser = parse_position)]
+    pub inspect: Option<[u32; 2]>,
+
+    /// Compare the images `A` and `B` of equal size, write their per channel difference to `OUT`
+    /// and exit without rendering
+    #[serde(skip)]
+    #[arg(long, num_args = 3, value_names = ["A", "B", "OUT"])]
+    pub diff: Option<Vec<String>>,
+
+    /// Factor the differences written by `--diff` are multiplied by to make small ones visible
+    #[serde(skip)]
+    #[arg(long, value_parser = amplify_in_range)]
+    pub diff_amplify: Option<f64>,
+
+    /// Make identical inputs produce identical files: a fixed seed is used if none is given,
+    /// `{timestamp}` is taken from `SOURCE_DATE_EPOCH` or 0, and durations are not reported
+    #[arg(long, num_args = 0, default_missing_value = "true")]
+    pub reproducible: Option<bool>,
+
+    /// Check the config and its gradient for problems, print every one found and exit without
+    /// rendering. Exits with an error if there are any
+    #[serde(skip)]
+    #[arg(long, action=clap::ArgAction::SetTrue)]
+    pub validate_only: Option<bool>,
+
+    /// Print an example JSON config containing every field with its default value and exit
+    #[serde(skip)]
+    #[arg(long, action=clap::ArgAction::SetTrue)]
+    pub print_config_template: Option<bool>,
 }
 
 fn noise_strength_in_range(s: &str) -> Result<f64, String> {
@@ -69,34 +1035,516 @@ fn noise_strength_in_range(s: &str) -> Result<f64, String> {
     if noise_strength >= 0.0 {
         Ok(noise_strength)
     } else {
-        Err(format!("Noise strength must not be negative!"))
+        Err("Noise strength must not be negative!".to_string())
+    }
+}
+
+fn noise_magnitude_in_range(s: &str) -> Result<f64, String> {
+    let noise_magnitude = s.parse().map_err(|_| format!("{s} is not a number."))?;
+
+    if noise_magnitude >= 0.0 {
+        Ok(noise_magnitude)
+    } else {
+        Err("Noise magnitude must not be negative!".to_string())
+    }
+}
+
+fn parse_band_noise(s: &str) -> Result<BandNoise, String> {
+    let mut band_noise = BandNoise::default();
+    for entry in s.split(',') {
+        let (name, strength) = entry
+            .split_once('=')
+            .ok_or_else(|| format!("{entry} is not a band noise strength, use KIND=STRENGTH."))?;
+        let strength = strength
+            .trim()
+            .parse()
+            .map_err(|_| format!("{strength} is not a number."))?;
+        band_noise.0.insert(name.trim().to_string(), strength);
+    }
+
+    band_noise.strengths()?;
+    Ok(band_noise)
+}
+
+fn parse_tile_ids(s: &str) -> Result<TileIds, String> {
+    let mut tile_ids = TileIds::default();
+    for entry in s.split(',') {
+        let (name, id) = entry
+            .split_once('=')
+            .ok_or_else(|| format!("{entry} is not a tile ID, use KIND=ID."))?;
+        let id = id
+            .trim()
+            .parse()
+            .map_err(|_| format!("{id} is not a tile ID."))?;
+        tile_ids.0.insert(name.trim().to_string(), id);
+    }
+
+    tile_ids.ids()?;
+    Ok(tile_ids)
+}
+
+fn parse_bbox(s: &str) -> Result<[f64; 4], String> {
+    let edges = s
+        .split(',')
+        .map(|edge| {
+            edge.trim()
+                .parse()
+                .map_err(|_| format!("{edge} is not a number."))
+        })
+        .collect::<Result<Vec<f64>, String>>()?;
+    let edges: [f64; 4] = edges
+        .try_into()
+        .map_err(|_| format!("{s} is not a bounding box, use MIN_LON,MIN_LAT,MAX_LON,MAX_LAT."))?;
+
+    bounding_box(edges).check(false)?;
+    Ok(edges)
+}
+
+/// Bounding box of `--bbox` edges.
+pub fn bounding_box([min_x, min_y, max_x, max_y]: [f64; 4]) -> BoundingBox {
+    BoundingBox {
+        min_x,
+        min_y,
+        max_x,
+        max_y,
+    }
+}
+
+fn parse_crs(s: &str) -> Result<u16, String> {
+    let code = s
+        .strip_prefix("EPSG:")
+        .or_else(|| s.strip_prefix("epsg:"))
+        .unwrap_or(s);
+    match code.parse() {
+        Ok(code) if (1..32767).contains(&code) => Ok(code),
+        _ => Err(format!("{s} is not an EPSG code, use e.g. EPSG:4326.")),
+    }
+}
+
+fn parse_stamp(s: &str) -> Result<Stamp, String> {
+    let format_err = || format!("{s} is not a stamp, use SHAPE:X:Y:RADIUS:STRENGTH.");
+    let (shape, values) = s.split_once(':').ok_or_else(format_err)?;
+    let values = values
+        .split(':')
+        .map(|value| {
+            value
+                .trim()
+                .parse()
+                .map_err(|_| format!("{value} is not a number."))
+        })
+        .collect::<Result<Vec<f64>, String>>()?;
+    let [x, y, radius, strength] = values.try_into().map_err(|_| format_err())?;
+
+    let stamp = Stamp {
+        shape: shape.trim().parse()?,
+        x,
+        y,
+        radius,
+        strength,
+    };
+    check_stamp(&stamp)?;
+    Ok(stamp)
+}
+
+fn check_stamp(stamp: &Stamp) -> Result<(), String> {
+    let values = [stamp.x, stamp.y, stamp.radius, stamp.strength];
+    if values.iter().any(|value| !value.is_finite()) {
+        Err("Stamp values must be finite numbers!".to_string())
+    } else if stamp.radius <= 0.0 {
+        Err("Stamp radius must be positive!".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+fn parse_depth_curve(s: &str) -> Result<DepthCurveSpec, String> {
+    let points = s
+        .split(',')
+        .map(|point| {
+            let (height, new_height) = point
+                .split_once(':')
+                .ok_or_else(|| format!("{point} is not a curve point, use HEIGHT:NEW_HEIGHT."))?;
+            let parse = |value: &str| {
+                value
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("{value} is not a number."))
+            };
+            Ok([parse(height)?, parse(new_height)?])
+        })
+        .collect::<Result<Vec<[f64; 2]>, String>>()?;
+
+    let spec = DepthCurveSpec(points);
+    spec.curve()?;
+    Ok(spec)
+}
+
+fn parse_noise_curve(s: &str) -> Result<NoiseCurveSpec, String> {
+    let points = s
+        .split(',')
+        .map(|point| {
+            let (elevation, strength) = point
+                .split_once(':')
+                .ok_or_else(|| format!("{point} is not a curve point, use ELEVATION:STRENGTH."))?;
+            let parse = |value: &str| {
+                value
+                    .trim()
+                    .parse()
+                    .map_err(|_| format!("{value} is not a number."))
+            };
+            Ok([parse(elevation)?, parse(strength)?])
+        })
+        .collect::<Result<Vec<[f64; 2]>, String>>()?;
+
+    let spec = NoiseCurveSpec(points);
+    spec.curve()?;
+    Ok(spec)
+}
+
+fn parse_output_multiple(s: &str) -> Result<OutputList, String> {
+    s.split(',')
+        .map(|output| {
+            let (kind, path) = output
+                .split_once(':')
+                .ok_or_else(|| format!("{output} is not an output, use KIND:PATH."))?;
+            if path.is_empty() {
+                return Err(format!("The {kind} output needs a path."));
+            }
+            Ok((kind.trim().parse()?, path.to_string()))
+        })
+        .collect::<Result<Vec<_>, String>>()
+        .map(OutputList)
+}
+
+fn level_in_range(s: &str) -> Result<f64, String> {
+    let level = s.parse().map_err(|_| format!("{s} is not a number."))?;
+
+    if (0.0..=1.0).contains(&level) {
+        Ok(level)
+    } else {
+        Err("Level must be between 0 and 1!".to_string())
+    }
+}
+
+fn land_ratio_tolerance_in_range(s: &str) -> Result<f64, String> {
+    let tolerance = s.parse().map_err(|_| format!("{s} is not a number."))?;
+
+    if tolerance > 0.0 && tolerance <= 1.0 {
+        Ok(tolerance)
+    } else {
+        Err("Land ratio tolerance must be above 0 and at most 1!".to_string())
+    }
+}
+
+fn band_blend_width_in_range(s: &str) -> Result<f64, String> {
+    let band_blend_width = s.parse().map_err(|_| format!("{s} is not a number."))?;
+
+    if (0.0..=1.0).contains(&band_blend_width) {
+        Ok(band_blend_width)
+    } else {
+        Err("Band blend width must be between 0 and 1!".to_string())
     }
 }
 
-fn base_height_in_range(s: &str) -> Result<f64, String> {
-    let base_height = s.parse().map_err(|_| format!("{s} is not a number."))?;
+fn blend_in_range(s: &str) -> Result<f64, String> {
+    let blend = s.parse().map_err(|_| format!("{s} is not a number."))?;
 
-    if base_height >= 0.0 && base_height <= 1.0 {
-        Ok(base_height)
+    if (0.0..=1.0).contains(&blend) {
+        Ok(blend)
     } else {
-        Err(format!("Base height must be between 0 and 1!"))
+        Err("Blend must be between 0 and 1!".to_string())
     }
 }
 
+fn opacity_in_range(s: &str) -> Result<f64, String> {
+    let opacity = s.parse().map_err(|_| format!("{s} is not a number."))?;
+
+    if (0.0..=1.0).contains(&opacity) {
+        Ok(opacity)
+    } else {
+        Err("Opacity must be between 0 and 1!".to_string())
+    }
+}
+
+fn contrast_in_range(s: &str) -> Result<f64, String> {
+    let contrast = s.parse().map_err(|_| format!("{s} is not a number."))?;
+
+    if contrast >= 0.0 {
+        Ok(contrast)
+    } else {
+        Err("Contrast must not be negative!".to_string())
+    }
+}
+
+fn peak_rarity_in_range(s: &str) -> Result<f64, String> {
+    let peak_rarity = s.parse().map_err(|_| format!("{s} is not a number."))?;
+
+    if peak_rarity >= 0.0 {
+        Ok(peak_rarity)
+    } else {
+        Err("Peak rarity must not be negative!".to_string())
+    }
+}
+
+fn contour_interval_in_range(s: &str) -> Result<f64, String> {
+    let interval = s.parse().map_err(|_| format!("{s} is not a number."))?;
+
+    if interval > 0.0 && interval <= 1.0 {
+        Ok(interval)
+    } else {
+        Err("Contour interval must be greater than 0 and at most 1!".to_string())
+    }
+}
+
+fn contour_width_in_range(s: &str) -> Result<f64, String> {
+    let width = s.parse().map_err(|_| format!("{s} is not a number."))?;
+
+    if width > 0.0 {
+        Ok(width)
+    } else {
+        Err("Contour width must be positive!".to_string())
+    }
+}
+
+fn svg_simplify_in_range(s: &str) -> Result<f64, String> {
+    let tolerance = s.parse().map_err(|_| format!("{s} is not a number."))?;
+
+    if tolerance >= 0.0 {
+        Ok(tolerance)
+    } else {
+        Err("SVG simplification must not be negative!".to_string())
+    }
+}
+
+fn check_planet_threshold_in_range(s: &str) -> Result<f64, String> {
+    let threshold = s.parse().map_err(|_| format!("{s} is not a number."))?;
+
+    if threshold > 0.0 {
+        Ok(threshold)
+    } else {
+        Err("Seam threshold must be positive!".to_string())
+    }
+}
+
+fn octave_jitter_in_range(s: &str) -> Result<f64, String> {
+    let octave_jitter = s.parse().map_err(|_| format!("{s} is not a number."))?;
+
+    if octave_jitter >= 0.0 {
+        Ok(octave_jitter)
+    } else {
+        Err("Octave jitter must not be negative!".to_string())
+    }
+}
+
+fn bias_in_range(s: &str) -> Result<f64, String> {
+    let bias = s.parse().map_err(|_| format!("{s} is not a number."))?;
+
+    if bias > 0.0 {
+        Ok(bias)
+    } else {
+        Err("Bias must be greater than 0!".to_string())
+    }
+}
+
+fn amplify_in_range(s: &str) -> Result<f64, String> {
+    let amplify = s.parse().map_err(|_| format!("{s} is not a number."))?;
+
+    if amplify > 0.0 {
+        Ok(amplify)
+    } else {
+        Err("Amplification must be greater than 0!".to_string())
+    }
+}
+
+fn feature_scale_in_range(s: &str) -> Result<f64, String> {
+    let feature_scale = s.parse().map_err(|_| format!("{s} is not a number."))?;
+
+    if feature_scale > 0.0 {
+        Ok(feature_scale)
+    } else {
+        Err("Feature scale must be greater than 0!".to_string())
+    }
+}
+
+fn timeout_in_range(s: &str) -> Result<f64, String> {
+    let timeout = s.parse().map_err(|_| format!("{s} is not a number."))?;
+
+    if timeout > 0.0 {
+        Ok(timeout)
+    } else {
+        Err("Timeout must be greater than 0!".to_string())
+    }
+}
+
+/// Whether images can be saved as `format` with the format features this binary is built with.
+pub fn format_enabled(format: ImageFormat) -> bool {
+    match format {
+        #[cfg(feature = "png")]
+        ImageFormat::Png => true,
+        #[cfg(feature = "jpeg")]
+        ImageFormat::Jpeg => true,
+        #[cfg(feature = "tiff")]
+        ImageFormat::Tiff => true,
+        #[cfg(feature = "webp")]
+        ImageFormat::WebP => true,
+        #[cfg(feature = "all-formats")]
+        _ => format.writing_enabled(),
+        #[cfg(not(feature = "all-formats"))]
+        _ => false,
+    }
+}
+
+/// Checks that images with the file extension `s` can be saved.
+fn writable_extension(s: &str) -> Result<ImageFormat, String> {
+    match ImageFormat::from_extension(s) {
+        Some(format) if format_enabled(format) => Ok(format),
+        Some(_) => Err(format!(
+            "Saving {s} images is not enabled in this build, see the format features."
+        )),
+        None => Err(format!("{s} is not a supported image format.")),
+    }
+}
+
+fn parse_format(s: &str) -> Result<String, String> {
+    writable_extension(s).map(|_| s.to_ascii_lowercase())
+}
+
+fn parse_name_template(s: &str) -> Result<String, String> {
+    let mut rest = s;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .ok_or_else(|| format!("{s} contains an unclosed placeholder."))?;
+        let placeholder = &rest[start..=start + end];
+        if !NAME_PLACEHOLDERS.contains(&placeholder) {
+            return Err(format!(
+                "{placeholder} is not a placeholder, use one of {}.",
+                NAME_PLACEHOLDERS.join(", ")
+            ));
+        }
+        rest = &rest[start + end + 1..];
+    }
+
+    if s.contains(['/', '\\']) {
+        return Err("Name template must be a file name, not a path!".to_string());
+    }
+
+    match std::path::Path::new(s).extension() {
+        Some(extension) => writable_extension(&extension.to_string_lossy()).map(|_| s.to_string()),
+        None => Err(format!("{s} does not end in a supported image extension.")),
+    }
+}
+
+fn parse_aspect(s: &str) -> Result<[u32; 2], String> {
+    let invalid = || format!("{s} is not an aspect ratio, use W:H.");
+
+    let (width, height) = s.split_once(':').ok_or_else(invalid)?;
+    let width: u32 = width.trim().parse().map_err(|_| invalid())?;
+    let height: u32 = height.trim().parse().map_err(|_| invalid())?;
+
+    if width == 0 || height == 0 {
+        return Err("Aspect ratio must not contain 0!".to_string());
+    }
+
+    Ok([width, height])
+}
+
+fn parse_position(s: &str) -> Result<[u32; 2], String> {
+    let invalid = || format!("{s} is not a position, use X,Y.");
+
+    let (x, y) = s.split_once(',').ok_or_else(invalid)?;
+    let x: u32 = x.trim().parse().map_err(|_| invalid())?;
+    let y: u32 = y.trim().parse().map_err(|_| invalid())?;
+
+    Ok([x, y])
+}
+
+fn parse_size(s: &str) -> Result<[u32; 2], String> {
+    let invalid = || format!("{s} is not a size, use WIDTHxHEIGHT.");
+
+    let (width, height) = s.split_once(['x', 'X']).ok_or_else(invalid)?;
+    let width: u32 = width.trim().parse().map_err(|_| invalid())?;
+    let height: u32 = height.trim().parse().map_err(|_| invalid())?;
+
+    if width == 0 || height == 0 {
+        return Err("Size must be at least 1x1!".to_string());
+    }
+
+    Ok([width, height])
+}
+
+fn parse_search_weights(s: &str) -> Result<[f64; 3], String> {
+    let weights = s
+        .split(',')
+        .map(|weight| {
+            let weight: f64 = weight
+                .trim()
+                .parse()
+                .map_err(|_| format!("{weight} is not a number."))?;
+            if weight.is_finite() && weight >= 0.0 {
+                Ok(weight)
+            } else {
+                Err("Search weights must not be negative!".to_string())
+            }
+        })
+        .collect::<Result<Vec<f64>, String>>()?;
+
+    weights
+        .try_into()
+        .map_err(|_| format!("{s} are not search weights, use LAND,MOUNTAINS,LANDMASS."))
+}
+
+fn parse_depth_tint(s: &str) -> Result<DepthTint, String> {
+    let (color, strength) = s
+        .rsplit_once(':')
+        .ok_or_else(|| format!("{s} is not a depth tint, use COLOR:STRENGTH."))?;
+
+    let strength: f64 = strength
+        .parse()
+        .map_err(|_| format!("{strength} is not a number."))?;
+    if !(0.0..=1.0).contains(&strength) {
+        return Err("Depth tint strength must be between 0.0 and 1.0!".to_string());
+    }
+
+    Ok(DepthTint {
+        color: parse_color(color)?,
+        strength,
+    })
+}
+
+fn parse_color(s: &str) -> Result<[u8; 3], String> {
+    let invalid = || format!("{s} is not a color, use r,g,b or #rrggbb.");
+
+    if let Some(hex) = s.strip_prefix('#') {
+        if hex.len() != 6 || !hex.is_ascii() {
+            return Err(invalid());
+        }
+        let channel =
+            |idx: usize| u8::from_str_radix(&hex[idx..idx + 2], 16).map_err(|_| invalid());
+        return Ok([channel(0)?, channel(2)?, channel(4)?]);
+    }
+
+    let channels = s
+        .split(',')
+        .map(|channel| channel.trim().parse::<u8>().map_err(|_| invalid()))
+        .collect::<Result<Vec<u8>, String>>()?;
+    channels.try_into().map_err(|_| invalid())
+}
+
 fn thread_count_in_range(s: &str) -> Result<usize, String> {
     let cpu_count = s.parse().map_err(|_| format!("{s} is not a number."))?;
 
-    if cpu_count >= 1 && cpu_count <= 256 {
+    if (1..=256).contains(&cpu_count) {
         Ok(cpu_count)
     } else {
-        Err(format!("Noise strength must not be negative!"))
+        Err("Thread count must be between 1 and 256!".to_string())
     }
 }
 
 #[allow(dead_code)]
 impl Config {
     pub fn new() -> Result<Self, std::io::Error> {
-        let config: Config;
+        let mut config: Config;
 
         let config_args = Config::parse();
 
@@ -104,22 +1552,239 @@ impl Config {
             let path = std::path::PathBuf::from(config_path);
 
             if path.exists() {
-                let config_file = File::open(path)?;
+                let config_file = File::open(&path)?;
                 let config_reader = BufReader::new(config_file);
-                let config_json: Config =
-                    serde_json::from_reader(config_reader).map_err(|err| {
-                        std::io::Error::new(std::io::ErrorKind::Other, format!("{err:?}"))
-                    })?;
-
-                config = config_args.merge_with_defaults(&config_json);
+                match serde_json::from_reader::<_, Config>(config_reader) {
+                    Ok(config_json) => config = config_args.merge_with_defaults(&config_json),
+                    Err(err) if config_args.lenient_config.unwrap() => {
+                        eprintln!(
+                            "Ignoring malformed config file '{path:?}', using arguments only: {err}"
+                        );
+                        config = config_args.merge_with_defaults(&config_args);
+                    }
+                    Err(err) => return Err(std::io::Error::other(format!("{err:?}"))),
+                }
             } else {
-                println!("Provided config file does not exist: '{path:?}'");
+                eprintln!("Provided config file does not exist: '{path:?}'");
                 config = config_args.merge_with_defaults(&config_args);
             }
         } else {
             config = config_args.merge_with_defaults(&config_args);
         }
 
+        config.resolve_aspect()?;
+        // problems are reported by the caller instead, all of them at once
+        if !config.validate_only.unwrap() {
+            config.validate()?;
+        }
+
+        Ok(config)
+    }
+
+    /// Sets width and height from `aspect` and `long_edge`, if both are given.
+    fn resolve_aspect(&mut self) -> Result<(), std::io::Error> {
+        let ([aspect_width, aspect_height], long_edge) = match (self.aspect, self.long_edge) {
+            (Some(aspect), Some(long_edge)) => (aspect, long_edge),
+            (None, None) => return Ok(()),
+            _ => {
+                return Err(std::io::Error::new(
+                    std::io::ErrorKind::InvalidInput,
+                    "aspect and long_edge must be given together",
+                ))
+            }
+        };
+
+        let short_edge =
+            |long: u32, short: u32| (long_edge as f64 * short as f64 / long as f64).round() as u32;
+        let (width, height) = if aspect_width >= aspect_height {
+            (long_edge, short_edge(aspect_width, aspect_height))
+        } else {
+            (short_edge(aspect_height, aspect_width), long_edge)
+        };
+
+        if width == 0 || height == 0 {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "Aspect ratio {aspect_width}:{aspect_height} with a long edge of {long_edge} results in an image of {width}x{height} pixels"
+                ),
+            ));
+        }
+
+        self.width = Some(width);
+        self.height = Some(height);
+        Ok(())
+    }
+
+    /// Checks values which can only be validated in combination with each other. Fails with the
+    /// first problem found, see [`Config::problems`].
+    pub fn validate(&self) -> Result<(), std::io::Error> {
+        match self.problems().into_iter().next() {
+            Some(problem) => Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                problem,
+            )),
+            None => Ok(()),
+        }
+    }
+
+    /// Every problem of values which can only be validated in combination with each other.
+    pub fn problems(&self) -> Vec<String> {
+        let mut problems = Vec::new();
+
+        if let Some(bands) = &self.gradient {
+            if bands.is_empty() {
+                problems.push("The gradient must contain at least one band".to_string());
+            }
+            for band in bands
+                .iter()
+                .filter(|band| !(0.0..=1.0).contains(&band.limit))
+            {
+                problems.push(format!("Band limit {} must be between 0 and 1", band.limit));
+            }
+        }
+
+        if let Some(Err(err)) = self.band_noise_strength.as_ref().map(BandNoise::strengths) {
+            problems.push(err);
+        }
+
+        if let Some(Err(err)) = self
+            .noise_strength_curve
+            .as_ref()
+            .map(NoiseCurveSpec::curve)
+        {
+            problems.push(err);
+        }
+
+        for stamp in self.stamps.iter().flatten() {
+            if let Err(err) = check_stamp(stamp) {
+                problems.push(err);
+            }
+        }
+
+        if let Some(Err(err)) = self.depth_curve.as_ref().map(DepthCurveSpec::curve) {
+            problems.push(err);
+        }
+
+        for tile_ids in [&self.tile_ids, &self.tile_transitions]
+            .into_iter()
+            .flatten()
+        {
+            if let Err(err) = tile_ids.ids() {
+                problems.push(err);
+            }
+        }
+
+        if let Some(path) = &self.tileset_index {
+            if TileFormat::from_path(path).is_none() {
+                problems.push(format!("{path} must end with .csv or .tmx"));
+            }
+        }
+
+        // the geographic ranges depend on the CRS, which may be given after the box
+        if let Some(bbox) = self.bbox {
+            let crs = self.crs.unwrap_or(DEFAULT_CRS);
+            if let Err(err) = bounding_box(bbox).check(export::is_geographic(crs)) {
+                problems.push(err);
+            }
+        }
+        let projected = !export::is_geographic(self.crs.unwrap_or(DEFAULT_CRS));
+        if self.geotiff.is_some() && projected && self.bbox.is_none() {
+            problems
+                .push("--geotiff with a projected --crs needs a --bbox in its units".to_string());
+        }
+
+        let wraps = self.antimeridian_wrap.unwrap_or(DEFAULT_ANTIMERIDIAN_WRAP)
+            || self.seamless.unwrap_or(DEFAULT_SEAMLESS)
+            || self.world_size.is_some();
+        if self.check_planet.unwrap_or(DEFAULT_CHECK_PLANET) && !wraps {
+            problems.push(
+                "--check-planet needs a wrapping map, use --antimeridian-wrap, --seamless or \
+                 --world-size"
+                    .to_string(),
+            );
+        }
+
+        // values of config files skip the argument parser, a zero interval would never end
+        if let Some(Err(err)) = self
+            .contour_interval
+            .map(|interval| contour_interval_in_range(&interval.to_string()))
+        {
+            problems.push(err);
+        }
+
+        if let (Some(width), Some(height)) = (self.width, self.height) {
+            let aa = self.aa.unwrap_or(DEFAULT_AA);
+            let (sample_width, sample_height) =
+                (width.saturating_mul(aa), height.saturating_mul(aa));
+            if generator::buffer_len(sample_width, sample_height).is_none() {
+                let err = generator::GenerationError::TooLarge {
+                    width: sample_width,
+                    height: sample_height,
+                };
+                problems.push(err.to_string());
+            }
+        }
+
+        problems
+    }
+
+    /// Width and height of the generated image, taking the preview scale into account.
+    pub fn dimensions(&self) -> (u32, u32) {
+        let scale = self.preview_scale.unwrap_or(DEFAULT_PREVIEW_SCALE);
+        (
+            (self.width.unwrap() / scale).max(1),
+            (self.height.unwrap() / scale).max(1),
+        )
+    }
+
+    /// Size after which the world wraps around, taking the preview scale into account.
+    pub fn world_dimensions(&self) -> Option<(u32, u32)> {
+        let scale = self.preview_scale.unwrap_or(DEFAULT_PREVIEW_SCALE);
+        self.world_size
+            .map(|[width, height]| ((width / scale).max(1), (height / scale).max(1)))
+    }
+
+    /// Size of the largest features, taking the preview scale into account.
+    pub fn scaled_feature_scale(&self) -> Option<f64> {
+        let scale = self.preview_scale.unwrap_or(DEFAULT_PREVIEW_SCALE);
+        self.feature_scale
+            .map(|feature_scale| feature_scale / scale as f64)
+    }
+
+    /// Config with every field set to its default. The seed is left empty, so a random one is
+    /// picked when the template is used as is.
+    pub fn template() -> Self {
+        let empty = Config::parse_from(["generate"]);
+        let mut config = empty.merge_with_defaults(&empty);
+        config.seed = None;
+        config
+    }
+
+    /// Creates a config from a single JSON document, merged with the command line arguments and
+    /// defaults the same way a config file is.
+    pub fn from_json(json: &str) -> Result<Self, std::io::Error> {
+        let config_args = Config::parse();
+        let config_json: Config =
+            serde_json::from_str(json).map_err(|err| std::io::Error::other(format!("{err:?}")))?;
+
+        let mut config = config_args.merge_with_defaults(&config_json);
+        config.resolve_aspect()?;
+        config.validate()?;
+
+        Ok(config)
+    }
+
+    /// Creates a config from the given command line arguments merged with the defaults, like
+    /// [`Config::new`] without a config file.
+    #[cfg(test)]
+    pub fn from_args(args: &[&str]) -> Result<Self, std::io::Error> {
+        let config_args =
+            Config::parse_from(std::iter::once("generate").chain(args.iter().copied()));
+        let mut config = config_args.merge_with_defaults(&config_args);
+        config.resolve_aspect()?;
+        config.validate()?;
+
         Ok(config)
     }
 
@@ -130,11 +1795,203 @@ impl Config {
                 .or(other.dump_config.or(Some(DEFAULT_DUMP_CONFIG))),
             width: self.width.or(other.width.or(None)),
             height: self.height.or(other.height.or(None)),
+            preview_scale: self.preview_scale.or(other.preview_scale.or(None)),
             noise_strength: self.noise_strength.or(other.noise_strength.or(None)),
+            noise_granularity: self.noise_granularity.or(other.noise_granularity.or(None)),
+            noise_magnitude: self.noise_magnitude.or(other.noise_magnitude.or(None)),
+            noise_shape: self.noise_shape.or(other.noise_shape.or(None)),
             base_level: self.base_level.or(other.base_level.or(None)),
+            contrast: self.contrast.or(other.contrast.or(None)),
+            bias: self.bias.or(other.bias.or(None)),
+            color_ramp: self.color_ramp.or(other.color_ramp.or(None)),
+            flatten_water: self.flatten_water.or(other.flatten_water.or(None)),
+            sea_level: self.sea_level.or(other.sea_level.or(None)),
+            seamless: self.seamless.or(other.seamless.or(None)),
+            aa: self.aa.or(other.aa.or(None)),
             seed: self.seed.clone().or(other.seed.clone().or(None)),
+            seed_b: self.seed_b.clone().or(other.seed_b.clone().or(None)),
+            blend: self.blend.or(other.blend.or(None)),
             thread_count: self.thread_count.or(other.thread_count.or(None)),
             verbose: self.verbose.or(other.verbose.or(None)),
+            stdin_configs: self.stdin_configs.or(other.stdin_configs.or(None)),
+            print_config_template: self
+                .print_config_template
+                .or(other.print_config_template.or(None)),
+            obj: self.obj.clone().or(other.obj.clone().or(None)),
+            mesh_step: self.mesh_step.or(other.mesh_step.or(None)),
+            z_scale: self.z_scale.or(other.z_scale.or(None)),
+            raw: self.raw.clone().or(other.raw.clone().or(None)),
+            raw_f64: self.raw_f64.clone().or(other.raw_f64.clone().or(None)),
+            stats_json: self
+                .stats_json
+                .clone()
+                .or(other.stats_json.clone().or(None)),
+            timeout: self.timeout.or(other.timeout.or(None)),
+            oceans_only_connected: self
+                .oceans_only_connected
+                .or(other.oceans_only_connected.or(None)),
+            lake_color: self.lake_color.or(other.lake_color.or(None)),
+            rotate: self.rotate.or(other.rotate.or(None)),
+            open: self.open.or(other.open.or(None)),
+            palette_from_image: self
+                .palette_from_image
+                .clone()
+                .or(other.palette_from_image.clone().or(None)),
+            seed_list: self.seed_list.clone().or(other.seed_list.clone().or(None)),
+            world_size: self.world_size.or(other.world_size.or(None)),
+            depth_tint: self.depth_tint.or(other.depth_tint.or(None)),
+            checkerboard_debug: self
+                .checkerboard_debug
+                .or(other.checkerboard_debug.or(None)),
+            mirror: self.mirror.or(other.mirror.or(None)),
+            format: self.format.clone().or(other.format.clone().or(None)),
+            snow_line: self.snow_line.or(other.snow_line.or(None)),
+            snow_falloff: self.snow_falloff.or(other.snow_falloff.or(None)),
+            lenient_config: self.lenient_config.or(other.lenient_config.or(None)),
+            color_quantize: self.color_quantize.or(other.color_quantize.or(None)),
+            profile: self.profile.clone().or(other.profile.clone().or(None)),
+            roughness: self.roughness.or(other.roughness.or(None)),
+            aspect: self.aspect.or(other.aspect.or(None)),
+            long_edge: self.long_edge.or(other.long_edge.or(None)),
+            inspect: self.inspect.or(other.inspect.or(None)),
+            equal_area_projection: self
+                .equal_area_projection
+                .or(other.equal_area_projection.or(None)),
+            diff: self.diff.clone().or(other.diff.clone().or(None)),
+            diff_amplify: self.diff_amplify.or(other.diff_amplify.or(None)),
+            band_report: self.band_report.or(other.band_report.or(None)),
+            output_dir: self
+                .output_dir
+                .clone()
+                .or(other.output_dir.clone().or(None)),
+            name_template: self
+                .name_template
+                .clone()
+                .or(other.name_template.clone().or(None)),
+            moisture_threshold: self
+                .moisture_threshold
+                .or(other.moisture_threshold.or(None)),
+            dry_color: self.dry_color.or(other.dry_color.or(None)),
+            wet_color: self.wet_color.or(other.wet_color.or(None)),
+            seed_increment: self.seed_increment.or(other.seed_increment.or(None)),
+            sheet_columns: self.sheet_columns.or(other.sheet_columns.or(None)),
+            sheet_cell_size: self.sheet_cell_size.or(other.sheet_cell_size.or(None)),
+            voronoi_regions: self.voronoi_regions.or(other.voronoi_regions.or(None)),
+            region_border_color: self
+                .region_border_color
+                .or(other.region_border_color.or(None)),
+            regions_include_water: self
+                .regions_include_water
+                .or(other.regions_include_water.or(None)),
+            flat_shading_levels: self
+                .flat_shading_levels
+                .or(other.flat_shading_levels.or(None)),
+            feature_scale: self.feature_scale.or(other.feature_scale.or(None)),
+            histogram: self.histogram.clone().or(other.histogram.clone().or(None)),
+            histogram_buckets: self.histogram_buckets.or(other.histogram_buckets.or(None)),
+            bake_gradient: self.bake_gradient.or(other.bake_gradient.or(None)),
+            lut_size: self.lut_size.or(other.lut_size.or(None)),
+            reproducible: self.reproducible.or(other.reproducible.or(None)),
+            band_noise_strength: self
+                .band_noise_strength
+                .clone()
+                .or(other.band_noise_strength.clone().or(None)),
+            tile_preview: self
+                .tile_preview
+                .clone()
+                .or(other.tile_preview.clone().or(None)),
+            gradient: self.gradient.clone().or(other.gradient.clone().or(None)),
+            fast: self.fast.or(other.fast.or(None)),
+            grid_overlay: self.grid_overlay.or(other.grid_overlay.or(None)),
+            grid_color: self.grid_color.or(other.grid_color.or(None)),
+            grid_opacity: self.grid_opacity.or(other.grid_opacity.or(None)),
+            grid_labels: self.grid_labels.or(other.grid_labels.or(None)),
+            island_shape: self.island_shape.or(other.island_shape.or(None)),
+            export_bands: self
+                .export_bands
+                .clone()
+                .or(other.export_bands.clone().or(None)),
+            seed_space_search: self.seed_space_search.or(other.seed_space_search.or(None)),
+            search_top: self.search_top.or(other.search_top.or(None)),
+            search_size: self.search_size.or(other.search_size.or(None)),
+            target_land: self.target_land.or(other.target_land.or(None)),
+            target_mountains: self.target_mountains.or(other.target_mountains.or(None)),
+            target_landmass: self.target_landmass.or(other.target_landmass.or(None)),
+            search_weights: self.search_weights.or(other.search_weights.or(None)),
+            palette: self.palette.or(other.palette.or(None)),
+            downscale_filter: self.downscale_filter.or(other.downscale_filter.or(None)),
+            export_tiles: self.export_tiles.or(other.export_tiles.or(None)),
+            parallel_tiles: self.parallel_tiles.or(other.parallel_tiles.or(None)),
+            theme: self.theme.or(other.theme.or(None)),
+            noise_strength_curve: self
+                .noise_strength_curve
+                .clone()
+                .or(other.noise_strength_curve.clone().or(None)),
+            validate_only: self.validate_only.or(other.validate_only.or(None)),
+            antimeridian_wrap: self.antimeridian_wrap.or(other.antimeridian_wrap.or(None)),
+            color_interpolation_across_all_bands: self
+                .color_interpolation_across_all_bands
+                .or(other.color_interpolation_across_all_bands.or(None)),
+            peak_rarity: self.peak_rarity.or(other.peak_rarity.or(None)),
+            output_multiple: self
+                .output_multiple
+                .clone()
+                .or(other.output_multiple.clone().or(None)),
+            min_thread_chunk: self.min_thread_chunk.or(other.min_thread_chunk.or(None)),
+            input_heightmap: self
+                .input_heightmap
+                .clone()
+                .or(other.input_heightmap.clone().or(None)),
+            seed_display: self.seed_display.or(other.seed_display.or(None)),
+            octave_blend: self.octave_blend.or(other.octave_blend.or(None)),
+            limit_memory: self.limit_memory.or(other.limit_memory.or(None)),
+            watch: self.watch.or(other.watch.or(None)),
+            checkpoint_heightmap: self
+                .checkpoint_heightmap
+                .clone()
+                .or(other.checkpoint_heightmap.clone().or(None)),
+            octave_jitter: self.octave_jitter.or(other.octave_jitter.or(None)),
+            emit_svg: self.emit_svg.clone().or(other.emit_svg.clone().or(None)),
+            contour_interval: self.contour_interval.or(other.contour_interval.or(None)),
+            contour_color: self.contour_color.or(other.contour_color.or(None)),
+            contour_width: self.contour_width.or(other.contour_width.or(None)),
+            svg_fill_bands: self.svg_fill_bands.or(other.svg_fill_bands.or(None)),
+            svg_simplify: self.svg_simplify.or(other.svg_simplify.or(None)),
+            tileset_index: self
+                .tileset_index
+                .clone()
+                .or(other.tileset_index.clone().or(None)),
+            tile_ids: self.tile_ids.clone().or(other.tile_ids.clone().or(None)),
+            tile_transitions: self
+                .tile_transitions
+                .clone()
+                .or(other.tile_transitions.clone().or(None)),
+            tileset_source: self
+                .tileset_source
+                .clone()
+                .or(other.tileset_source.clone().or(None)),
+            tileset_tile_size: self.tileset_tile_size.or(other.tileset_tile_size.or(None)),
+            depth_curve: self
+                .depth_curve
+                .clone()
+                .or(other.depth_curve.clone().or(None)),
+            check_planet: self.check_planet.or(other.check_planet.or(None)),
+            check_planet_threshold: self
+                .check_planet_threshold
+                .or(other.check_planet_threshold.or(None)),
+            prewarm_threadpool: self
+                .prewarm_threadpool
+                .or(other.prewarm_threadpool.or(None)),
+            band_blend_width: self.band_blend_width.or(other.band_blend_width.or(None)),
+            geotiff: self.geotiff.clone().or(other.geotiff.clone().or(None)),
+            bbox: self.bbox.or(other.bbox.or(None)),
+            crs: self.crs.or(other.crs.or(None)),
+            land_ratio_target: self.land_ratio_target.or(other.land_ratio_target.or(None)),
+            land_ratio_tolerance: self
+                .land_ratio_tolerance
+                .or(other.land_ratio_tolerance.or(None)),
+            stamps: self.stamps.clone().or(other.stamps.clone().or(None)),
+            jitter_rng: self.jitter_rng.or(other.jitter_rng.or(None)),
             output_path: self
                 .output_path
                 .clone()
@@ -147,25 +2004,320 @@ impl Config {
     }
 
     fn merge_with_defaults(&self, other: &Config) -> Self {
+        // the theme ranks below the arguments and the config file, but above the defaults
+        let themed;
+        let other = match self.theme.or(other.theme) {
+            Some(theme) => {
+                themed = other.merge(&theme.overrides());
+                &themed
+            }
+            None => other,
+        };
+        let reproducible = self
+            .reproducible
+            .or(other.reproducible)
+            .unwrap_or(DEFAULT_REPRODUCIBLE);
+
         Config {
             dump_config: self
                 .dump_config
                 .or(other.dump_config.or(Some(DEFAULT_DUMP_CONFIG))),
             width: self.width.or(other.width.or(Some(DEFAULT_WIDTH))),
             height: self.height.or(other.height.or(Some(DEFAULT_HEIGHT))),
+            preview_scale: self
+                .preview_scale
+                .or(other.preview_scale.or(Some(DEFAULT_PREVIEW_SCALE))),
             noise_strength: self
                 .noise_strength
                 .or(other.noise_strength.or(Some(DEFAULT_NOISE_STRENGTH))),
+            noise_granularity: self.noise_granularity.or(other
+                .noise_granularity
+                .or(Some(generator::DEFAULT_NOISE_GRANULARITY))),
+            noise_magnitude: self
+                .noise_magnitude
+                .or(other.noise_magnitude.or(Some(generator::WHITE_NOISE_RANGE))),
+            noise_shape: self
+                .noise_shape
+                .or(other.noise_shape.or(Some(NoiseShape::Uniform))),
             base_level: self
                 .base_level
                 .or(other.base_level.or(Some(DEFAULT_HEIGHT_OFFSET))),
-            seed: self.seed.clone().or(other.seed.clone().or(Some(
-                Alphanumeric.sample_string(&mut rand::thread_rng(), 32),
-            ))),
+            contrast: self.contrast.or(other.contrast.or(Some(DEFAULT_CONTRAST))),
+            bias: self.bias.or(other.bias.or(Some(DEFAULT_BIAS))),
+            color_ramp: self.color_ramp.or(other.color_ramp.or(None)),
+            flatten_water: self
+                .flatten_water
+                .or(other.flatten_water.or(Some(DEFAULT_FLATTEN_WATER))),
+            sea_level: self
+                .sea_level
+                .or(other.sea_level.or(Some(postprocess::DEFAULT_SEA_LEVEL))),
+            seamless: self.seamless.or(other.seamless.or(Some(DEFAULT_SEAMLESS))),
+            aa: self.aa.or(other.aa.or(Some(DEFAULT_AA))),
+            seed: self
+                .seed
+                .clone()
+                .or(other.seed.clone().or(Some(if reproducible {
+                    REPRODUCIBLE_SEED.to_string()
+                } else {
+                    Alphanumeric.sample_string(&mut rand::thread_rng(), 32)
+                }))),
+            seed_b: self.seed_b.clone().or(other.seed_b.clone().or(None)),
+            blend: self.blend.or(other.blend.or(Some(DEFAULT_BLEND))),
             verbose: self.verbose.or(other.verbose.or(Some(DEFAULT_VERBOSE))),
-            thread_count: self
+            stdin_configs: self
+                .stdin_configs
+                .or(other.stdin_configs.or(Some(DEFAULT_STDIN_CONFIGS))),
+            print_config_template: self
+                .print_config_template
+                .or(other.print_config_template.or(Some(false))),
+            obj: self.obj.clone().or(other.obj.clone().or(None)),
+            mesh_step: self.mesh_step.or(other.mesh_step.or(None)),
+            z_scale: self.z_scale.or(other.z_scale.or(Some(DEFAULT_Z_SCALE))),
+            raw: self.raw.clone().or(other.raw.clone().or(None)),
+            raw_f64: self.raw_f64.clone().or(other.raw_f64.clone().or(None)),
+            stats_json: self
+                .stats_json
+                .clone()
+                .or(other.stats_json.clone().or(None)),
+            thread_count: self.thread_count.or(other
                 .thread_count
-                .or(other.thread_count.or(Some(num_cpus::get() - 1))),
+                .or(Some(num_cpus::get().saturating_sub(1).max(1)))),
+            timeout: self.timeout.or(other.timeout.or(None)),
+            oceans_only_connected: self.oceans_only_connected.or(other
+                .oceans_only_connected
+                .or(Some(DEFAULT_OCEANS_ONLY_CONNECTED))),
+            lake_color: self
+                .lake_color
+                .or(other.lake_color.or(Some(DEFAULT_LAKE_COLOR))),
+            rotate: self.rotate.or(other.rotate.or(Some(DEFAULT_ROTATE))),
+            open: self.open.or(other.open.or(Some(DEFAULT_OPEN))),
+            palette_from_image: self
+                .palette_from_image
+                .clone()
+                .or(other.palette_from_image.clone().or(None)),
+            seed_list: self.seed_list.clone().or(other.seed_list.clone().or(None)),
+            world_size: self.world_size.or(other.world_size.or(None)),
+            depth_tint: self.depth_tint.or(other.depth_tint.or(None)),
+            checkerboard_debug: self
+                .checkerboard_debug
+                .or(other.checkerboard_debug.or(Some(false))),
+            mirror: self.mirror.or(other.mirror.or(Some(Mirror::None))),
+            format: self.format.clone().or(other.format.clone().or(None)),
+            snow_line: self.snow_line.or(other.snow_line.or(None)),
+            snow_falloff: self
+                .snow_falloff
+                .or(other.snow_falloff.or(Some(DEFAULT_SNOW_FALLOFF))),
+            lenient_config: self.lenient_config.or(other.lenient_config.or(Some(false))),
+            color_quantize: self.color_quantize.or(other.color_quantize.or(None)),
+            profile: self.profile.clone().or(other.profile.clone().or(None)),
+            roughness: self.roughness.or(other.roughness.or(None)),
+            aspect: self.aspect.or(other.aspect.or(None)),
+            long_edge: self.long_edge.or(other.long_edge.or(None)),
+            inspect: self.inspect.or(other.inspect.or(None)),
+            equal_area_projection: self.equal_area_projection.or(other
+                .equal_area_projection
+                .or(Some(DEFAULT_EQUAL_AREA_PROJECTION))),
+            diff: self.diff.clone().or(other.diff.clone().or(None)),
+            diff_amplify: self
+                .diff_amplify
+                .or(other.diff_amplify.or(Some(DEFAULT_DIFF_AMPLIFY))),
+            band_report: self
+                .band_report
+                .or(other.band_report.or(Some(DEFAULT_BAND_REPORT))),
+            output_dir: self
+                .output_dir
+                .clone()
+                .or(other.output_dir.clone().or(None)),
+            name_template: self
+                .name_template
+                .clone()
+                .or(other.name_template.clone().or(None)),
+            moisture_threshold: self
+                .moisture_threshold
+                .or(other.moisture_threshold.or(None)),
+            dry_color: self
+                .dry_color
+                .or(other.dry_color.or(Some(DEFAULT_DRY_COLOR))),
+            wet_color: self
+                .wet_color
+                .or(other.wet_color.or(Some(DEFAULT_WET_COLOR))),
+            seed_increment: self.seed_increment.or(other.seed_increment.or(None)),
+            sheet_columns: self.sheet_columns.or(other.sheet_columns.or(None)),
+            sheet_cell_size: self
+                .sheet_cell_size
+                .or(other.sheet_cell_size.or(Some(DEFAULT_SHEET_CELL_SIZE))),
+            voronoi_regions: self.voronoi_regions.or(other.voronoi_regions.or(None)),
+            region_border_color: self.region_border_color.or(other
+                .region_border_color
+                .or(Some(DEFAULT_REGION_BORDER_COLOR))),
+            regions_include_water: self.regions_include_water.or(other
+                .regions_include_water
+                .or(Some(DEFAULT_REGIONS_INCLUDE_WATER))),
+            flat_shading_levels: self
+                .flat_shading_levels
+                .or(other.flat_shading_levels.or(None)),
+            feature_scale: self.feature_scale.or(other.feature_scale.or(None)),
+            histogram: self.histogram.clone().or(other.histogram.clone().or(None)),
+            histogram_buckets: self
+                .histogram_buckets
+                .or(other.histogram_buckets.or(Some(DEFAULT_HISTOGRAM_BUCKETS))),
+            bake_gradient: self
+                .bake_gradient
+                .or(other.bake_gradient.or(Some(DEFAULT_BAKE_GRADIENT))),
+            lut_size: self.lut_size.or(other.lut_size.or(Some(DEFAULT_LUT_SIZE))),
+            reproducible: self
+                .reproducible
+                .or(other.reproducible.or(Some(DEFAULT_REPRODUCIBLE))),
+            band_noise_strength: self
+                .band_noise_strength
+                .clone()
+                .or(other.band_noise_strength.clone().or(None)),
+            tile_preview: self
+                .tile_preview
+                .clone()
+                .or(other.tile_preview.clone().or(None)),
+            gradient: self.gradient.clone().or(other.gradient.clone().or(None)),
+            fast: self.fast.or(other.fast.or(Some(DEFAULT_FAST))),
+            grid_overlay: self.grid_overlay.or(other.grid_overlay.or(None)),
+            grid_color: self
+                .grid_color
+                .or(other.grid_color.or(Some(DEFAULT_GRID_COLOR))),
+            grid_opacity: self
+                .grid_opacity
+                .or(other.grid_opacity.or(Some(DEFAULT_GRID_OPACITY))),
+            grid_labels: self
+                .grid_labels
+                .or(other.grid_labels.or(Some(DEFAULT_GRID_LABELS))),
+            island_shape: self.island_shape.or(other.island_shape.or(None)),
+            export_bands: self
+                .export_bands
+                .clone()
+                .or(other.export_bands.clone().or(None)),
+            seed_space_search: self.seed_space_search.or(other.seed_space_search.or(None)),
+            search_top: self
+                .search_top
+                .or(other.search_top.or(Some(DEFAULT_SEARCH_TOP))),
+            search_size: self.search_size.or(other.search_size.or(None)),
+            target_land: self
+                .target_land
+                .or(other.target_land.or(Some(DEFAULT_TARGET_LAND))),
+            target_mountains: self
+                .target_mountains
+                .or(other.target_mountains.or(Some(DEFAULT_TARGET_MOUNTAINS))),
+            target_landmass: self
+                .target_landmass
+                .or(other.target_landmass.or(Some(DEFAULT_TARGET_LANDMASS))),
+            search_weights: self
+                .search_weights
+                .or(other.search_weights.or(Some(DEFAULT_SEARCH_WEIGHTS))),
+            palette: self.palette.or(other.palette.or(None)),
+            downscale_filter: self
+                .downscale_filter
+                .or(other.downscale_filter.or(Some(DEFAULT_DOWNSCALE_FILTER))),
+            export_tiles: self.export_tiles.or(other.export_tiles.or(None)),
+            parallel_tiles: self
+                .parallel_tiles
+                .or(other.parallel_tiles.or(Some(DEFAULT_PARALLEL_TILES))),
+            theme: self.theme.or(other.theme.or(None)),
+            noise_strength_curve: self
+                .noise_strength_curve
+                .clone()
+                .or(other.noise_strength_curve.clone().or(None)),
+            validate_only: self.validate_only.or(other.validate_only.or(Some(false))),
+            antimeridian_wrap: self
+                .antimeridian_wrap
+                .or(other.antimeridian_wrap.or(Some(DEFAULT_ANTIMERIDIAN_WRAP))),
+            color_interpolation_across_all_bands: self.color_interpolation_across_all_bands.or(
+                other
+                    .color_interpolation_across_all_bands
+                    .or(Some(DEFAULT_COLOR_INTERPOLATION_ACROSS_ALL_BANDS)),
+            ),
+            peak_rarity: self
+                .peak_rarity
+                .or(other.peak_rarity.or(Some(DEFAULT_PEAK_RARITY))),
+            output_multiple: self
+                .output_multiple
+                .clone()
+                .or(other.output_multiple.clone().or(None)),
+            min_thread_chunk: self.min_thread_chunk.or(other
+                .min_thread_chunk
+                .or(Some(generator::DEFAULT_MIN_THREAD_CHUNK as u32))),
+            input_heightmap: self
+                .input_heightmap
+                .clone()
+                .or(other.input_heightmap.clone().or(None)),
+            seed_display: self
+                .seed_display
+                .or(other.seed_display.or(Some(DEFAULT_SEED_DISPLAY))),
+            octave_blend: self
+                .octave_blend
+                .or(other.octave_blend.or(Some(OctaveBlend::Add))),
+            limit_memory: self.limit_memory.or(other.limit_memory.or(None)),
+            watch: self.watch.or(other.watch.or(Some(DEFAULT_WATCH))),
+            checkpoint_heightmap: self
+                .checkpoint_heightmap
+                .clone()
+                .or(other.checkpoint_heightmap.clone().or(None)),
+            octave_jitter: self
+                .octave_jitter
+                .or(other.octave_jitter.or(Some(DEFAULT_OCTAVE_JITTER))),
+            emit_svg: self.emit_svg.clone().or(other.emit_svg.clone().or(None)),
+            contour_interval: self
+                .contour_interval
+                .or(other.contour_interval.or(Some(DEFAULT_CONTOUR_INTERVAL))),
+            contour_color: self
+                .contour_color
+                .or(other.contour_color.or(Some(DEFAULT_CONTOUR_COLOR))),
+            contour_width: self
+                .contour_width
+                .or(other.contour_width.or(Some(DEFAULT_CONTOUR_WIDTH))),
+            svg_fill_bands: self
+                .svg_fill_bands
+                .or(other.svg_fill_bands.or(Some(DEFAULT_SVG_FILL_BANDS))),
+            svg_simplify: self.svg_simplify.or(other.svg_simplify.or(None)),
+            tileset_index: self
+                .tileset_index
+                .clone()
+                .or(other.tileset_index.clone().or(None)),
+            tile_ids: self.tile_ids.clone().or(other.tile_ids.clone().or(None)),
+            tile_transitions: self
+                .tile_transitions
+                .clone()
+                .or(other.tile_transitions.clone().or(None)),
+            tileset_source: self.tileset_source.clone().or(other
+                .tileset_source
+                .clone()
+                .or(Some(DEFAULT_TILESET_SOURCE.to_string()))),
+            tileset_tile_size: self
+                .tileset_tile_size
+                .or(other.tileset_tile_size.or(Some(DEFAULT_TILESET_TILE_SIZE))),
+            depth_curve: self
+                .depth_curve
+                .clone()
+                .or(other.depth_curve.clone().or(None)),
+            check_planet: self
+                .check_planet
+                .or(other.check_planet.or(Some(DEFAULT_CHECK_PLANET))),
+            check_planet_threshold: self.check_planet_threshold.or(other
+                .check_planet_threshold
+                .or(Some(DEFAULT_CHECK_PLANET_THRESHOLD))),
+            prewarm_threadpool: self.prewarm_threadpool.or(other
+                .prewarm_threadpool
+                .or(Some(DEFAULT_PREWARM_THREADPOOL))),
+            band_blend_width: self
+                .band_blend_width
+                .or(other.band_blend_width.or(Some(DEFAULT_BAND_BLEND_WIDTH))),
+            geotiff: self.geotiff.clone().or(other.geotiff.clone().or(None)),
+            bbox: self.bbox.or(other.bbox.or(None)),
+            crs: self.crs.or(other.crs.or(Some(DEFAULT_CRS))),
+            land_ratio_target: self.land_ratio_target.or(other.land_ratio_target.or(None)),
+            land_ratio_tolerance: self.land_ratio_tolerance.or(other
+                .land_ratio_tolerance
+                .or(Some(DEFAULT_LAND_RATIO_TOLERANCE))),
+            stamps: self.stamps.clone().or(other.stamps.clone().or(None)),
+            jitter_rng: self
+                .jitter_rng
+                .or(other.jitter_rng.or(Some(JitterRng::SeedHash))),
             output_path: self
                 .output_path
                 .clone()
@@ -0,0 +1,50 @@
+use image::RgbImage;
+use map_generation::overlay;
+
+/// Space between the cells of a sheet in pixels.
+const GAP: u32 = 2;
+
+/// Grid of equally sized images, each labeled with its index, to compare several renders at once.
+pub struct ContactSheet {
+    image: RgbImage,
+    columns: u32,
+    cell: (u32, u32),
+}
+
+impl ContactSheet {
+    /// Empty sheet with room for `count` cells of `cell_width` x `cell_height` pixels, filled row
+    /// by row with `columns` cells per row.
+    pub fn new(count: u32, columns: u32, cell_width: u32, cell_height: u32) -> Self {
+        let columns = columns.clamp(1, count.max(1));
+        let rows = count.div_ceil(columns).max(1);
+
+        Self {
+            image: RgbImage::new(
+                columns * (cell_width + GAP) - GAP,
+                rows * (cell_height + GAP) - GAP,
+            ),
+            columns,
+            cell: (cell_width, cell_height),
+        }
+    }
+
+    /// Copies `image` into the cell at `index` and labels it with the index. Parts of `image`
+    /// which do not fit into the cell are cut off.
+    pub fn place(&mut self, index: u32, image: &RgbImage) {
+        let left = (index % self.columns) * (self.cell.0 + GAP);
+        let top = (index / self.columns) * (self.cell.1 + GAP);
+
+        for (x, y, pixel) in image.enumerate_pixels() {
+            if x < self.cell.0 && y < self.cell.1 {
+                self.image.put_pixel(left + x, top + y, *pixel);
+            }
+        }
+
+        let width = self.image.width() as usize;
+        overlay::label(&mut self.image, width, left as usize, top as usize, index);
+    }
+
+    pub fn into_image(self) -> RgbImage {
+        self.image
+    }
+}
@@ -1,56 +1,1638 @@
+mod checkpoint;
 mod config;
-mod hasher;
+mod contact_sheet;
+mod profiling;
+mod search;
+mod stats;
+mod timings;
 
 use std::{
+    fs::File,
     hash::{Hash, Hasher},
-    time::Instant,
+    io::{BufRead, BufReader, BufWriter, Cursor, ErrorKind, Write},
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        mpsc::RecvTimeoutError,
+    },
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
 };
 
 use config::*;
-use hasher::SeedHasher;
-use image::{ImageBuffer, Rgb};
-use map_generation::generator;
+use contact_sheet::ContactSheet;
+use image::{imageops, GrayImage, ImageFormat, Rgb, RgbImage};
+use map_generation::{
+    export::{self, BoundingBox},
+    generator::{self, ClampCounts, GenParams, GenerationError, Generator, Mirror, PixelTrace},
+    gradient::{ColorLut, Gradient, TerrainKind, SCALES},
+    hasher::SeedHasher,
+    heightfield::HeightField,
+    overlay, palette, postprocess,
+    stats::{histogram, HeightStats},
+};
+use notify::{Event, RecursiveMode, Watcher};
+use profiling::Profiler;
+use search::MapMetrics;
+use stats::StatsReport;
+use timings::Timings;
+
+/// Set on Ctrl-C, which stops generation instead of killing the process, so no partial file is
+/// written.
+static INTERRUPTED: AtomicBool = AtomicBool::new(false);
+
+/// Number of renders in flight, see [`RenderGuard`]. Ctrl-C only stops renders cleanly, while none
+/// is in flight it ends the process right away.
+static RENDERS: AtomicUsize = AtomicUsize::new(0);
+
+/// Exit status after Ctrl-C ended the process, 128 + SIGINT like shells report it.
+const INTERRUPTED_STATUS: i32 = 130;
+
+/// Output path which writes the encoded image to stdout instead of a file.
+const STDOUT_PATH: &str = "-";
+
+/// Prints like `println!`, but to stderr if `$to_stderr` is set, e.g. to keep stdout free for
+/// image data.
+macro_rules! status {
+    ($to_stderr:expr, $($arg:tt)*) => {
+        if $to_stderr {
+            eprintln!($($arg)*);
+        } else {
+            println!($($arg)*);
+        }
+    };
+}
+
+/// Share of clamped samples in percent above which the verbose report hints at misconfiguration.
+const CLAMP_WARNING_PERCENT: f64 = 5.0;
+
+/// Length of the longer side of the images rendered by the seed search, if no size is given.
+const SEARCH_SIZE: u32 = 128;
+
+/// Most samples `--land-ratio-target` renders while searching the base level, enough to narrow it
+/// down to less than a thousandth.
+const LAND_RATIO_STEPS: u32 = 12;
+
+/// Interval at which `--watch` checks whether Ctrl-C was pressed while waiting for changes.
+const WATCH_INTERVAL: Duration = Duration::from_millis(200);
+/// Time without further changes of the config file before `--watch` renders again, so saving it
+/// with several writes in a row renders only once.
+const WATCH_DEBOUNCE: Duration = Duration::from_millis(300);
+
+/// Bytes per megabyte of `--limit-memory`.
+const MEGABYTE: u64 = 1024 * 1024;
+
+/// Maximum width and height a palette reference image is scaled down to before extracting colors.
+const PALETTE_SAMPLE_SIZE: u32 = 256;
 
 fn main() -> Result<(), std::io::Error> {
-    let config = Config::new().unwrap();
-    if config.dump_config.unwrap() {
-        println!("{config}");
+    ctrlc::set_handler(interrupt).map_err(std::io::Error::other)?;
+
+    let config = Config::new()?;
+
+    if config.prewarm_threadpool.unwrap() {
+        let pool = generator::thread_pool(config.thread_count).map_err(std::io::Error::other)?;
+        return pool.install(|| run(config));
+    }
+
+    run(config)
+}
+
+/// Runs the mode selected by `config`.
+fn run(config: Config) -> Result<(), std::io::Error> {
+    if config.validate_only.unwrap() {
+        return validate_only(&config);
+    }
+
+    if config.print_config_template.unwrap() {
+        println!("{}", Config::template());
+        return Ok(());
+    }
+
+    if let Some([x, y]) = config.inspect {
+        return inspect(&config, x, y);
     }
 
-    let width = config.width.unwrap();
-    let height = config.height.unwrap();
+    if let Some([a, b, out]) = config.diff.as_deref() {
+        return diff(a, b, out, config.diff_amplify.unwrap());
+    }
+
+    if config.watch.unwrap() {
+        return watch(config);
+    }
+
+    if config.stdin_configs.unwrap() {
+        return render_stdin_configs(config.output_path.as_ref().unwrap());
+    }
+
+    if let Some(seed_list) = &config.seed_list {
+        return render_seed_list(&config, seed_list);
+    }
+
+    if let Some(tile_size) = config.export_tiles {
+        return render_tiles(&config, tile_size);
+    }
+
+    if let Some(count) = config.seed_space_search {
+        return search_seeds(&config, count);
+    }
+
+    if let Some(count) = config.seed_increment {
+        return render_contact_sheet(&config, count);
+    }
+
+    render(&config, 1)
+}
+
+/// Renders the image of `config` and again whenever its config file changes, until Ctrl-C is
+/// pressed.
+///
+/// The directory of the file is watched rather than the file itself, as editors often save by
+/// replacing the file. Failed renders and invalid configs are reported without ending the loop.
+fn watch(mut config: Config) -> Result<(), std::io::Error> {
+    let path = config.config_file.clone().ok_or_else(|| {
+        std::io::Error::new(
+            ErrorKind::InvalidInput,
+            "--watch needs a --config-file to watch",
+        )
+    })?;
+    let file = std::path::absolute(&path)?;
+    let directory = file.parent().unwrap_or(Path::new("/"));
+
+    let (sender, events) = std::sync::mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).map_err(std::io::Error::other)?;
+    watcher
+        .watch(directory, RecursiveMode::NonRecursive)
+        .map_err(std::io::Error::other)?;
+    let changes_file = |event: notify::Result<Event>| match event {
+        Ok(event) => !event.kind.is_access() && event.paths.contains(&file),
+        Err(err) => {
+            eprintln!("Error while watching {path}: {err}");
+            false
+        }
+    };
+
+    let to_stdout = config.output_path.as_deref() == Some(STDOUT_PATH);
+    status!(
+        to_stdout,
+        "Watching {path} for changes, press Ctrl-C to stop"
+    );
+
+    let mut opened = false;
+    loop {
+        // the viewer shows the file, so it only needs to be opened once
+        let open = config.open.unwrap();
+        config.open = Some(open && !opened);
+        match render(&config, 1) {
+            Ok(()) => opened |= open,
+            Err(err) if err.kind() == ErrorKind::Interrupted => return Ok(()),
+            Err(err) => eprintln!("Error: {err}"),
+        }
+
+        // wait for a change which is not followed by another one right away
+        loop {
+            let changed = match events.recv_timeout(WATCH_INTERVAL) {
+                Ok(event) => changes_file(event),
+                Err(RecvTimeoutError::Timeout) => false,
+                Err(RecvTimeoutError::Disconnected) => {
+                    return Err(std::io::Error::other(format!("Stopped watching {path}")));
+                }
+            };
+            if !changed {
+                if INTERRUPTED.load(Ordering::Relaxed) {
+                    return Ok(());
+                }
+                continue;
+            }
+            while events.recv_timeout(WATCH_DEBOUNCE).is_ok() {}
+
+            match Config::new() {
+                Ok(changed) => {
+                    config = changed;
+                    break;
+                }
+                Err(err) => eprintln!("Error in {path}: {err}"),
+            }
+        }
+    }
+}
+
+/// Checks the config and the gradient it describes without rendering, printing every problem.
+fn validate_only(config: &Config) -> Result<(), std::io::Error> {
+    let mut problems = config.problems();
+
+    if let Some(path) = &config.config_file {
+        if !Path::new(path).exists() {
+            problems.push(format!("Config file {path} does not exist"));
+        }
+    }
+
+    match gradient(config) {
+        Ok(gradient) => {
+            // inline bands are partly checked by the config already
+            for problem in gradient.validate().err().unwrap_or_default() {
+                if !problems.contains(&problem) {
+                    problems.push(problem);
+                }
+            }
+        }
+        Err(err) => problems.push(format!("Could not load the gradient: {err}")),
+    }
+
+    if problems.is_empty() {
+        println!("Config is valid");
+        return Ok(());
+    }
+
+    for problem in &problems {
+        eprintln!("{problem}");
+    }
+    Err(std::io::Error::new(
+        ErrorKind::InvalidInput,
+        format!("Found {} problem(s) in the config", problems.len()),
+    ))
+}
+
+/// Generates one image per line of stdin, each line holding a JSON config. A line that fails is
+/// reported and skipped, the remaining lines are still rendered.
+fn render_stdin_configs(output_path: &str) -> Result<(), std::io::Error> {
+    let mut failed = 0;
+
+    for (idx, line) in std::io::stdin().lock().lines().enumerate() {
+        check_interrupted()?;
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let result = Config::from_json(&line).and_then(|mut config| {
+            config.output_path = Some(numbered_path(output_path, idx + 1));
+            render(&config, idx + 1)
+        });
+
+        if let Err(err) = result {
+            eprintln!("Line {}: {err}", idx + 1);
+            failed += 1;
+        }
+    }
+
+    batch_result(failed)
+}
+
+/// Generates one image per line of the seed list at `path`, each line holding a name and a seed
+/// separated by whitespace. Images are saved as `<name>.<extension of output path>` next to the
+/// output path. Blank lines and lines starting with `#` are skipped, failing lines are reported
+/// and skipped.
+fn render_seed_list(config: &Config, path: &str) -> Result<(), std::io::Error> {
     let output_path = config.output_path.as_ref().unwrap();
-    let verbose = config.verbose.unwrap();
+    let mut failed = 0;
 
-    let mut image: Vec<u8> = vec![0; (width * height * 3) as usize];
+    for (idx, line) in BufReader::new(File::open(path)?).lines().enumerate() {
+        check_interrupted()?;
+        let line = line?;
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') {
+            continue;
+        }
 
-    if verbose {
-        println!("Generating...");
+        let result = match line.split_once(char::is_whitespace) {
+            Some((name, seed)) => {
+                let mut config = config.clone();
+                config.seed = Some(seed.trim().to_string());
+                config.output_path = Some(named_path(output_path, name));
+                render(&config, idx + 1)
+            }
+            None => Err(std::io::Error::new(
+                ErrorKind::InvalidData,
+                format!("expected a name and a seed, got \"{line}\""),
+            )),
+        };
+
+        if let Err(err) = result {
+            eprintln!("Line {}: {err}", idx + 1);
+            failed += 1;
+        }
     }
 
+    batch_result(failed)
+}
+
+/// Renders `count` seeds counting up from the configured one at the cell size into a single
+/// contact sheet, labeling each cell with its index.
+fn render_contact_sheet(config: &Config, count: u32) -> Result<(), std::io::Error> {
+    let _rendering = RenderGuard::new();
+    let base = config.seed.as_ref().unwrap();
+    let [cell_width, cell_height] = config.sheet_cell_size.unwrap();
+    let columns = config
+        .sheet_columns
+        .unwrap_or_else(|| (count as f64).sqrt().ceil() as u32);
+    let mut sheet = ContactSheet::new(count, columns, cell_width, cell_height);
+
+    let mut cell_config = config.clone();
+    cell_config.preview_scale = None;
+    cell_config.width = Some(cell_width);
+    cell_config.height = Some(cell_height);
+    // every cell has its own seed, so they would only keep replacing each other's checkpoint
+    cell_config.checkpoint_heightmap = None;
+    let gradient = gradient(config)?;
+
+    for index in 0..count {
+        let seed = nth_seed(base, index);
+        cell_config.seed = Some(seed.clone());
+
+        let params = gen_params(&cell_config, cell_width, cell_height);
+        let len = generator::buffer_len(cell_width, cell_height).ok_or_else(|| {
+            std::io::Error::new(ErrorKind::InvalidInput, "Sheet cells are too large")
+        })?;
+        let mut image = vec![0; len];
+        let (heights, _) = generate(
+            &mut image,
+            &params,
+            &gradient,
+            &cell_config,
+            None,
+            &mut Timings::new(),
+        )?;
+        apply_overlays(&mut image, &heights, &params, &cell_config)?;
+
+        let image = generator::image_from_vec(cell_width, cell_height, image)
+            .map_err(std::io::Error::other)?;
+        sheet.place(index, &image);
+        println!("{index}\t{seed}");
+    }
+
+    let output_path = output_path(config, 1)?;
+    sheet
+        .into_image()
+        .save(&output_path)
+        .map_err(std::io::Error::other)?;
+    println!("Wrote contact sheet to: {output_path}");
+
+    Ok(())
+}
+
+/// Renders `count` seeds counting up from the configured one at low resolution and prints the
+/// ones closest to the search targets, best first.
+fn search_seeds(config: &Config, count: u32) -> Result<(), std::io::Error> {
+    let _rendering = RenderGuard::new();
+    let base = config.seed.as_ref().unwrap();
+    let targets = MapMetrics {
+        land: config.target_land.unwrap(),
+        mountains: config.target_mountains.unwrap(),
+        largest_landmass: config.target_landmass.unwrap(),
+    };
+    let weights = config.search_weights.unwrap();
+    let gradient = gradient(config)?;
+
+    let mut seed_config = config.clone();
+    let mut scores = Vec::new();
+    for index in 0..count {
+        check_interrupted()?;
+        let seed = nth_seed(base, index);
+        seed_config.seed = Some(seed.clone());
+
+        let heights = sample_heights(&seed_config, &gradient)?;
+        let metrics = MapMetrics::new(&heights, &gradient);
+        scores.push((metrics.score(&targets, weights), seed, metrics));
+    }
+
+    scores.sort_by(|(a, ..), (b, ..)| a.total_cmp(b));
+    println!("rank\tscore\tland\tmountains\tlandmass\tseed");
+    for (rank, (score, seed, metrics)) in scores
+        .iter()
+        .take(config.search_top.unwrap() as usize)
+        .enumerate()
+    {
+        println!(
+            "{}\t{score:.4}\t{:.1}%\t{:.1}%\t{:.1}%\t{seed}",
+            rank + 1,
+            metrics.land * 100.0,
+            metrics.mountains * 100.0,
+            metrics.largest_landmass * 100.0
+        );
+    }
+
+    Ok(())
+}
+
+/// Generates and post-processes the height field of `config` at the low resolution of
+/// `--search-size`, showing the same part of the map as the full image. Band noise and `--fast`
+/// classify the heights by the bands of `gradient` like in the full image.
+fn sample_heights(config: &Config, gradient: &Gradient) -> Result<HeightField, std::io::Error> {
+    let (width, height) = config.dimensions();
+    let [sample_width, sample_height] = config.search_size.unwrap_or_else(|| {
+        let scale = SEARCH_SIZE as f64 / width.max(height) as f64;
+        [
+            ((width as f64 * scale).round() as u32).max(1),
+            ((height as f64 * scale).round() as u32).max(1),
+        ]
+    });
+
+    let mut params = gen_params(config, sample_width, sample_height);
+    params.feature_scale = params
+        .feature_scale
+        .map(|scale| scale * sample_width as f64 / width as f64);
+
+    let mut heights = HeightField::new(sample_width, sample_height);
+    Generator::new(params, gradient.clone())
+        .render_heightmap_cancellable(&mut heights.data, &INTERRUPTED)
+        .map_err(generation_error)?;
+    post_process(&mut heights, config, width);
+    Ok(heights)
+}
+
+/// Binary searches the base level at which the share of land of low resolution samples is within
+/// `--land-ratio-tolerance` of `target`, see [`sample_heights`].
+///
+/// Raising the base level only ever raises the terrain, so the share of land grows with it. Returns
+/// the level and its share of land, which is the closest one found if no level is within the
+/// tolerance after [`LAND_RATIO_STEPS`] steps, e.g. because the seed has more land than the target
+/// even at level 0 or an island mask limits the land.
+fn tune_base_level(config: &Config, target: f64) -> Result<(f64, f64), std::io::Error> {
+    let tolerance = config.land_ratio_tolerance.unwrap();
+    let gradient = gradient(config)?;
+    let mut sample_config = config.clone();
+
+    // the same range --base-level accepts
+    let (mut low, mut high) = (0.0, 1.0);
+    let mut best: Option<(f64, f64)> = None;
+    for _ in 0..LAND_RATIO_STEPS {
+        check_interrupted()?;
+        let level = (low + high) / 2.0;
+        sample_config.base_level = Some(level);
+        let land = MapMetrics::new(&sample_heights(&sample_config, &gradient)?, &gradient).land;
+
+        if best.is_none_or(|(_, best)| (land - target).abs() < (best - target).abs()) {
+            best = Some((level, land));
+        }
+        if (land - target).abs() <= tolerance {
+            break;
+        }
+        if land < target {
+            low = level;
+        } else {
+            high = level;
+        }
+    }
+
+    // at least one step is taken
+    Ok(best.unwrap())
+}
+
+/// Seed at `index` when counting up from `base`. Numeric seeds are incremented, others get the
+/// index appended, except for the first one which is `base` itself.
+fn nth_seed(base: &str, index: u32) -> String {
+    match base.parse::<u64>() {
+        Ok(number) => number.wrapping_add(index as u64).to_string(),
+        Err(_) if index == 0 => base.to_string(),
+        Err(_) => format!("{base}-{index}"),
+    }
+}
+
+/// Format the image is saved in, given by `--format` or the extension of `output_path`.
+fn output_format(config: &Config, output_path: &str) -> Result<ImageFormat, std::io::Error> {
+    let format = match &config.format {
+        // the extension was validated when parsing the config
+        Some(extension) => ImageFormat::from_extension(extension).unwrap(),
+        None if output_path == STDOUT_PATH => {
+            return Err(std::io::Error::new(
+                ErrorKind::InvalidInput,
+                "A format must be given with --format when writing the image to stdout",
+            ))
+        }
+        None => ImageFormat::from_path(output_path).map_err(std::io::Error::other)?,
+    };
+    if !format_enabled(format) {
+        return Err(std::io::Error::new(
+            ErrorKind::Unsupported,
+            format!(
+                "Saving {format:?} images is not enabled in this build, see the format features"
+            ),
+        ));
+    }
+
+    Ok(format)
+}
+
+/// Renders the image as separate tiles of `tile_width` x `tile_height` pixels, each saved next to
+/// the output path as `<output>_<column>_<row>.<extension>`. Tiles at the right and bottom edges
+/// are smaller if the image size is not a multiple of the tile size.
+///
+/// `--parallel-tiles` tiles are rendered at the same time, each on a single thread then. Tiles are
+/// written to a temporary file first and renamed once complete, so an interrupted run never leaves
+/// a partially written tile behind.
+fn render_tiles(
+    config: &Config,
+    [tile_width, tile_height]: [u32; 2],
+) -> Result<(), std::io::Error> {
+    let _rendering = RenderGuard::new();
+    let (width, height) = config.dimensions();
+    let output_path = config.output_path.as_ref().unwrap();
+    if output_path == STDOUT_PATH {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidInput,
+            "Tiles cannot be written to stdout",
+        ));
+    }
+    if config.mirror.unwrap() != Mirror::None {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidInput,
+            "Mirrored images cannot be rendered as tiles",
+        ));
+    }
+    let format = output_format(config, output_path)?;
+
+    let parallel_tiles = config.parallel_tiles.unwrap() as usize;
+    let mut params = gen_params(config, width, height);
+    if parallel_tiles > 1 {
+        // the tiles already keep the threads busy
+        params.thread_count = Some(1);
+    }
+    let generator = Generator::new(params, gradient(config)?);
+
+    let tiles: Vec<(u32, u32)> = (0..height.div_ceil(tile_height))
+        .flat_map(|row| (0..width.div_ceil(tile_width)).map(move |column| (column, row)))
+        .collect();
+    let next = AtomicUsize::new(0);
+
+    let render_tile = |(column, row): (u32, u32)| -> Result<(), std::io::Error> {
+        let offset = (column * tile_width, row * tile_height);
+        let size = (
+            tile_width.min(width - offset.0),
+            tile_height.min(height - offset.1),
+        );
+        // tiles are at most as large as the validated image
+        let mut buffer = vec![0; generator::buffer_len(size.0, size.1).unwrap()];
+        generator
+            .render(offset, size, &mut buffer)
+            .map_err(generation_error)?;
+
+        let image =
+            generator::image_from_vec(size.0, size.1, buffer).map_err(std::io::Error::other)?;
+        let mut encoded = Cursor::new(Vec::new());
+        image
+            .write_to(&mut encoded, format)
+            .map_err(std::io::Error::other)?;
+
+        let tile_path = suffixed_path(output_path, &format!("{column}_{row}"), None);
+        write_atomically(&tile_path, &encoded.into_inner())?;
+        println!("Wrote tile {column},{row} to: {tile_path}");
+        Ok(())
+    };
+
+    std::thread::scope(|scope| {
+        let workers: Vec<_> = (0..parallel_tiles.min(tiles.len()))
+            .map(|_| {
+                scope.spawn(|| {
+                    while let Some(tile) = tiles.get(next.fetch_add(1, Ordering::Relaxed)) {
+                        check_interrupted()?;
+                        if let Err(err) = render_tile(*tile) {
+                            // stop the other workers from starting more tiles
+                            next.store(tiles.len(), Ordering::Relaxed);
+                            return Err(err);
+                        }
+                    }
+                    Ok(())
+                })
+            })
+            .collect();
+
+        workers
+            .into_iter()
+            .try_for_each(|worker| worker.join().expect("tile worker panicked"))
+    })
+}
+
+/// Approximate peak memory in bytes of rendering an image of `width` x `height` pixels at once:
+/// its colors and encoded data plus the height of every sample and, if the samples are resized
+/// with a filter, their colors.
+fn render_memory(width: u32, height: u32, aa: u32, resized: bool) -> u64 {
+    let samples_per_pixel = aa as u64 * aa as u64;
+    let sample_bytes = if aa > 1 && resized { 8 + 3 } else { 8 };
+    width as u64 * height as u64 * (3 + 3 + samples_per_pixel * sample_bytes)
+}
+
+/// Renders the image of `config` in bands of rows, each streamed into the PNG at `output_path`
+/// once colored, so about `limit` bytes of memory are enough. Post-processing, overlays and
+/// additional outputs need the whole image and are skipped.
+///
+/// The file is written to a temporary path and renamed once complete, like tiles.
+fn render_bands(
+    config: &Config,
+    output_path: &str,
+    needed: u64,
+    limit: u64,
+) -> Result<(), std::io::Error> {
+    let (width, height) = config.dimensions();
+    let to_stdout = output_path == STDOUT_PATH;
+    if config.mirror.unwrap() != Mirror::None {
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidInput,
+            "Mirrored images cannot be rendered in bands, raise --limit-memory",
+        ));
+    }
+    if output_format(config, output_path)? != ImageFormat::Png {
+        return Err(std::io::Error::new(
+            ErrorKind::Unsupported,
+            "Only PNG images can be rendered in bands, raise --limit-memory or save as PNG",
+        ));
+    }
+
+    let params = gen_params(config, width, height);
+    let row_bytes = render_memory(width, 1, params.aa_factor, false);
+    let band_rows = (limit / row_bytes).clamp(1, height as u64) as u32;
+    status!(
+        to_stdout,
+        "Image needs about {} MB, rendering it in bands of {band_rows} rows to stay below {} MB. \
+         Post-processing, overlays and additional outputs are skipped",
+        needed.div_ceil(MEGABYTE),
+        limit / MEGABYTE
+    );
+
+    let generator = Generator::new(params, gradient(config)?);
+    let render_band = |top: u32, buffer: &mut Vec<u8>| -> Result<(), std::io::Error> {
+        check_interrupted()?;
+        let rows = band_rows.min(height - top);
+        // bands are at most as large as the validated image
+        buffer.resize(generator::buffer_len(width, rows).unwrap(), 0);
+        generator
+            .render((0, top), (width, rows), buffer)
+            .map_err(generation_error)
+    };
+
+    if to_stdout {
+        write_png_bands(
+            std::io::stdout().lock(),
+            width,
+            height,
+            band_rows,
+            render_band,
+        )
+    } else {
+        let temporary = format!("{output_path}.tmp");
+        let file = BufWriter::new(File::create(&temporary)?);
+        write_png_bands(file, width, height, band_rows, render_band)?;
+        std::fs::rename(&temporary, output_path)
+    }
+}
+
+/// Writes a PNG of `width` x `height` pixels to `writer`, filling its rows `band_rows` at a time
+/// with `render_band`, which gets the first row of the band.
+#[cfg(feature = "png")]
+fn write_png_bands<W, F>(
+    writer: W,
+    width: u32,
+    height: u32,
+    band_rows: u32,
+    mut render_band: F,
+) -> Result<(), std::io::Error>
+where
+    W: Write,
+    F: FnMut(u32, &mut Vec<u8>) -> Result<(), std::io::Error>,
+{
+    let mut encoder = png::Encoder::new(writer, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut png_writer = encoder.write_header().map_err(std::io::Error::other)?;
+    let mut stream = png_writer.stream_writer().map_err(std::io::Error::other)?;
+
+    let mut buffer = Vec::new();
+    for top in (0..height).step_by(band_rows as usize) {
+        render_band(top, &mut buffer)?;
+        stream.write_all(&buffer)?;
+    }
+
+    stream.finish().map_err(std::io::Error::other)
+}
+
+#[cfg(not(feature = "png"))]
+fn write_png_bands<W, F>(_: W, _: u32, _: u32, _: u32, _: F) -> Result<(), std::io::Error> {
+    Err(std::io::Error::new(
+        ErrorKind::Unsupported,
+        "Rendering in bands needs the png feature",
+    ))
+}
+
+/// Writes `data` to a temporary file next to `path` and renames it to `path` once complete.
+fn write_atomically(path: &str, data: &[u8]) -> Result<(), std::io::Error> {
+    let temporary = format!("{path}.tmp");
+    std::fs::write(&temporary, data)?;
+    std::fs::rename(&temporary, path)
+}
+
+/// Converts a generation error, reporting cancellation as interruption by Ctrl-C.
+fn generation_error(err: GenerationError) -> std::io::Error {
+    match err {
+        GenerationError::Cancelled => std::io::Error::new(
+            ErrorKind::Interrupted,
+            "Interrupted, the image was not saved",
+        ),
+        err => std::io::Error::other(err),
+    }
+}
+
+/// Handles Ctrl-C. The first one stops the renders in flight at the next check, a second one
+/// or one while waiting for input, e.g. by `--stdin-configs`, exits right away.
+fn interrupt() {
+    if INTERRUPTED.swap(true, Ordering::Relaxed) || RENDERS.load(Ordering::Relaxed) == 0 {
+        std::process::exit(INTERRUPTED_STATUS);
+    }
+}
+
+/// Marks a render as in flight while alive, so Ctrl-C stops it cleanly instead of exiting.
+struct RenderGuard;
+
+impl RenderGuard {
+    fn new() -> Self {
+        RENDERS.fetch_add(1, Ordering::Relaxed);
+        Self
+    }
+}
+
+impl Drop for RenderGuard {
+    fn drop(&mut self) {
+        RENDERS.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Fails once Ctrl-C was pressed, to stop before starting more work.
+fn check_interrupted() -> Result<(), std::io::Error> {
+    if INTERRUPTED.load(Ordering::Relaxed) {
+        return Err(std::io::Error::new(
+            ErrorKind::Interrupted,
+            "Interrupted, remaining images were not rendered",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Fails if any image of a batch could not be rendered.
+fn batch_result(failed: usize) -> Result<(), std::io::Error> {
+    if failed > 0 {
+        return Err(std::io::Error::other(format!(
+            "{failed} config(s) could not be rendered"
+        )));
+    }
+
+    Ok(())
+}
+
+/// Path the image is saved at. The file name of the output path is replaced by the name template
+/// and the directory by the output directory, if they are set. `index` is the position of the
+/// image in a batch.
+fn output_path(config: &Config, index: usize) -> Result<String, std::io::Error> {
+    let output_path = config.output_path.as_ref().unwrap();
+    if output_path == STDOUT_PATH {
+        return Ok(output_path.clone());
+    }
+
+    let mut path = PathBuf::from(output_path);
+    if let Some(template) = &config.name_template {
+        let (width, height) = config.dimensions();
+        let timestamp = timestamp(config.reproducible.unwrap());
+        // seeds are arbitrary strings, but must not turn the name into a path
+        let seed = config.seed.as_ref().unwrap().replace(['/', '\\'], "_");
+
+        let name = template
+            .replace("{seed}", &seed)
+            .replace("{width}", &width.to_string())
+            .replace("{height}", &height.to_string())
+            .replace("{index}", &index.to_string())
+            .replace("{timestamp}", &timestamp.to_string());
+        path.set_file_name(name);
+    }
+    if let Some(dir) = &config.output_dir {
+        std::fs::create_dir_all(dir)?;
+        path = Path::new(dir).join(path.file_name().unwrap_or_default());
+    }
+
+    Ok(path.to_string_lossy().into_owned())
+}
+
+/// Seconds since the Unix epoch. `SOURCE_DATE_EPOCH` takes precedence over the current time, which
+/// reproducible renders replace with 0.
+fn timestamp(reproducible: bool) -> u64 {
+    let source_date = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.trim().parse().ok());
+
+    match source_date {
+        Some(epoch) => epoch,
+        None if reproducible => 0,
+        None => SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs(),
+    }
+}
+
+/// Replaces the file stem of `path` with `name`, e.g. `out/output.png` -> `out/name.png`.
+fn named_path(path: &str, name: &str) -> String {
+    let path = Path::new(path);
+    let file_name = match path.extension() {
+        Some(extension) => format!("{name}.{}", extension.to_string_lossy()),
+        None => name.to_string(),
+    };
+
+    path.with_file_name(file_name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Inserts `number` between file stem and extension, e.g. `output.png` -> `output_3.png`.
+fn numbered_path(path: &str, number: usize) -> String {
+    suffixed_path(path, &number.to_string(), None)
+}
+
+/// Appends `_<suffix>` to the file stem of `path`, e.g. `output.png` -> `output_suffix.png`. The
+/// extension is replaced by `extension` if given.
+fn suffixed_path(path: &str, suffix: &str, extension: Option<&str>) -> String {
+    let path = Path::new(path);
+    let stem = path.file_stem().unwrap_or_default().to_string_lossy();
+    let extension = extension.map(str::to_string).or_else(|| {
+        path.extension()
+            .map(|ext| ext.to_string_lossy().into_owned())
+    });
+    let file_name = match extension {
+        Some(extension) => format!("{stem}_{suffix}.{extension}"),
+        None => format!("{stem}_{suffix}"),
+    };
+
+    path.with_file_name(file_name)
+        .to_string_lossy()
+        .into_owned()
+}
+
+/// Saves the roughness of `heights` as grayscale image, scaled so the roughest area is white.
+fn write_roughness(
+    heights: &HeightField,
+    radius: u32,
+    wrap: bool,
+    path: &str,
+) -> Result<(), std::io::Error> {
+    let roughness = heights.roughness(radius, wrap);
+    let max = roughness.data.iter().copied().fold(0.0, f64::max);
+    let scale = if max > 0.0 { 255.0 / max } else { 0.0 };
+
+    let pixels = roughness
+        .data
+        .iter()
+        .map(|value| (value * scale).round() as u8)
+        .collect();
+    // the roughness map has the dimensions of the height field
+    let image = GrayImage::from_vec(roughness.width, roughness.height, pixels).unwrap();
+    image.save(path).map_err(std::io::Error::other)
+}
+
+/// Writes each product of `--output-multiple`, all derived from the same generated map.
+fn write_outputs(
+    image: &RgbImage,
+    heights: &HeightField,
+    outputs: &[(OutputKind, String)],
+    config: &Config,
+    to_stdout: bool,
+) -> Result<(), std::io::Error> {
+    for (kind, path) in outputs {
+        check_interrupted()?;
+        let result = match kind {
+            OutputKind::Color => image.save_with_format(path, output_format(config, path)?),
+            OutputKind::Height => export::heightmap_image(heights).save(path),
+            OutputKind::Normal => {
+                export::normal_map(heights, config.z_scale.unwrap(), config.seamless.unwrap())
+                    .save(path)
+            }
+        };
+        result.map_err(|err| std::io::Error::other(format!("Could not write {path}: {err}")))?;
+        status!(to_stdout, "Wrote {} to: {path}", kind.description());
+    }
+
+    Ok(())
+}
+
+/// Saves a mask of each terrain kind covering any of `heights` into `dir`, named after the kind,
+/// white where the kind is present and black elsewhere. Returns the kinds which were skipped
+/// because they cover nothing.
+fn write_band_masks(
+    heights: &HeightField,
+    gradient: &Gradient,
+    dir: &str,
+) -> Result<Vec<TerrainKind>, std::io::Error> {
+    std::fs::create_dir_all(dir)?;
+
+    let kinds: Vec<Option<usize>> = heights
+        .data
+        .iter()
+        .map(|height| gradient.get_terrain_kind(*height))
+        .collect();
+    let mut skipped = Vec::new();
+
+    for idx in 0..TerrainKind::COUNT {
+        let kind = TerrainKind::from(idx);
+        if !kinds.contains(&Some(idx)) {
+            skipped.push(kind);
+            continue;
+        }
+
+        let pixels = kinds
+            .iter()
+            .map(|cell| if *cell == Some(idx) { 255 } else { 0 })
+            .collect();
+        // the mask has the dimensions of the height field
+        let mask = GrayImage::from_vec(heights.width, heights.height, pixels).unwrap();
+        let path = Path::new(dir).join(format!("{}.png", kind.name().replace('-', "_")));
+        mask.save(path).map_err(std::io::Error::other)?;
+    }
+
+    Ok(skipped)
+}
+
+/// Height of the histogram chart in pixels.
+const HISTOGRAM_HEIGHT: u32 = 200;
+/// Width the histogram chart is widened to at least, by drawing each bar several pixels wide.
+const HISTOGRAM_MIN_WIDTH: u32 = 512;
+
+/// Saves a bar chart of the distribution of `heights` into `buckets` bars, scaled so the highest
+/// bar fills the chart. The lower limit of every band of `gradient` is marked by a vertical line in
+/// the color of the band.
+fn write_histogram(
+    heights: &[f64],
+    buckets: u32,
+    gradient: &Gradient,
+    path: &str,
+) -> Result<(), std::io::Error> {
+    let counts = histogram(heights, buckets as usize);
+    let highest = counts.iter().copied().max().unwrap_or(0).max(1);
+    let bar_width = HISTOGRAM_MIN_WIDTH.div_ceil(buckets);
+
+    let mut chart = RgbImage::from_pixel(buckets * bar_width, HISTOGRAM_HEIGHT, Rgb([255; 3]));
+    for (bucket, count) in counts.iter().enumerate() {
+        let bar_height = (*count as f64 / highest as f64 * HISTOGRAM_HEIGHT as f64).round() as u32;
+        for x in bucket as u32 * bar_width..(bucket as u32 + 1) * bar_width {
+            for y in HISTOGRAM_HEIGHT - bar_height..HISTOGRAM_HEIGHT {
+                chart.put_pixel(x, y, Rgb([96; 3]));
+            }
+        }
+    }
+
+    for (band, [min, _]) in gradient.terrain_limits.iter().enumerate().skip(1) {
+        let x = ((min * chart.width() as f64) as u32).min(chart.width() - 1);
+        for y in 0..HISTOGRAM_HEIGHT {
+            chart.put_pixel(x, y, gradient.colors[band]);
+        }
+    }
+
+    chart.save(path).map_err(std::io::Error::other)
+}
+
+/// Seed strings and the numbers they are turned into, see `--seed-display`.
+fn seed_display(config: &Config, params: &GenParams) -> String {
+    let describe = |name: &str, seed: &str, hashed: u64| {
+        format!(
+            "{name}: {seed} (hashed to {hashed}, noise seed {})",
+            generator::noise_seed(hashed)
+        )
+    };
+
+    let mut display = describe("Seed", config.seed.as_ref().unwrap(), params.seed);
+    if let (Some(seed_b), Some(hashed_b)) = (&config.seed_b, params.seed_b) {
+        display.push('\n');
+        display.push_str(&describe("Seed B", seed_b, hashed_b));
+    }
+    display
+}
+
+/// Hashes a seed string into the numeric seed used for generation.
+fn hash_seed(seed: &str) -> u64 {
     let mut hasher = SeedHasher::new();
-    config.seed.unwrap().hash(&mut hasher);
+    seed.hash(&mut hasher);
+    hasher.finish()
+}
 
-    let start = Instant::now();
-    generator::generate(
-        &mut image,
-        hasher.finish(),
-        config.width.unwrap(),
-        config.height.unwrap(),
+fn gen_params(config: &Config, width: u32, height: u32) -> GenParams {
+    let mut params = GenParams::new(
+        hash_seed(config.seed.as_ref().unwrap()),
+        width,
+        height,
         config.base_level.unwrap(),
         config.noise_strength.unwrap(),
-        None,
-        None,
     );
+    params.noise_granularity = config.noise_granularity.unwrap();
+    params.noise_magnitude = config.noise_magnitude.unwrap();
+    params.noise_shape = config.noise_shape.unwrap();
+    if let Some(band_noise) = &config.band_noise_strength {
+        // validated when the config is created
+        params.band_noise_strength = band_noise.strengths().unwrap();
+    }
+    if let Some(curve) = &config.noise_strength_curve {
+        // validated when the config is created
+        params.noise_strength_curve = Some(curve.curve().unwrap());
+    }
+    params.thread_count = config.thread_count;
+    params.min_thread_chunk = config.min_thread_chunk.unwrap() as usize;
+    params.contrast = config.contrast.unwrap();
+    params.bias = config.bias.unwrap();
+    params.peak_rarity = config.peak_rarity.unwrap();
+    params.seamless = config.seamless.unwrap();
+    params.world_size = config.world_dimensions();
+    params.antimeridian_wrap = config.antimeridian_wrap.unwrap();
+    params.rotation = config.rotate.unwrap();
+    params.equal_area = config.equal_area_projection.unwrap();
+    params.feature_scale = config.scaled_feature_scale();
+    params.fast = config.fast.unwrap();
+    params.octave_blend = config.octave_blend.unwrap();
+    params.octave_jitter = config.octave_jitter.unwrap();
+    params.jitter_rng = config.jitter_rng.unwrap();
+    params.mirror = config.mirror.unwrap();
+    params.seed_b = config.seed_b.as_deref().map(hash_seed);
+    params.blend = config.blend.unwrap();
+    params.aa_factor = config.aa.unwrap();
+    params.timeout = config.timeout.map(Duration::from_secs_f64);
+    params
+}
+
+/// Generates the moisture field, a plain height field of a seed derived from the configured one.
+fn moisture(params: &GenParams, config: &Config) -> Result<Vec<f64>, std::io::Error> {
+    // the field wraps, rotates and mirrors like the terrain, but is not shaped like it
+    let params = GenParams {
+        seed: hash_seed(&format!("{}-moisture", config.seed.as_ref().unwrap())),
+        base_level: 0.0,
+        noise_strength: 0.0,
+        contrast: 1.0,
+        bias: 1.0,
+        peak_rarity: 0.0,
+        seed_b: None,
+        aa_factor: 1,
+        ..*params
+    };
+    let mut moisture = vec![0.0; generator::pixel_count(params.width, params.height).unwrap()];
+    generator::generate_heightmap_cancellable(&mut moisture, &params, &INTERRUPTED)
+        .map_err(generation_error)?;
+    Ok(moisture)
+}
+
+/// Generates the height field, applies the configured post-processing, and colors it into `image`.
+///
+/// When antialiasing, the colors are averaged from the supersampled field and the returned field
+/// is downsampled to the size of the image. Also returns how many samples were clamped into range.
+fn generate(
+    image: &mut [u8],
+    params: &GenParams,
+    gradient: &Gradient,
+    config: &Config,
+    input: Option<HeightField>,
+    timings: &mut Timings,
+) -> Result<(HeightField, ClampCounts), std::io::Error> {
+    debug_assert_eq!(
+        Some(image.len()),
+        generator::buffer_len(params.width, params.height),
+        "image buffer must be allocated with generator::buffer_len"
+    );
+
+    let (mut samples, clamped) = match input {
+        Some(input) => (input, ClampCounts::default()),
+        None => {
+            let sample_params = params.supersampled();
+            let checkpoint = config
+                .checkpoint_heightmap
+                .as_deref()
+                .map(|path| (path, checkpoint::key(&sample_params, gradient)));
+            let cached = match checkpoint {
+                Some((path, key)) => checkpoint::load(path, key)?,
+                None => None,
+            };
+
+            match cached {
+                Some(cached) => cached,
+                None => {
+                    let mut samples = HeightField::new(sample_params.width, sample_params.height);
+                    // band noise and --fast classify the heights by the bands of the gradient
+                    let clamped = Generator::new(sample_params, gradient.clone())
+                        .render_heightmap_cancellable(&mut samples.data, &INTERRUPTED)
+                        .map_err(generation_error)?;
+                    if let Some((path, key)) = checkpoint {
+                        checkpoint::save(path, key, &samples, clamped)?;
+                    }
+                    (samples, clamped)
+                }
+            }
+        }
+    };
+    timings.lap("generation");
+
+    post_process(&mut samples, config, params.width);
+    timings.lap("post-processing");
+
+    let lut = config
+        .bake_gradient
+        .unwrap()
+        .then(|| ColorLut::new(gradient, config.lut_size.unwrap() as usize));
+    let color_fn = |height| match &lut {
+        Some(lut) => lut.color(height),
+        None => gradient.lerp_color(height),
+    };
+
+    if params.aa_factor > 1 {
+        match config.downscale_filter.unwrap().filter_type() {
+            Some(filter) => {
+                let mut sample_image = vec![0; samples.data.len() * 3];
+                generator::color_heights_with(
+                    &mut sample_image,
+                    &samples.data,
+                    &color_fn,
+                    params.thread_count,
+                    params.min_thread_chunk,
+                );
+                // the sample buffer holds one color per height of the field
+                let sample_image =
+                    RgbImage::from_vec(samples.width, samples.height, sample_image).unwrap();
+                let resized = imageops::resize(&sample_image, params.width, params.height, filter);
+                image.copy_from_slice(resized.as_raw());
+            }
+            None => generator::color_supersampled_with(
+                image,
+                &samples.data,
+                params.width,
+                params.aa_factor,
+                &color_fn,
+                params.thread_count,
+                params.min_thread_chunk,
+            )
+            .map_err(generation_error)?,
+        }
+        Ok((samples.downsample(params.aa_factor), clamped))
+    } else {
+        generator::color_heights_with(
+            image,
+            &samples.data,
+            &color_fn,
+            params.thread_count,
+            params.min_thread_chunk,
+        );
+        Ok((samples, clamped))
+    }
+}
+
+/// Loads a grayscale heightmap as height field, scaled from the full range of its bit depth to
+/// levels 0 to 1.
+fn load_heightmap(path: &str) -> Result<HeightField, std::io::Error> {
+    let heightmap = image::open(path)
+        .map_err(|err| std::io::Error::other(format!("Could not read {path}: {err}")))?
+        // 8 bit images are widened to the full 16 bit range
+        .to_luma16();
+    let (width, height) = heightmap.dimensions();
+    let data = heightmap
+        .into_raw()
+        .into_iter()
+        .map(|value| value as f64 / u16::MAX as f64)
+        .collect();
+
+    // the decoded image holds one value per pixel
+    Ok(HeightField::from_vec(data, width, height).unwrap())
+}
+
+/// Number of pairs of neighboring columns or rows `--check-planet` compares the seams to.
+const SEAM_SAMPLES: u32 = 64;
+
+/// Checks that the map of `params` continues smoothly across the edges it wraps around.
+///
+/// The heights are sampled without white noise, which would hide a discontinuity among the steps
+/// between neighboring pixels. Prints the steepest step across each seam and fails if it is more
+/// than `threshold` times as steep as the steepest step between [`SEAM_SAMPLES`] pairs of columns
+/// or rows elsewhere.
+fn check_planet(params: &GenParams, threshold: f64, to_stdout: bool) -> Result<(), std::io::Error> {
+    let params = GenParams {
+        noise_strength: 0.0,
+        band_noise_strength: [None; TerrainKind::COUNT],
+        noise_strength_curve: None,
+        fast: false,
+        aa_factor: 1,
+        timeout: None,
+        ..*params
+    };
+    let (wrap_x, wrap_y) = match params.world_size {
+        Some((width, height)) => (Some(width), Some(height)),
+        None if params.seamless => (Some(params.width), Some(params.height)),
+        None => (params.antimeridian_wrap.then_some(params.width), None),
+    };
+    let generator = Generator::new(params, Gradient::default());
+
+    // heights of the column at `position`, or of the row if `rows` is set
+    let line = |position: u32, rows: bool| -> Result<Vec<f64>, std::io::Error> {
+        let (offset, size) = match rows {
+            false => ((position, 0), (1, params.height)),
+            true => ((0, position), (params.width, 1)),
+        };
+        let mut heights = vec![0.0; size.0 as usize * size.1 as usize];
+        generator
+            .render_heights(offset, size, &mut heights)
+            .map_err(generation_error)?;
+        Ok(heights)
+    };
+    let step = |a: u32, b: u32, rows: bool| -> Result<f64, std::io::Error> {
+        let (a, b) = (line(a, rows)?, line(b, rows)?);
+        Ok(a.iter()
+            .zip(&b)
+            .map(|(a, b)| (a - b).abs())
+            .fold(0.0, f64::max))
+    };
+
+    let seams = [("East/west", wrap_x, false), ("North/south", wrap_y, true)];
+    for (name, wrap, rows) in seams {
+        let Some(wrap) = wrap.filter(|wrap| *wrap > 1) else {
+            continue;
+        };
+
+        let seam = step(wrap - 1, 0, rows)?;
+        let mut elsewhere: f64 = 0.0;
+        for idx in 1..=SEAM_SAMPLES.min(wrap - 1) {
+            let position = (idx as u64 * (wrap - 1) as u64 / (SEAM_SAMPLES as u64 + 1)) as u32;
+            elsewhere = elsewhere.max(step(position, position + 1, rows)?);
+        }
+        let ratio = seam / elsewhere.max(f64::EPSILON);
+
+        status!(
+            to_stdout,
+            "{name} seam: steepest step {seam:.6}, {elsewhere:.6} elsewhere ({ratio:.2}x)"
+        );
+        if ratio > threshold {
+            return Err(std::io::Error::other(format!(
+                "{name} seam is {ratio:.2} times as steep as the map elsewhere, more than the \
+                 threshold of {threshold}"
+            )));
+        }
+    }
+    Ok(())
+}
+
+/// Summary of how many samples were clamped at each end of the height range.
+fn clamp_report(clamped: ClampCounts) -> String {
+    let percent = |count| count as f64 / clamped.total.max(1) as f64 * 100.0;
+    let mut report = format!(
+        "Clamped {} samples to the lowest height ({:.2}%) and {} to the highest ({:.2}%)",
+        clamped.low,
+        percent(clamped.low),
+        clamped.high,
+        percent(clamped.high)
+    );
+
+    if percent(clamped.low + clamped.high) > CLAMP_WARNING_PERCENT {
+        report.push_str(", check --base-level, --noise-strength and --contrast");
+    }
+    report
+}
+
+/// Prints how the pixel at `x`, `y` is computed, without rendering the image.
+fn inspect(config: &Config, x: u32, y: u32) -> Result<(), std::io::Error> {
+    let (width, height) = config.dimensions();
+    let generator = Generator::new(gen_params(config, width, height), gradient(config)?);
+    let inspection = generator.inspect(x, y).ok_or_else(|| {
+        std::io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("Pixel {x},{y} is outside of the {width}x{height} image"),
+        )
+    })?;
+
+    println!("Pixel {x},{y} of seed {}", config.seed.as_ref().unwrap());
+    print_trace(&inspection.trace);
+    if let Some(trace_b) = &inspection.trace_b {
+        println!("Seed B {}", config.seed_b.as_ref().unwrap());
+        print_trace(trace_b);
+        println!("  blended height: {:.6}", inspection.height);
+    }
+
+    let band = match inspection.band {
+        Some(band) => format!(
+            "{band} ({})",
+            generator.gradient().band_name(band).unwrap_or_default()
+        ),
+        None => "none".to_string(),
+    };
+    let [r, g, b] = inspection.color.0;
+    println!("  band: {band}");
+    println!("  color: #{r:02x}{g:02x}{b:02x} ({r}, {g}, {b})");
+    Ok(())
+}
+
+fn print_trace(trace: &PixelTrace) {
+    for (idx, (octave, scale)) in trace.octaves.iter().zip(SCALES).enumerate() {
+        println!("  octave {idx} (scale {scale}): {octave:+.6}");
+    }
+    println!("  octave sum: {:.6}", trace.octave_sum);
+    println!("  white noise: {:.6}", trace.white_noise);
+    println!("  leveled: {:.6}", trace.leveled);
+    println!("  height: {:.6}", trace.height);
+}
+
+/// Gradient used to color the image. A color ramp takes precedence over a palette image.
+fn gradient(config: &Config) -> Result<Gradient, std::io::Error> {
+    if let Some(ramp) = config.color_ramp {
+        return Ok(Gradient::from_ramp(ramp));
+    }
+
+    let mut gradient = match &config.palette_from_image {
+        Some(path) => {
+            let reference = image::open(path).map_err(std::io::Error::other)?;
+            // the palette only needs the overall color distribution, not every pixel
+            let reference = reference.thumbnail(PALETTE_SAMPLE_SIZE, PALETTE_SAMPLE_SIZE);
+            let colors =
+                palette::extract_palette(&reference.to_rgb8(), Gradient::default().band_count());
+            Gradient::from_colors(colors)
+        }
+        None => match (config.palette, config.gradient.as_deref()) {
+            (Some(palette), _) => palette.gradient(),
+            (None, Some(bands)) => BandSpec::gradient(bands),
+            (None, None) => Gradient::default(),
+        },
+    };
+    gradient.continuous = config.color_interpolation_across_all_bands.unwrap();
+    gradient.blend_width = config.band_blend_width.unwrap();
+
+    Ok(gradient)
+}
+
+/// Writes the per channel absolute difference of the images at `a` and `b`, multiplied by
+/// `amplify`, to `out` and prints how many pixels differ.
+fn diff(a: &str, b: &str, out: &str, amplify: f64) -> Result<(), std::io::Error> {
+    let open = |path: &str| {
+        image::open(path)
+            .map(|image| image.to_rgb8())
+            .map_err(|err| std::io::Error::other(format!("Could not read {path}: {err}")))
+    };
+    let (image_a, image_b) = (open(a)?, open(b)?);
+
+    if image_a.dimensions() != image_b.dimensions() {
+        let (width_a, height_a) = image_a.dimensions();
+        let (width_b, height_b) = image_b.dimensions();
+        return Err(std::io::Error::new(
+            ErrorKind::InvalidInput,
+            format!("{a} is {width_a}x{height_a}, but {b} is {width_b}x{height_b}"),
+        ));
+    }
+
+    let mut differing = 0;
+    let difference = RgbImage::from_fn(image_a.width(), image_a.height(), |x, y| {
+        let (pixel_a, pixel_b) = (image_a.get_pixel(x, y), image_b.get_pixel(x, y));
+        if pixel_a != pixel_b {
+            differing += 1;
+        }
+        Rgb(std::array::from_fn(|channel| {
+            let difference = pixel_a[channel].abs_diff(pixel_b[channel]);
+            (difference as f64 * amplify).round().min(255.0) as u8
+        }))
+    });
+    difference.save(out).map_err(std::io::Error::other)?;
+
+    let pixel_count = image_a.width() as u64 * image_a.height() as u64;
+    let share = if pixel_count > 0 {
+        differing as f64 / pixel_count as f64 * 100.0
+    } else {
+        0.0
+    };
+    println!("{differing} of {pixel_count} pixels differ ({share:.2}%)");
+
+    Ok(())
+}
+
+/// One tab separated line per band of `gradient` from lowest to highest, after a header line.
+fn band_report(gradient: &Gradient) -> String {
+    let mut report = "band\tname\tmin\tmax\tcenter\tcolor".to_string();
+    for idx in 0..gradient.band_count() {
+        let [min, max] = gradient.terrain_limits[idx];
+        let center = gradient.terrain_centers[idx];
+        // interpolation yields the band color at the center, or the ramp color if one is used
+        let Rgb([r, g, b]) = gradient.lerp_color(center);
+        report += &format!(
+            "\n{idx}\t{}\t{min:.6}\t{max:.6}\t{center:.6}\t#{r:02x}{g:02x}{b:02x}",
+            gradient.band_name(idx).unwrap_or_default()
+        );
+    }
+    report
+}
+
+/// Height field passes applied before coloring. Terrain is always classified with the default
+/// bands, independent of the gradient used for coloring. Stamps are placed in pixels of an image
+/// `image_width` wide, which the field may be larger or smaller than.
+fn post_process(heights: &mut HeightField, config: &Config, image_width: u32) {
+    if let Some(shape) = config.island_shape {
+        postprocess::island_mask(heights, shape);
+    }
+    if let Some(stamps) = &config.stamps {
+        let scale = heights.width as f64 / image_width as f64;
+        postprocess::apply_stamps(heights, stamps, scale);
+    }
+    if let Some(spec) = &config.depth_curve {
+        // validated when the config is created
+        postprocess::remap_heights(&mut heights.data, &spec.curve().unwrap());
+    }
+    if config.flatten_water.unwrap() {
+        postprocess::flatten_water(
+            &mut heights.data,
+            &Gradient::default(),
+            config.sea_level.unwrap(),
+        );
+    }
+}
+
+/// Opens `path` with the default viewer of the OS. Failing to do so only prints a warning, the
+/// image has already been saved at that point.
+fn open_viewer(path: &str) {
+    let mut command = if cfg!(target_os = "windows") {
+        let mut command = Command::new("cmd");
+        command.args(["/C", "start", ""]);
+        command
+    } else if cfg!(target_os = "macos") {
+        Command::new("open")
+    } else {
+        Command::new("xdg-open")
+    };
+
+    if let Err(err) = command.arg(path).spawn() {
+        eprintln!("Warning: could not open {path} in a viewer: {err}");
+    }
+}
+
+/// Applies the configured passes which change the colors of the generated `image`.
+fn apply_overlays(
+    image: &mut [u8],
+    heights: &HeightField,
+    params: &GenParams,
+    config: &Config,
+) -> Result<(), std::io::Error> {
+    if config.oceans_only_connected.unwrap() {
+        let lakes = postprocess::find_lakes(heights, &Gradient::default());
+        overlay::fill(image, &lakes, Rgb(config.lake_color.unwrap()));
+    }
+    if let Some(threshold) = config.moisture_threshold {
+        let moisture = moisture(params, config)?;
+        overlay::moisture(
+            image,
+            &heights.data,
+            &moisture,
+            &Gradient::default(),
+            threshold,
+            Rgb(config.dry_color.unwrap()),
+            Rgb(config.wet_color.unwrap()),
+        );
+    }
+    if let Some(levels) = config.flat_shading_levels {
+        overlay::flat_shading(image, heights, levels, params.seamless);
+    }
+    if let Some(count) = config.voronoi_regions {
+        let regions = postprocess::regions(
+            heights,
+            &Gradient::default(),
+            params.seed,
+            count as usize,
+            config.regions_include_water.unwrap(),
+        );
+        overlay::regions(
+            image,
+            &regions,
+            params.width as usize,
+            Rgb(config.region_border_color.unwrap()),
+        );
+    }
+    if let Some(snow_line) = config.snow_line {
+        let default = Gradient::default();
+        overlay::snow_line(
+            image,
+            &heights.data,
+            params.width as usize,
+            snow_line,
+            config.snow_falloff.unwrap(),
+            default.colors[TerrainKind::MountainTop as usize],
+        );
+    }
+    if let Some(tint) = config.depth_tint {
+        overlay::depth_tint(image, &heights.data, Rgb(tint.color), tint.strength);
+    }
+    if let Some(count) = config.color_quantize {
+        palette::quantize(image, count as usize);
+    }
+    if let Some(spacing) = config.grid_overlay {
+        overlay::grid(
+            image,
+            params.width as usize,
+            spacing as usize,
+            Rgb(config.grid_color.unwrap()),
+            config.grid_opacity.unwrap(),
+            config.grid_labels.unwrap(),
+        );
+    }
+    if config.checkerboard_debug.unwrap() {
+        let chunk_len = generator::thread_area_size(
+            heights.data.len(),
+            params.thread_count,
+            params.min_thread_chunk,
+        );
+        overlay::tint_chunks(image, chunk_len);
+    }
+
+    Ok(())
+}
+
+/// Renders the image of `config`, `index` being its position in a batch.
+fn render(config: &Config, index: usize) -> Result<(), std::io::Error> {
+    let _rendering = RenderGuard::new();
+    // the base level has no effect on loaded height fields
+    let tuned;
+    let config = match config.land_ratio_target {
+        Some(target) if config.input_heightmap.is_none() => {
+            let (level, land) = tune_base_level(config, target)?;
+            let to_stdout = output_path(config, index)? == STDOUT_PATH;
+            if (land - target).abs() > config.land_ratio_tolerance.unwrap() {
+                status!(
+                    to_stdout,
+                    "No base level gives {:.1}% land, using the closest one",
+                    target * 100.0
+                );
+            }
+            status!(
+                to_stdout,
+                "Base level {level:.4} gives {:.1}% land",
+                land * 100.0
+            );
+            tuned = Config {
+                base_level: Some(level),
+                ..config.clone()
+            };
+            &tuned
+        }
+        _ => config,
+    };
+
+    let input = config
+        .input_heightmap
+        .as_deref()
+        .map(load_heightmap)
+        .transpose()?;
+    let (width, height) = match &input {
+        Some(input) => (input.width, input.height),
+        None => config.dimensions(),
+    };
+
+    if let (Some(limit), None) = (config.limit_memory, &input) {
+        let resized = config.downscale_filter.unwrap().filter_type().is_some();
+        let needed = render_memory(width, height, config.aa.unwrap(), resized);
+        if needed > limit.saturating_mul(MEGABYTE) {
+            let output_path = output_path(config, index)?;
+            return render_bands(config, &output_path, needed, limit.saturating_mul(MEGABYTE));
+        }
+    }
+    let output_path = &output_path(config, index)?;
+    // stdout is kept free for the image data in that case
+    let to_stdout = output_path == STDOUT_PATH;
+
+    if config.dump_config.unwrap() {
+        status!(to_stdout, "{config}");
+    }
+
+    let verbosity = config.verbose.unwrap();
+    let verbose = verbosity > 0;
+
+    if verbose {
+        status!(to_stdout, "Generating...");
+    }
+
+    let start = Instant::now();
+    let mut timings = Timings::new();
+
+    // dimensions are validated when the config is created, those of the input when it is loaded
+    let mut image: Vec<u8> = vec![0; generator::buffer_len(width, height).unwrap()];
+    let mut params = gen_params(config, width, height);
+    if input.is_some() {
+        // the input has a single height per pixel
+        params.aa_factor = 1;
+    }
+    let gradient = gradient(config)?;
+    timings.lap("setup");
+
+    if config.seed_display.unwrap() {
+        status!(to_stdout, "{}", seed_display(config, &params));
+    }
+
+    if config.band_report.unwrap() {
+        status!(to_stdout, "{}", band_report(&gradient));
+    }
+
+    let profiler = config.profile.as_deref().map(Profiler::start).transpose()?;
+    let (heights, clamped) = generate(&mut image, &params, &gradient, config, input, &mut timings)?;
+    if let Some(profiler) = profiler {
+        profiler.finish()?;
+    }
+    apply_overlays(&mut image, &heights, &params, config)?;
+    timings.lap("coloring");
     let end = Instant::now();
     let duration = end - start;
 
     if verbose {
-        println!(
+        status!(
+            to_stdout,
             "Done! Took {:.3?} (~ {} px / sec)",
             end - start,
             // calculate number of pixels per second
-            (((width * height) as f64 / duration.as_secs_f64()) as u64)
+            ((width as f64 * height as f64 / duration.as_secs_f64()) as u64)
                 // format result as comma split 1000s like in this number: 10,000,000
                 .to_string()
                 .as_bytes()
@@ -61,18 +1643,266 @@ fn main() -> Result<(), std::io::Error> {
                 .unwrap()
                 .join(",")
         );
-        println!("Writing output to: {output_path}");
+        if config.input_heightmap.is_none() {
+            status!(to_stdout, "{}", clamp_report(clamped));
+        }
+        if config.output_multiple.is_none() {
+            status!(to_stdout, "Writing output to: {output_path}");
+        }
     }
 
-    let image: ImageBuffer<Rgb<u8>, Vec<u8>> =
-        match ImageBuffer::from_vec(config.width.unwrap(), config.height.unwrap(), image) {
-            Some(image) => image,
-            None => panic!("Could not create image from vector data!"),
+    if let Some(stats_path) = &config.stats_json {
+        check_interrupted()?;
+        let stats = HeightStats::new(&heights.data, &Gradient::default());
+        let mut report = StatsReport::new(
+            config.seed.as_ref().unwrap(),
+            params.seed,
+            width,
+            height,
+            &stats,
+            // the duration differs between runs
+            if config.reproducible.unwrap() {
+                Duration::ZERO
+            } else {
+                duration
+            },
+        );
+        report.roughness_radius = config.roughness;
+        report.write(stats_path)?;
+        timings.lap("statistics");
+    }
+
+    let image = generator::image_from_vec(width, height, image).map_err(std::io::Error::other)?;
+
+    if let Some(outputs) = &config.output_multiple {
+        check_interrupted()?;
+        write_outputs(&image, &heights, &outputs.0, config, to_stdout)?;
+        timings.lap("save");
+    } else {
+        let format = output_format(config, output_path)?;
+        let mut encoded = Cursor::new(Vec::new());
+        image
+            .write_to(&mut encoded, format)
+            .map_err(std::io::Error::other)?;
+        timings.lap("encoding");
+        check_interrupted()?;
+
+        if to_stdout {
+            let mut stdout = std::io::stdout().lock();
+            stdout.write_all(&encoded.into_inner())?;
+            stdout.flush()?;
+        } else {
+            std::fs::write(output_path, encoded.into_inner())?;
+        }
+        timings.lap("save");
+
+        if config.open.unwrap() && !to_stdout {
+            open_viewer(output_path);
+        }
+    }
+
+    if verbosity > 1 {
+        status!(to_stdout, "{timings}");
+    }
+
+    if let Some(preview_path) = &config.tile_preview {
+        check_interrupted()?;
+        let mut preview = RgbImage::new(width.saturating_mul(3), height.saturating_mul(3));
+        imageops::tile(&mut preview, &image);
+        preview.save(preview_path).map_err(std::io::Error::other)?;
+        status!(to_stdout, "Wrote tile preview to: {preview_path}");
+    }
+
+    if let Some(radius) = config.roughness {
+        check_interrupted()?;
+        let roughness_path = suffixed_path(output_path, "roughness", Some("png"));
+        write_roughness(&heights, radius, config.seamless.unwrap(), &roughness_path)?;
+        status!(to_stdout, "Wrote roughness map to: {roughness_path}");
+    }
+
+    if let Some(histogram_path) = &config.histogram {
+        check_interrupted()?;
+        write_histogram(
+            &heights.data,
+            config.histogram_buckets.unwrap(),
+            &Gradient::default(),
+            histogram_path,
+        )?;
+        status!(to_stdout, "Wrote histogram to: {histogram_path}");
+    }
+
+    if let Some(bands_dir) = &config.export_bands {
+        check_interrupted()?;
+        let skipped = write_band_masks(&heights, &Gradient::default(), bands_dir)?;
+        status!(to_stdout, "Wrote terrain masks to: {bands_dir}");
+        if !skipped.is_empty() {
+            let names: Vec<&str> = skipped.iter().map(TerrainKind::name).collect();
+            status!(
+                to_stdout,
+                "Skipped masks of terrain without coverage: {}",
+                names.join(", ")
+            );
+        }
+    }
+
+    if let Some(obj_path) = &config.obj {
+        check_interrupted()?;
+        let step = config
+            .mesh_step
+            .map(|step| step as usize)
+            .unwrap_or_else(|| export::mesh_step(width, height));
+
+        if verbose {
+            status!(
+                to_stdout,
+                "Writing mesh with a step of {step} px to: {obj_path}"
+            );
+        }
+
+        let mut writer = BufWriter::new(File::create(obj_path)?);
+        export::write_obj(&mut writer, &heights, step, config.z_scale.unwrap())?;
+    }
+
+    if let Some(svg_path) = &config.emit_svg {
+        check_interrupted()?;
+        let style = export::SvgStyle {
+            contour_interval: config.contour_interval.unwrap(),
+            stroke: Rgb(config.contour_color.unwrap()),
+            stroke_width: config.contour_width.unwrap(),
+            tolerance: config
+                .svg_simplify
+                .unwrap_or_else(|| export::svg_tolerance(width, height)),
         };
+        let bands = config.svg_fill_bands.unwrap().then_some(&gradient);
 
-    image
-        .save(output_path)
-        .map_err(|err| std::io::Error::new(std::io::ErrorKind::Other, err))?;
+        let mut writer = BufWriter::new(File::create(svg_path)?);
+        export::write_svg(&mut writer, &heights, &style, bands)?;
+        status!(to_stdout, "Wrote contour map to: {svg_path}");
+    }
+
+    if let Some(tiles_path) = &config.tileset_index {
+        check_interrupted()?;
+        let ids = config.tile_ids.as_ref().map(TileIds::ids);
+        let transitions = config.tile_transitions.as_ref().map(TileIds::ids);
+        // validated when the config is created
+        let (ids, transitions) = (
+            ids.transpose().unwrap().unwrap_or_default(),
+            transitions.transpose().unwrap().unwrap_or_default(),
+        );
+        let mut mapping = export::TileMapping::default();
+        for (idx, id) in ids.into_iter().enumerate() {
+            mapping.ids[idx] = id.unwrap_or(mapping.ids[idx]);
+        }
+        mapping.transitions = transitions;
+
+        let tiles = export::tile_indices(&heights, &Gradient::default(), &mapping);
+        let mut writer = BufWriter::new(File::create(tiles_path)?);
+        // the format is validated when the config is created
+        match TileFormat::from_path(tiles_path).unwrap() {
+            TileFormat::Csv => export::write_tile_csv(&mut writer, &tiles, width as usize)?,
+            TileFormat::Tmx => export::write_tmx(
+                &mut writer,
+                &tiles,
+                (width, height),
+                config.tileset_source.as_deref().unwrap(),
+                config.tileset_tile_size.unwrap(),
+            )?,
+        }
+        status!(
+            to_stdout,
+            "Wrote {width}x{height} tile IDs to: {tiles_path}"
+        );
+    }
+
+    if let Some(tiff_path) = &config.geotiff {
+        check_interrupted()?;
+        let bbox = config.bbox.map(bounding_box).unwrap_or(BoundingBox::WORLD);
+        let crs = config.crs.unwrap();
+        let mut writer = BufWriter::new(File::create(tiff_path)?);
+        export::write_geotiff(&mut writer, &heights, bbox, crs)?;
+        status!(
+            to_stdout,
+            "Wrote {width}x{height} GeoTIFF in EPSG:{crs} to: {tiff_path}"
+        );
+    }
+
+    if let Some(raw_path) = &config.raw {
+        check_interrupted()?;
+        let mut writer = BufWriter::new(File::create(raw_path)?);
+        export::write_raw_f32(&mut writer, &heights.data)?;
+        status!(
+            to_stdout,
+            "Wrote {width}x{height} f32 height field to: {raw_path}"
+        );
+    }
+
+    if let Some(raw_path) = &config.raw_f64 {
+        check_interrupted()?;
+        let mut writer = BufWriter::new(File::create(raw_path)?);
+        export::write_raw_f64(&mut writer, &heights.data)?;
+        status!(
+            to_stdout,
+            "Wrote {width}x{height} f64 height field to: {raw_path}"
+        );
+    }
+
+    if config.check_planet.unwrap() {
+        check_interrupted()?;
+        check_planet(&params, config.check_planet_threshold.unwrap(), to_stdout)?;
+    }
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Files written to `dir` by rendering with `args`, sorted by name.
+    fn render_files(dir: &Path, args: &[&str]) -> Vec<(String, Vec<u8>)> {
+        let _ = std::fs::remove_dir_all(dir);
+        let dir_arg = dir.to_string_lossy();
+        let stats_path = dir.join("stats.json");
+        let stats_arg = stats_path.to_string_lossy();
+        let config_args = [
+            &["--output-dir", &dir_arg, "--stats-json", &stats_arg],
+            args,
+        ]
+        .concat();
+        let config = Config::from_args(&config_args).unwrap();
+        render(&config, 1).unwrap();
+
+        let mut files: Vec<(String, Vec<u8>)> = std::fs::read_dir(dir)
+            .unwrap()
+            .map(|entry| {
+                let path = entry.unwrap().path();
+                let name = path.file_name().unwrap().to_string_lossy().into_owned();
+                (name, std::fs::read(&path).unwrap())
+            })
+            .collect();
+        files.sort();
+        std::fs::remove_dir_all(dir).unwrap();
+        files
+    }
+
+    #[test]
+    fn reproducible_renders_are_identical() {
+        let dir =
+            std::env::temp_dir().join(format!("generate-reproducible-{}", std::process::id()));
+        let args = [
+            "--reproducible",
+            "--width",
+            "64",
+            "--height",
+            "48",
+            "--name-template",
+            "{seed}_{timestamp}.png",
+        ];
+
+        let first = render_files(&dir.join("first"), &args);
+        let second = render_files(&dir.join("second"), &args);
+        assert_eq!(first.len(), 2);
+        assert_eq!(first, second);
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+}
@@ -0,0 +1,50 @@
+//! CPU profiling of the generation phase, only available with the `profiling` feature.
+
+/// Samples the call stacks of the process while it is alive.
+#[cfg(feature = "profiling")]
+pub struct Profiler {
+    guard: pprof::ProfilerGuard<'static>,
+    path: String,
+}
+
+#[cfg(feature = "profiling")]
+impl Profiler {
+    /// Sampling frequency in Hz.
+    const FREQUENCY: i32 = 1000;
+
+    pub fn start(path: &str) -> Result<Self, std::io::Error> {
+        let guard = pprof::ProfilerGuardBuilder::default()
+            .frequency(Self::FREQUENCY)
+            .blocklist(&["libc", "libgcc", "pthread", "vdso"])
+            .build()
+            .map_err(std::io::Error::other)?;
+
+        Ok(Self {
+            guard,
+            path: path.to_string(),
+        })
+    }
+
+    /// Stops profiling and writes the samples as flamegraph SVG.
+    pub fn finish(self) -> Result<(), std::io::Error> {
+        let report = self.guard.report().build().map_err(std::io::Error::other)?;
+        let file = std::fs::File::create(&self.path)?;
+        report.flamegraph(file).map_err(std::io::Error::other)
+    }
+}
+
+/// Stand-in when built without the `profiling` feature, profiling is skipped with a warning.
+#[cfg(not(feature = "profiling"))]
+pub struct Profiler;
+
+#[cfg(not(feature = "profiling"))]
+impl Profiler {
+    pub fn start(path: &str) -> Result<Self, std::io::Error> {
+        eprintln!("Warning: not writing profile to {path}, built without the `profiling` feature");
+        Ok(Self)
+    }
+
+    pub fn finish(self) -> Result<(), std::io::Error> {
+        Ok(())
+    }
+}
@@ -0,0 +1,47 @@
+use map_generation::{
+    gradient::{Gradient, TerrainKind},
+    heightfield::HeightField,
+    postprocess,
+    stats::HeightStats,
+};
+
+/// Measures of a map which seeds are ranked by, each as a share of all pixels between 0 and 1.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MapMetrics {
+    /// shore and higher terrain
+    pub land: f64,
+    /// mountains and mountain tops
+    pub mountains: f64,
+    /// the largest connected land area
+    pub largest_landmass: f64,
+}
+
+impl MapMetrics {
+    /// Measures `heights`, classifying each height with `gradient`.
+    pub fn new(heights: &HeightField, gradient: &Gradient) -> Self {
+        let stats = HeightStats::new(&heights.data, gradient);
+        let share = |count: usize| count as f64 / stats.pixel_count.max(1) as f64;
+        let land = (TerrainKind::Shore as usize..TerrainKind::COUNT)
+            .map(|idx| stats.count(TerrainKind::from(idx)))
+            .sum();
+        let mountains = stats.count(TerrainKind::Mountains) + stats.count(TerrainKind::MountainTop);
+        let largest_landmass = postprocess::landmasses(heights, gradient)
+            .first()
+            .copied()
+            .unwrap_or(0);
+
+        Self {
+            land: share(land),
+            mountains: share(mountains),
+            largest_landmass: share(largest_landmass),
+        }
+    }
+
+    /// Weighted distance of the metrics to `targets`, lower is better. `weights` are in the order
+    /// land, mountains, largest landmass.
+    pub fn score(&self, targets: &MapMetrics, weights: [f64; 3]) -> f64 {
+        weights[0] * (self.land - targets.land).abs()
+            + weights[1] * (self.mountains - targets.mountains).abs()
+            + weights[2] * (self.largest_landmass - targets.largest_landmass).abs()
+    }
+}
@@ -0,0 +1,71 @@
+use std::{fs::File, io::BufWriter, time::Duration};
+
+use map_generation::{generator, gradient::TerrainKind, stats::HeightStats};
+
+/// Machine readable statistics of a render, written by `--stats-json`.
+#[derive(serde::Serialize)]
+pub struct StatsReport {
+    pub seed: String,
+    /// numeric seed the seed string is hashed to
+    pub hashed_seed: u64,
+    /// part of the hashed seed the noise functions are created with
+    pub noise_seed: u32,
+    pub width: u32,
+    pub height: u32,
+    pub min_height: f64,
+    pub max_height: f64,
+    pub mean_height: f64,
+    pub terrain: Vec<TerrainStats>,
+    pub duration_secs: f64,
+    /// radius of the roughness map, if one was written
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub roughness_radius: Option<u32>,
+}
+
+#[derive(serde::Serialize)]
+pub struct TerrainStats {
+    pub kind: String,
+    pub count: usize,
+    pub percentage: f64,
+}
+
+impl StatsReport {
+    pub fn new(
+        seed: &str,
+        hashed_seed: u64,
+        width: u32,
+        height: u32,
+        stats: &HeightStats,
+        duration: Duration,
+    ) -> Self {
+        let terrain = (0..stats.terrain_counts.len())
+            .map(|idx| {
+                let kind = TerrainKind::from(idx);
+                TerrainStats {
+                    kind: kind.name().to_string(),
+                    count: stats.count(kind),
+                    percentage: stats.percentage(kind),
+                }
+            })
+            .collect();
+
+        Self {
+            seed: seed.to_string(),
+            hashed_seed,
+            noise_seed: generator::noise_seed(hashed_seed),
+            width,
+            height,
+            min_height: stats.min,
+            max_height: stats.max,
+            mean_height: stats.mean,
+            terrain,
+            duration_secs: duration.as_secs_f64(),
+            roughness_radius: None,
+        }
+    }
+
+    pub fn write(&self, path: &str) -> Result<(), std::io::Error> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self).map_err(std::io::Error::other)
+    }
+}
@@ -0,0 +1,42 @@
+use std::{
+    fmt::Display,
+    time::{Duration, Instant},
+};
+
+/// Durations of the phases of a render, printed with `-vv`.
+pub struct Timings {
+    phases: Vec<(&'static str, Duration)>,
+    last: Instant,
+}
+
+impl Timings {
+    pub fn new() -> Self {
+        Self {
+            phases: Vec::new(),
+            last: Instant::now(),
+        }
+    }
+
+    /// Ends the current phase and records its duration under `name`.
+    pub fn lap(&mut self, name: &'static str) {
+        let now = Instant::now();
+        self.phases.push((name, now - self.last));
+        self.last = now;
+    }
+}
+
+impl Display for Timings {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let total: Duration = self.phases.iter().map(|(_, duration)| *duration).sum();
+
+        writeln!(f, "Timings:")?;
+        for (name, duration) in &self.phases {
+            writeln!(
+                f,
+                "  {name:<16} {duration:>10.3?} ({:>5.1} %)",
+                duration.as_secs_f64() / total.as_secs_f64() * 100.0
+            )?;
+        }
+        write!(f, "  {:<16} {total:>10.3?}", "total")
+    }
+}
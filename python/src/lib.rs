@@ -0,0 +1,96 @@
+//! Python bindings for `map_generation`.
+//!
+//! Built with maturin from this directory, e.g. `pip install ./python`. They live in their own
+//! crate so the native library does not depend on `pyo3`.
+
+#[cfg(feature = "python")]
+mod bindings {
+    use std::io::Cursor;
+
+    use image::ImageFormat;
+    use map_generation::generator::{self, GenParams};
+    use pyo3::exceptions::{PyRuntimeError, PyValueError};
+    use pyo3::prelude::*;
+    use pyo3::types::PyBytes;
+
+    fn validate(width: u32, height: u32, base_level: f64, noise_strength: f64) -> PyResult<()> {
+        if width == 0 || height == 0 {
+            return Err(PyValueError::new_err(
+                "Width and height must be at least 1!",
+            ));
+        }
+        if generator::buffer_len(width, height).is_none() {
+            return Err(PyValueError::new_err(format!(
+                "Image of {width}x{height} pixels is too large!"
+            )));
+        }
+        if !(0.0..=1.0).contains(&base_level) {
+            return Err(PyValueError::new_err("Base level must be between 0 and 1!"));
+        }
+        if noise_strength < 0.0 {
+            return Err(PyValueError::new_err(
+                "Noise strength must not be negative!",
+            ));
+        }
+        Ok(())
+    }
+
+    /// Generates a map and returns it encoded as PNG.
+    #[pyfunction]
+    fn generate(
+        py: Python<'_>,
+        seed: u64,
+        width: u32,
+        height: u32,
+        base_level: f64,
+        noise_strength: f64,
+    ) -> PyResult<PyObject> {
+        validate(width, height, base_level, noise_strength)?;
+
+        let png = py.allow_threads(|| {
+            let mut image = vec![0; generator::buffer_len(width, height).unwrap()];
+            let params = GenParams::new(seed, width, height, base_level, noise_strength);
+            generator::generate(&mut image, &params, None);
+
+            let image =
+                generator::image_from_vec(width, height, image).map_err(|err| err.to_string())?;
+
+            let mut png = Vec::new();
+            image
+                .write_to(&mut Cursor::new(&mut png), ImageFormat::Png)
+                .map_err(|err| err.to_string())?;
+            Ok::<_, String>(png)
+        });
+
+        png.map(|png| PyBytes::new_bound(py, &png).into())
+            .map_err(PyRuntimeError::new_err)
+    }
+
+    /// Generates the raw height field as a flat, row-major list of levels between 0 and 1.
+    #[pyfunction]
+    fn generate_heightmap(
+        py: Python<'_>,
+        seed: u64,
+        width: u32,
+        height: u32,
+        base_level: f64,
+        noise_strength: f64,
+    ) -> PyResult<Vec<f64>> {
+        validate(width, height, base_level, noise_strength)?;
+
+        py.allow_threads(|| {
+            let mut heights = vec![0.0; generator::pixel_count(width, height).unwrap()];
+            let params = GenParams::new(seed, width, height, base_level, noise_strength);
+            generator::generate_heightmap(&mut heights, &params).map(|_| heights)
+        })
+        .map_err(|err| PyRuntimeError::new_err(err.to_string()))
+    }
+
+    #[pymodule]
+    #[pyo3(name = "map_generation")]
+    fn map_generation_py(m: &Bound<'_, PyModule>) -> PyResult<()> {
+        m.add_function(wrap_pyfunction!(generate, m)?)?;
+        m.add_function(wrap_pyfunction!(generate_heightmap, m)?)?;
+        Ok(())
+    }
+}
@@ -0,0 +1,250 @@
+//! Contour lines of a height field, e.g. for topographic maps.
+
+use std::collections::{HashMap, VecDeque};
+
+use crate::heightfield::HeightField;
+
+/// Points of a line in pixel coordinates, the top left corner of the image being `(0, 0)`. Closed
+/// lines end with their first point.
+pub type Polyline = Vec<(f64, f64)>;
+
+/// Lines along which the height field crosses `level`, traced with marching squares.
+///
+/// Heights are located at the pixel centers and interpolated linearly in between. Lines running
+/// into the border of the image end there, all others are closed. Where four pixels are
+/// ambiguous, their average decides whether the higher or the lower ones are connected.
+///
+/// If `closed` is set, the field is surrounded by heights below every level instead. Then every
+/// line is closed, running along the border of the image where the area at or above `level`
+/// touches it, so the lines outline that area and can be filled as polygons with the even-odd
+/// rule.
+///
+/// # Examples
+/// A cone has a single circular contour at each level.
+/// ```rust
+/// use map_generation::contour::contour_lines;
+/// use map_generation::heightfield::HeightField;
+///
+/// let mut heights = HeightField::new(100, 100);
+/// for y in 0..100 {
+///     for x in 0..100 {
+///         let (dx, dy) = (x as f64 - 49.5, y as f64 - 49.5);
+///         heights.data[y * 100 + x] = 1.0 - (dx * dx + dy * dy).sqrt() / 50.0;
+///     }
+/// }
+///
+/// let lines = contour_lines(&heights, 0.5, false);
+/// assert_eq!(lines.len(), 1);
+/// assert_eq!(lines[0].first(), lines[0].last());
+/// for (x, y) in &lines[0] {
+///     let radius = ((x - 50.0).powi(2) + (y - 50.0).powi(2)).sqrt();
+///     assert!((radius - 25.0).abs() < 0.1);
+/// }
+/// ```
+pub fn contour_lines(heights: &HeightField, level: f64, closed: bool) -> Vec<Polyline> {
+    let grid = Grid::new(heights, closed);
+    let segments = grid.segments(level);
+
+    // every edge is crossed by the segments of at most the two cells sharing it
+    let mut by_edge: HashMap<u64, [Option<usize>; 2]> = HashMap::new();
+    for (idx, segment) in segments.iter().enumerate() {
+        for key in segment {
+            let entry = by_edge.entry(*key).or_default();
+            let free = if entry[0].is_none() { 0 } else { 1 };
+            entry[free] = Some(idx);
+        }
+    }
+
+    let mut used = vec![false; segments.len()];
+    let next = |key: u64, used: &[bool]| {
+        by_edge[&key]
+            .into_iter()
+            .flatten()
+            .find(|idx| !used[*idx])
+            .map(|idx| {
+                let [a, b] = segments[idx];
+                (idx, if a == key { b } else { a })
+            })
+    };
+
+    let mut lines = Vec::new();
+    for start in 0..segments.len() {
+        if used[start] {
+            continue;
+        }
+        used[start] = true;
+
+        let mut keys = VecDeque::from(segments[start]);
+        while let Some((idx, key)) = next(*keys.back().unwrap(), &used) {
+            used[idx] = true;
+            keys.push_back(key);
+        }
+        // lines ending at the border of the image are traced in both directions
+        if keys.front() != keys.back() {
+            while let Some((idx, key)) = next(*keys.front().unwrap(), &used) {
+                used[idx] = true;
+                keys.push_front(key);
+            }
+        }
+
+        lines.push(
+            keys.into_iter()
+                .map(|key| grid.crossing(key, level))
+                .collect(),
+        );
+    }
+    lines
+}
+
+/// Removes points of `line` which deviate less than `tolerance` pixels from the simplified line,
+/// with the Ramer-Douglas-Peucker algorithm.
+///
+/// The first and last point are always kept, so closed lines stay closed.
+pub fn simplify(line: &[(f64, f64)], tolerance: f64) -> Polyline {
+    if line.len() < 3 {
+        return line.to_vec();
+    }
+
+    let mut keep = vec![false; line.len()];
+    keep[0] = true;
+    keep[line.len() - 1] = true;
+
+    let mut ranges = vec![(0, line.len() - 1)];
+    while let Some((first, last)) = ranges.pop() {
+        let farthest = (first + 1..last)
+            .map(|idx| (idx, distance_to_segment(line[idx], line[first], line[last])))
+            .max_by(|(_, a), (_, b)| a.total_cmp(b));
+
+        if let Some((idx, distance)) = farthest {
+            if distance > tolerance {
+                keep[idx] = true;
+                ranges.push((first, idx));
+                ranges.push((idx, last));
+            }
+        }
+    }
+
+    line.iter()
+        .zip(keep)
+        .filter_map(|(point, keep)| keep.then_some(*point))
+        .collect()
+}
+
+/// Distance of `point` to the segment from `a` to `b`.
+fn distance_to_segment(point: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    let (dx, dy) = (b.0 - a.0, b.1 - a.1);
+    let length_sq = dx * dx + dy * dy;
+    let t = if length_sq > 0.0 {
+        (((point.0 - a.0) * dx + (point.1 - a.1) * dy) / length_sq).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    let (x, y) = (a.0 + t * dx, a.1 + t * dy);
+    ((point.0 - x).powi(2) + (point.1 - y).powi(2)).sqrt()
+}
+
+/// Heights at the pixel centers, optionally padded with a border below every level.
+struct Grid<'a> {
+    heights: &'a HeightField,
+    /// width of the padding, 1 if padded and 0 otherwise
+    pad: i64,
+    width: i64,
+    height: i64,
+}
+
+impl<'a> Grid<'a> {
+    fn new(heights: &'a HeightField, padded: bool) -> Self {
+        let pad = padded as i64;
+        Self {
+            heights,
+            pad,
+            width: heights.width as i64 + 2 * pad,
+            height: heights.height as i64 + 2 * pad,
+        }
+    }
+
+    /// Height at a grid position, negative infinity in the padding.
+    fn value(&self, x: i64, y: i64) -> f64 {
+        let (x, y) = (x - self.pad, y - self.pad);
+        if x < 0 || y < 0 || x >= self.heights.width as i64 || y >= self.heights.height as i64 {
+            return f64::NEG_INFINITY;
+        }
+        self.heights.get(x as u32, y as u32)
+    }
+
+    /// Key of the edge from `x`, `y` to the right or, if `down` is set, downwards.
+    fn key(&self, x: i64, y: i64, down: bool) -> u64 {
+        (y * self.width + x) as u64 * 2 + down as u64
+    }
+
+    /// Segments crossing `level` as pairs of the keys of the edges they connect.
+    fn segments(&self, level: f64) -> Vec<[u64; 2]> {
+        let mut segments = Vec::new();
+
+        for y in 0..self.height - 1 {
+            for x in 0..self.width - 1 {
+                let corners = [
+                    self.value(x, y),
+                    self.value(x + 1, y),
+                    self.value(x + 1, y + 1),
+                    self.value(x, y + 1),
+                ];
+                let [top_left, top_right, bottom_right, bottom_left] =
+                    corners.map(|height| height >= level);
+                let (top, right) = (self.key(x, y, false), self.key(x + 1, y, true));
+                let (bottom, left) = (self.key(x, y + 1, false), self.key(x, y, true));
+
+                let mut crossed = [0; 4];
+                let mut count = 0;
+                for (is_crossed, key) in [
+                    (top_left != top_right, top),
+                    (top_right != bottom_right, right),
+                    (bottom_left != bottom_right, bottom),
+                    (top_left != bottom_left, left),
+                ] {
+                    if is_crossed {
+                        crossed[count] = key;
+                        count += 1;
+                    }
+                }
+
+                match crossed[..count] {
+                    [a, b] => segments.push([a, b]),
+                    [_, _, _, _] => {
+                        // saddle, the corners on the other side of the level than the center are cut off
+                        let center = corners.iter().sum::<f64>() / 4.0 >= level;
+                        if top_left == center {
+                            segments.push([top, right]);
+                            segments.push([bottom, left]);
+                        } else {
+                            segments.push([left, top]);
+                            segments.push([right, bottom]);
+                        }
+                    }
+                    _ => (),
+                }
+            }
+        }
+        segments
+    }
+
+    /// Position in pixel coordinates where the edge of `key` crosses `level`.
+    fn crossing(&self, key: u64, level: f64) -> (f64, f64) {
+        let (idx, down) = ((key / 2) as i64, key % 2 == 1);
+        let (x, y) = (idx % self.width, idx / self.width);
+        let (dx, dy) = if down { (0, 1) } else { (1, 0) };
+
+        let (a, b) = (self.value(x, y), self.value(x + dx, y + dy));
+        // the padding is half a pixel outside of the image, on its border
+        let t = if a.is_finite() && b.is_finite() {
+            (level - a) / (b - a)
+        } else {
+            0.5
+        };
+
+        (
+            (x - self.pad) as f64 + 0.5 + t * dx as f64,
+            (y - self.pad) as f64 + 0.5 + t * dy as f64,
+        )
+    }
+}
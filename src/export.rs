@@ -0,0 +1,619 @@
+use std::io::{self, Write};
+
+use image::{ImageBuffer, Luma, Rgb, RgbImage};
+
+use crate::{
+    contour::{self, Polyline},
+    generator::MAX_HEIGHT,
+    gradient::{Gradient, TerrainKind},
+    heightfield::HeightField,
+};
+
+/// Number of vertices [`mesh_step`] aims to stay below when picking a step automatically.
+pub const MAX_MESH_VERTICES: usize = 1 << 20;
+
+/// Smallest mesh step that keeps a mesh of the given dimensions below [`MAX_MESH_VERTICES`].
+pub fn mesh_step(width: u32, height: u32) -> usize {
+    let pixels = width as f64 * height as f64;
+    (pixels / MAX_MESH_VERTICES as f64).sqrt().ceil().max(1.0) as usize
+}
+
+/// Writes a height field as Wavefront OBJ mesh.
+///
+/// Every `step`th pixel of each row and column becomes a vertex `(x, height * z_scale, y)`, the
+/// last row and column are always included. Neighboring vertices are connected by two triangles.
+///
+/// # Arguments
+///   * `writer` destination of the OBJ data. Should be buffered as many small writes are made.
+///   * `heights` height field to write
+///   * `step` distance between two vertices in pixels. Use [`mesh_step`] for large fields.
+///   * `z_scale` factor applied to the heights to exaggerate or flatten the terrain
+pub fn write_obj<W: Write>(
+    writer: &mut W,
+    heights: &HeightField,
+    step: usize,
+    z_scale: f64,
+) -> io::Result<()> {
+    let columns = grid_positions(heights.width as usize, step);
+    let rows = grid_positions(heights.height as usize, step);
+
+    for y in &rows {
+        for x in &columns {
+            let z = heights.get(*x as u32, *y as u32) * z_scale;
+            writeln!(writer, "v {x} {z} {y}")?;
+        }
+    }
+
+    // OBJ indices start at 1
+    let row_len = columns.len();
+    for row in 0..rows.len().saturating_sub(1) {
+        for column in 0..row_len.saturating_sub(1) {
+            let top_left = row * row_len + column + 1;
+            let top_right = top_left + 1;
+            let bottom_left = top_left + row_len;
+            let bottom_right = bottom_left + 1;
+            writeln!(writer, "f {top_left} {bottom_left} {top_right}")?;
+            writeln!(writer, "f {top_right} {bottom_left} {bottom_right}")?;
+        }
+    }
+
+    writer.flush()
+}
+
+/// Writes a height field as headerless little-endian `f32` values in row-major order.
+///
+/// The format carries no dimensions, they must be known when importing the file again.
+pub fn write_raw_f32<W: Write>(writer: &mut W, heights: &[f64]) -> io::Result<()> {
+    for height in heights {
+        writer.write_all(&(*height as f32).to_le_bytes())?;
+    }
+    writer.flush()
+}
+
+/// Writes a height field as headerless little-endian `f64` values in row-major order.
+///
+/// Same as [`write_raw_f32`], but without losing precision.
+pub fn write_raw_f64<W: Write>(writer: &mut W, heights: &[f64]) -> io::Result<()> {
+    for height in heights {
+        writer.write_all(&height.to_le_bytes())?;
+    }
+    writer.flush()
+}
+
+/// Height field as 16 bit grayscale image, 0.0 being black and 1.0 white.
+///
+/// Keeps far more detail than the 8 bit color image, as needed for displacing terrain meshes.
+pub fn heightmap_image(heights: &HeightField) -> ImageBuffer<Luma<u16>, Vec<u16>> {
+    let pixels = heights
+        .data
+        .iter()
+        .map(|height| (height.clamp(0.0, 1.0) * u16::MAX as f64).round() as u16)
+        .collect();
+    // the height field holds exactly one height per pixel
+    ImageBuffer::from_vec(heights.width, heights.height, pixels).unwrap()
+}
+
+/// Tangent space normal map of a height field, e.g. to light a flat mesh as if it had the
+/// terrain's relief.
+///
+/// Each axis of the unit normal is mapped from -1.0..1.0 to 0..255 in the red, green and blue
+/// channel. Green points up in the image like in OpenGL, flat terrain is `(128, 128, 255)`.
+/// `z_scale` is the height of level 1.0 in pixels, like in [`write_obj`], so the normals match
+/// the mesh. Edges wrap around if `wrap` is set, as needed for seamless maps.
+pub fn normal_map(heights: &HeightField, z_scale: f64, wrap: bool) -> RgbImage {
+    RgbImage::from_fn(heights.width, heights.height, |x, y| {
+        let (slope_x, slope_y) = heights.slope(x as i64, y as i64, wrap);
+        // image rows go down, while the green axis points up
+        let normal = [-slope_x * z_scale, slope_y * z_scale, 1.0];
+        let length = normal.iter().map(|axis| axis * axis).sum::<f64>().sqrt();
+
+        Rgb(normal.map(|axis| ((axis / length * 0.5 + 0.5) * 255.0).round() as u8))
+    })
+}
+
+/// Simplification tolerance for a contour map of the given dimensions.
+///
+/// Half a pixel up to 1024 pixels along the longer side and proportionally more beyond, so larger
+/// maps do not have more points per line than a map of that size.
+pub fn svg_tolerance(width: u32, height: u32) -> f64 {
+    (width.max(height) as f64 / 2048.0).max(0.5)
+}
+
+/// Appearance of a contour map written by [`write_svg`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SvgStyle {
+    /// height difference between neighboring contour lines, must be positive. A line is drawn at
+    /// every multiple of it below [`MAX_HEIGHT`].
+    pub contour_interval: f64,
+    /// color of the contour lines
+    pub stroke: Rgb<u8>,
+    /// width of the contour lines in pixels
+    pub stroke_width: f64,
+    /// largest distance in pixels by which simplified lines may deviate from the traced ones, see
+    /// [`svg_tolerance`]
+    pub tolerance: f64,
+}
+
+/// Writes the contour lines of a height field as SVG image the size of the field.
+///
+/// The lines of each level are traced with [`contour::contour_lines`] and simplified by
+/// `style.tolerance`. If `bands` is given, the area of each of its bands is filled with the color
+/// of the band below the lines, each band as a polygon covering the area from its lower limit up.
+pub fn write_svg<W: Write>(
+    writer: &mut W,
+    heights: &HeightField,
+    style: &SvgStyle,
+    bands: Option<&Gradient>,
+) -> io::Result<()> {
+    let (width, height) = (heights.width, heights.height);
+    writeln!(
+        writer,
+        r#"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}">"#
+    )?;
+
+    if let Some(gradient) = bands.filter(|gradient| gradient.band_count() > 0) {
+        writeln!(writer, r#"<g stroke="none" fill-rule="evenodd">"#)?;
+        let color = |idx: usize| svg_color(gradient.get_color(gradient.terrain_centers[idx]));
+        writeln!(
+            writer,
+            r#"<rect width="{width}" height="{height}" fill="{}"/>"#,
+            color(0)
+        )?;
+        for idx in 1..gradient.band_count() {
+            let lines = contour::contour_lines(heights, gradient.terrain_limits[idx][0], true);
+            write_svg_path(
+                writer,
+                &lines,
+                style.tolerance,
+                &format!(r#"fill="{}""#, color(idx)),
+            )?;
+        }
+        writeln!(writer, "</g>")?;
+    }
+
+    writeln!(
+        writer,
+        r#"<g fill="none" stroke="{}" stroke-width="{}" stroke-linejoin="round" stroke-linecap="round">"#,
+        svg_color(style.stroke),
+        style.stroke_width
+    )?;
+    let levels = (1..)
+        .map(|step| step as f64 * style.contour_interval)
+        .take_while(|level| *level < MAX_HEIGHT);
+    for level in levels {
+        let lines = contour::contour_lines(heights, level, false);
+        write_svg_path(
+            writer,
+            &lines,
+            style.tolerance,
+            &format!(r#"data-level="{level}""#),
+        )?;
+    }
+    writeln!(writer, "</g>")?;
+
+    writeln!(writer, "</svg>")?;
+    writer.flush()
+}
+
+/// Writes `lines` as one SVG path element with the given attributes, skipping it if there are no
+/// lines.
+fn write_svg_path<W: Write>(
+    writer: &mut W,
+    lines: &[Polyline],
+    tolerance: f64,
+    attributes: &str,
+) -> io::Result<()> {
+    if lines.is_empty() {
+        return Ok(());
+    }
+
+    write!(writer, r#"<path {attributes} d=""#)?;
+    for line in lines {
+        let line = contour::simplify(line, tolerance);
+        let closed = line.len() > 2 && line.first() == line.last();
+        // closed lines end with their first point, which `Z` replaces
+        let points = if closed {
+            &line[..line.len() - 1]
+        } else {
+            &line[..]
+        };
+        for (idx, (x, y)) in points.iter().enumerate() {
+            let command = if idx == 0 { "M" } else { "L" };
+            write!(writer, "{command}{x:.2} {y:.2}")?;
+        }
+        if closed {
+            write!(writer, "Z")?;
+        }
+    }
+    writeln!(writer, r#""/>"#)
+}
+
+/// Color as SVG hex notation, e.g. `#1e90ff`.
+fn svg_color(Rgb([r, g, b]): Rgb<u8>) -> String {
+    format!("#{r:02x}{g:02x}{b:02x}")
+}
+
+/// Tile IDs of the terrain kinds, see [`tile_indices`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TileMapping {
+    /// ID of the tile of each terrain kind, indexed by `TerrainKind as usize`
+    pub ids: [u32; TerrainKind::COUNT],
+    /// first ID of a block of 16 transition tiles of each terrain kind, indexed like `ids`. Kinds
+    /// with a block use it instead of their ID, see [`tile_indices`].
+    pub transitions: [Option<u32>; TerrainKind::COUNT],
+}
+
+impl Default for TileMapping {
+    /// Each terrain kind has its index as ID and no transitions.
+    fn default() -> Self {
+        Self {
+            ids: std::array::from_fn(|idx| idx as u32),
+            transitions: [None; TerrainKind::COUNT],
+        }
+    }
+}
+
+/// Tile ID of each cell of a height field in row-major order, e.g. for tile based game engines.
+///
+/// Cells are classified into terrain kinds by `gradient`, which needs one band per
+/// [`TerrainKind`]. Cells without a kind get ID 0. Kinds with transition tiles are auto-tiled: a
+/// mask is built from the neighbors above (1), right (2), below (4) and left (8) which are of a
+/// lower kind, so e.g. shore knows in which directions the water is. The cell gets the tile at the
+/// mask's offset in the block of its kind, where offset 0 is the tile of cells without lower
+/// neighbors. Neighbors beyond the edges count as the same kind.
+///
+/// # Examples
+/// ```rust
+/// use map_generation::export::{tile_indices, TileMapping};
+/// use map_generation::gradient::{Gradient, TerrainKind};
+/// use map_generation::heightfield::HeightField;
+///
+/// let gradient = Gradient::default();
+/// let height = |kind: TerrainKind| gradient.terrain_centers[kind as usize];
+/// let (water, shore) = (height(TerrainKind::Water), height(TerrainKind::Shore));
+/// let heights = HeightField::from_vec(vec![water, shore, shore], 3, 1).unwrap();
+///
+/// let mut mapping = TileMapping::default();
+/// assert_eq!(tile_indices(&heights, &gradient, &mapping), [1, 3, 3]);
+///
+/// // the shore next to the water has water to its left
+/// mapping.transitions[TerrainKind::Shore as usize] = Some(100);
+/// assert_eq!(tile_indices(&heights, &gradient, &mapping), [1, 108, 100]);
+/// ```
+pub fn tile_indices(heights: &HeightField, gradient: &Gradient, mapping: &TileMapping) -> Vec<u32> {
+    let kinds: Vec<Option<TerrainKind>> = heights
+        .data
+        .iter()
+        .map(|height| gradient.terrain_kind(*height))
+        .collect();
+    let (width, height) = (heights.width as i64, heights.height as i64);
+    let kind_at = |x: i64, y: i64| {
+        (x >= 0 && y >= 0 && x < width && y < height).then(|| kinds[(y * width + x) as usize])
+    };
+
+    kinds
+        .iter()
+        .enumerate()
+        .map(|(idx, kind)| {
+            let Some(kind) = kind else {
+                return 0;
+            };
+            let Some(block) = mapping.transitions[*kind as usize] else {
+                return mapping.ids[*kind as usize];
+            };
+
+            let (x, y) = (idx as i64 % width, idx as i64 / width);
+            let mask = [(0, -1), (1, 0), (0, 1), (-1, 0)]
+                .into_iter()
+                .enumerate()
+                .filter(|(_, (dx, dy))| {
+                    kind_at(x + dx, y + dy)
+                        .flatten()
+                        .is_some_and(|neighbor| neighbor < *kind)
+                })
+                .map(|(bit, _)| 1 << bit)
+                .sum::<u32>();
+            block + mask
+        })
+        .collect()
+}
+
+/// Writes tile IDs as CSV, one line per row of `width` IDs.
+pub fn write_tile_csv<W: Write>(writer: &mut W, ids: &[u32], width: usize) -> io::Result<()> {
+    for row in ids.chunks(width.max(1)) {
+        let row: Vec<String> = row.iter().map(u32::to_string).collect();
+        writeln!(writer, "{}", row.join(","))?;
+    }
+    writer.flush()
+}
+
+/// Writes tile IDs as map of the Tiled editor (TMX) with a single layer.
+///
+/// Tiled counts tiles of a map from 1, as 0 is an empty cell, so every ID is increased by one.
+/// The tiles are taken from the external tileset at `tileset`, relative to the map, whose tiles
+/// are `tile_size` pixels large.
+pub fn write_tmx<W: Write>(
+    writer: &mut W,
+    ids: &[u32],
+    (width, height): (u32, u32),
+    tileset: &str,
+    tile_size: u32,
+) -> io::Result<()> {
+    let tileset = tileset
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;");
+
+    writeln!(writer, r#"<?xml version="1.0" encoding="UTF-8"?>"#)?;
+    writeln!(
+        writer,
+        r#"<map version="1.10" orientation="orthogonal" renderorder="right-down" width="{width}" height="{height}" tilewidth="{tile_size}" tileheight="{tile_size}" infinite="0" nextlayerid="2" nextobjectid="1">"#
+    )?;
+    writeln!(writer, r#" <tileset firstgid="1" source="{tileset}"/>"#)?;
+    writeln!(
+        writer,
+        r#" <layer id="1" name="terrain" width="{width}" height="{height}">"#
+    )?;
+    writeln!(writer, r#"  <data encoding="csv">"#)?;
+    let rows: Vec<String> = ids
+        .chunks(width.max(1) as usize)
+        .map(|row| {
+            let row: Vec<String> = row.iter().map(|id| (id + 1).to_string()).collect();
+            row.join(",")
+        })
+        .collect();
+    writeln!(writer, "{}", rows.join(",\n"))?;
+    writeln!(writer, "  </data>")?;
+    writeln!(writer, " </layer>")?;
+    writeln!(writer, "</map>")?;
+    writer.flush()
+}
+
+/// Area covered by a map in the units of its coordinate reference system, e.g. degrees of
+/// longitude (x) and latitude (y).
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    /// western edge of the map
+    pub min_x: f64,
+    /// southern edge of the map
+    pub min_y: f64,
+    /// eastern edge of the map
+    pub max_x: f64,
+    /// northern edge of the map
+    pub max_y: f64,
+}
+
+impl BoundingBox {
+    /// The whole earth in degrees of longitude and latitude.
+    pub const WORLD: Self = Self {
+        min_x: -180.0,
+        min_y: -90.0,
+        max_x: 180.0,
+        max_y: 90.0,
+    };
+
+    /// Checks that the box has finite edges and a positive size. If it is `geographic`, the
+    /// latitudes must lie within -90 and 90 degrees and the longitudes span at most 360 degrees.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use map_generation::export::BoundingBox;
+    ///
+    /// assert!(BoundingBox::WORLD.check(true).is_ok());
+    ///
+    /// let upside_down = BoundingBox { min_y: 50.0, max_y: 40.0, ..BoundingBox::WORLD };
+    /// assert!(upside_down.check(false).is_err());
+    ///
+    /// let beyond_pole = BoundingBox { max_y: 100.0, ..BoundingBox::WORLD };
+    /// assert!(beyond_pole.check(false).is_ok());
+    /// assert!(beyond_pole.check(true).is_err());
+    /// ```
+    pub fn check(&self, geographic: bool) -> Result<(), String> {
+        let edges = [self.min_x, self.min_y, self.max_x, self.max_y];
+        if edges.iter().any(|edge| !edge.is_finite()) {
+            return Err("Bounding box edges must be finite numbers".to_string());
+        }
+        if self.min_x >= self.max_x || self.min_y >= self.max_y {
+            return Err(format!(
+                "Bounding box minimum ({}, {}) must be below its maximum ({}, {})",
+                self.min_x, self.min_y, self.max_x, self.max_y
+            ));
+        }
+        if geographic {
+            if self.min_y < -90.0 || self.max_y > 90.0 {
+                return Err(format!(
+                    "Bounding box latitudes {} to {} must be between -90 and 90",
+                    self.min_y, self.max_y
+                ));
+            }
+            if self.max_x - self.min_x > 360.0 {
+                return Err(format!(
+                    "Bounding box longitudes {} to {} must span at most 360 degrees",
+                    self.min_x, self.max_x
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Whether the EPSG code belongs to a geographic coordinate reference system in degrees, like
+/// EPSG:4326, rather than a projected one like EPSG:3857.
+///
+/// Only looks at the range of the code, geographic systems being numbered from 4000 to 4999.
+pub fn is_geographic(epsg: u16) -> bool {
+    (4000..5000).contains(&epsg)
+}
+
+/// Writes a height field as single band `f32` GeoTIFF, georeferenced to cover `bbox` in the
+/// coordinate reference system of the EPSG code `epsg`, e.g. to import it into GIS software.
+///
+/// The first row of the field is the northern edge of the box. The heights are written
+/// uncompressed as a single strip, so the field must stay below 4 GiB.
+///
+/// # Examples
+/// ```rust
+/// use map_generation::export::{write_geotiff, BoundingBox};
+/// use map_generation::heightfield::HeightField;
+///
+/// let heights = HeightField::from_vec(vec![0.0, 0.25, 0.5, 1.0], 2, 2).unwrap();
+/// let mut tiff = Vec::new();
+/// write_geotiff(&mut tiff, &heights, BoundingBox::WORLD, 4326).unwrap();
+///
+/// assert_eq!(&tiff[..4], b"II*\0");
+/// let data: Vec<f32> = tiff[tiff.len() - 16..]
+///     .chunks(4)
+///     .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+///     .collect();
+/// assert_eq!(data, [0.0, 0.25, 0.5, 1.0]);
+/// ```
+pub fn write_geotiff<W: Write>(
+    writer: &mut W,
+    heights: &HeightField,
+    bbox: BoundingBox,
+    epsg: u16,
+) -> io::Result<()> {
+    let geographic = is_geographic(epsg);
+    bbox.check(geographic)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidInput, err))?;
+    let (width, height) = (heights.width, heights.height);
+    let data_len = u32::try_from(heights.data.len() * 4).map_err(|_| {
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!("{width}x{height} pixels are too many for a TIFF"),
+        )
+    })?;
+
+    // raster space to model space: pixel size and the model position of the top left corner
+    let pixel_scale = [
+        (bbox.max_x - bbox.min_x) / width as f64,
+        (bbox.max_y - bbox.min_y) / height as f64,
+        0.0,
+    ];
+    let tiepoint = [0.0, 0.0, 0.0, bbox.min_x, bbox.max_y, 0.0];
+    // header, then keys as (id, location, count, value), the values all being stored inline
+    let (model_type, crs_key) = if geographic {
+        (GEO_MODEL_GEOGRAPHIC, GEO_KEY_GEOGRAPHIC_TYPE)
+    } else {
+        (GEO_MODEL_PROJECTED, GEO_KEY_PROJECTED_CS_TYPE)
+    };
+    let geo_keys: [[u16; 4]; 4] = [
+        [1, 1, 0, 3],
+        [GEO_KEY_MODEL_TYPE, 0, 1, model_type],
+        [GEO_KEY_RASTER_TYPE, 0, 1, GEO_RASTER_PIXEL_IS_AREA],
+        [crs_key, 0, 1, epsg],
+    ];
+
+    const ENTRY_COUNT: u32 = 14;
+    let ifd_len = 2 + ENTRY_COUNT * 12 + 4;
+    let pixel_scale_offset = 8 + ifd_len;
+    let tiepoint_offset = pixel_scale_offset + 3 * 8;
+    let geo_keys_offset = tiepoint_offset + 6 * 8;
+    let data_offset = geo_keys_offset + 16 * 2;
+
+    writer.write_all(b"II")?;
+    writer.write_all(&42u16.to_le_bytes())?;
+    writer.write_all(&8u32.to_le_bytes())?;
+
+    // entries must be sorted by tag, values of up to 4 bytes are stored in place of their offset
+    writer.write_all(&(ENTRY_COUNT as u16).to_le_bytes())?;
+    let mut entry = |tag: u16, kind: u16, count: u32, value: u32| -> io::Result<()> {
+        writer.write_all(&tag.to_le_bytes())?;
+        writer.write_all(&kind.to_le_bytes())?;
+        writer.write_all(&count.to_le_bytes())?;
+        writer.write_all(&value.to_le_bytes())
+    };
+    entry(256, TIFF_LONG, 1, width)?; // image width
+    entry(257, TIFF_LONG, 1, height)?; // image length
+    entry(258, TIFF_SHORT, 1, 32)?; // bits per sample
+    entry(259, TIFF_SHORT, 1, 1)?; // no compression
+    entry(262, TIFF_SHORT, 1, 1)?; // black is zero
+    entry(273, TIFF_LONG, 1, data_offset)?; // strip offset
+    entry(277, TIFF_SHORT, 1, 1)?; // samples per pixel
+    entry(278, TIFF_LONG, 1, height)?; // rows per strip
+    entry(279, TIFF_LONG, 1, data_len)?; // strip byte count
+    entry(284, TIFF_SHORT, 1, 1)?; // chunky planar configuration
+    entry(339, TIFF_SHORT, 1, 3)?; // floating point samples
+    entry(33550, TIFF_DOUBLE, 3, pixel_scale_offset)?; // model pixel scale
+    entry(33922, TIFF_DOUBLE, 6, tiepoint_offset)?; // model tiepoint
+    entry(34735, TIFF_SHORT, 16, geo_keys_offset)?; // GeoKey directory
+    writer.write_all(&0u32.to_le_bytes())?; // no further images
+
+    for value in pixel_scale.iter().chain(&tiepoint) {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+    for value in geo_keys.iter().flatten() {
+        writer.write_all(&value.to_le_bytes())?;
+    }
+
+    write_raw_f32(writer, &heights.data)
+}
+
+// TIFF field types used by `write_geotiff`
+const TIFF_SHORT: u16 = 3;
+const TIFF_LONG: u16 = 4;
+const TIFF_DOUBLE: u16 = 12;
+
+// GeoKeys and their values used by `write_geotiff`
+const GEO_KEY_MODEL_TYPE: u16 = 1024;
+const GEO_KEY_RASTER_TYPE: u16 = 1025;
+const GEO_KEY_GEOGRAPHIC_TYPE: u16 = 2048;
+const GEO_KEY_PROJECTED_CS_TYPE: u16 = 3072;
+const GEO_MODEL_PROJECTED: u16 = 1;
+const GEO_MODEL_GEOGRAPHIC: u16 = 2;
+const GEO_RASTER_PIXEL_IS_AREA: u16 = 1;
+
+/// Every `step`th position in `0..len` plus the last one.
+fn grid_positions(len: usize, step: usize) -> Vec<usize> {
+    let mut positions: Vec<usize> = (0..len).step_by(step.max(1)).collect();
+    if positions.last() != Some(&(len - 1)) {
+        positions.push(len - 1);
+    }
+    positions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{self, GenParams, MIN_HEIGHT};
+
+    fn heights() -> Vec<f64> {
+        let mut heights = vec![0.0; 40 * 30];
+        generator::generate_heightmap(&mut heights, &GenParams::new(12345, 40, 30, 0.2, 0.1))
+            .unwrap();
+        heights.extend([MIN_HEIGHT, MAX_HEIGHT, 0.0, 1.0]);
+        heights
+    }
+
+    #[test]
+    fn raw_f32_round_trip() {
+        let heights = heights();
+        let mut raw = Vec::new();
+        write_raw_f32(&mut raw, &heights).unwrap();
+
+        assert_eq!(raw.len(), heights.len() * 4);
+        let read: Vec<f32> = raw
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        for (read, height) in read.iter().zip(&heights) {
+            assert_eq!(*read, *height as f32);
+            assert!((*read as f64 - height).abs() <= 1e-7);
+        }
+    }
+
+    #[test]
+    fn raw_f64_round_trip() {
+        let heights = heights();
+        let mut raw = Vec::new();
+        write_raw_f64(&mut raw, &heights).unwrap();
+
+        assert_eq!(raw.len(), heights.len() * 8);
+        let read: Vec<f64> = raw
+            .chunks_exact(8)
+            .map(|chunk| f64::from_le_bytes(chunk.try_into().unwrap()))
+            .collect();
+        assert_eq!(read, heights);
+    }
+}
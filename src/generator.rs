@@ -1,7 +1,637 @@
-use crate::gradient::*;
+use crate::{gradient::*, hasher::JitterRng};
+
+use std::{
+    hash::Hash,
+    str::FromStr,
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
 
 use noise::{NoiseFn, Perlin};
-use rand::Rng;
+
+/// Largest number of pixels a generated image may have.
+///
+/// Keeps pixel indices representable as `u32`, so the same dimensions are accepted regardless of
+/// the platform's pointer width.
+pub const MAX_PIXELS: u64 = u32::MAX as u64;
+
+/// Lowest height of a generated height field, lower values are raised to it.
+pub const MIN_HEIGHT: f64 = 0.0000001;
+/// Highest height of a generated height field, higher values are lowered to it.
+pub const MAX_HEIGHT: f64 = 0.99999999;
+
+/// Height above which [`GenParams::peak_rarity`] reshapes the terrain, the center of the flat land
+/// band of the default gradient.
+pub const PEAK_RARITY_KNEE: f64 = 0.72;
+
+/// Number of pixels of an image with the given dimensions.
+///
+/// Returns `None` if the image would have more than [`MAX_PIXELS`] pixels.
+pub fn pixel_count(width: u32, height: u32) -> Option<usize> {
+    let pixels = width as u64 * height as u64;
+    if pixels > MAX_PIXELS {
+        return None;
+    }
+    usize::try_from(pixels).ok()
+}
+
+/// Length of the RGB buffer needed for an image with the given dimensions.
+///
+/// Returns `None` if the image is too large, see [`pixel_count`].
+pub fn buffer_len(width: u32, height: u32) -> Option<usize> {
+    pixel_count(width, height)?.checked_mul(3)
+}
+
+/// Wraps a generated RGB buffer into an image.
+///
+/// Unlike [`image::ImageBuffer::from_vec`], a buffer of the wrong length is reported with the
+/// expected and actual length instead of just `None`.
+///
+/// # Errors
+/// Returns [`GenerationError::TooLarge`] if the dimensions exceed [`MAX_PIXELS`] and
+/// [`GenerationError::BufferSize`] if `data` does not hold exactly one RGB value per pixel.
+pub fn image_from_vec(
+    width: u32,
+    height: u32,
+    data: Vec<u8>,
+) -> Result<image::RgbImage, GenerationError> {
+    let expected = buffer_len(width, height).ok_or(GenerationError::TooLarge { width, height })?;
+    if data.len() != expected {
+        return Err(GenerationError::BufferSize {
+            expected,
+            actual: data.len(),
+        });
+    }
+
+    // the length was checked above, so this cannot fail
+    Ok(image::RgbImage::from_vec(width, height, data).unwrap())
+}
+
+/// Largest white noise value before it is scaled by the noise strength, if not configured
+/// otherwise, see [`GenParams::noise_magnitude`].
+pub const WHITE_NOISE_RANGE: f64 = 0.01;
+
+/// Number of distinct white noise values used if not configured otherwise.
+pub const DEFAULT_NOISE_GRANULARITY: u32 = 1000;
+
+/// Fewest pixels a thread works on if not configured otherwise, see [`GenParams::min_thread_chunk`].
+pub const DEFAULT_MIN_THREAD_CHUNK: usize = 4096;
+
+/// Seed the noise functions of a height field with seed `seed` are created with.
+///
+/// Noise functions only take 32 bit seeds, so only the lower half of the seed is used for them.
+/// The white noise still depends on the full seed.
+pub fn noise_seed(seed: u64) -> u32 {
+    seed as u32
+}
+
+/// How the weighted octaves of the noise are combined into one value, see
+/// [`GenParams::octave_blend`].
+///
+/// The modes other than `Add` are normalized to roughly the same range as the sum, so the base
+/// level, contrast and bias keep their effect and maps do not end up entirely clamped.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum OctaveBlend {
+    /// sum of all octaves
+    #[default]
+    Add,
+    /// largest octave. The coarse octaves have the largest weights and win most of the time, so
+    /// the terrain is smooth with creases where another octave takes over. The largest octave is
+    /// rarely negative, so it is lowered by a quarter of the largest weight and stretched by the
+    /// ratio of the total weight to the largest weight.
+    Max,
+    /// product of the octaves, for features concentrated where octaves agree. Each octave `o`
+    /// becomes the factor `1 + 2o` and the product `p` becomes `(p - 1) / 2`, which matches the
+    /// sum for small octaves. Lows flatten and highs sharpen compared to the sum.
+    Multiply,
+}
+
+impl OctaveBlend {
+    /// Combines the weighted `octaves` into a single value centered around 0.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use map_generation::generator::OctaveBlend;
+    ///
+    /// let octaves = [0.1, -0.02, 0.01];
+    /// assert_eq!(OctaveBlend::Add.combine(&octaves), 0.1 - 0.02 + 0.01);
+    /// // close to the sum for small octaves
+    /// assert!((OctaveBlend::Multiply.combine(&octaves) - 0.09).abs() < 0.01);
+    /// ```
+    pub fn combine(&self, octaves: &[f64]) -> f64 {
+        match self {
+            OctaveBlend::Add => octaves.iter().sum(),
+            OctaveBlend::Max => {
+                let largest_weight = WEIGHTS.iter().copied().fold(0.0, f64::max);
+                let total_weight: f64 = WEIGHTS.iter().sum();
+                let max = octaves.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+                (max - largest_weight / 4.0) * total_weight / largest_weight
+            }
+            OctaveBlend::Multiply => {
+                let product: f64 = octaves.iter().map(|octave| 1.0 + 2.0 * octave).product();
+                (product - 1.0) / 2.0
+            }
+        }
+    }
+}
+
+impl FromStr for OctaveBlend {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "add" => Ok(OctaveBlend::Add),
+            "max" => Ok(OctaveBlend::Max),
+            "multiply" => Ok(OctaveBlend::Multiply),
+            _ => Err(format!(
+                "{s} is not an octave blend mode. Available are: add, max, multiply"
+            )),
+        }
+    }
+}
+
+/// Symmetry of a generated height field.
+///
+/// Only one half or quadrant is generated and reflected to fill the rest of the image, so
+/// mirrored images are cheaper to generate.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Mirror {
+    /// no symmetry
+    #[default]
+    None,
+    /// the right half mirrors the left half, `heights[x] == heights[width - 1 - x]`
+    Horizontal,
+    /// the bottom half mirrors the top half
+    Vertical,
+    /// every quadrant mirrors the top left one
+    Quad,
+}
+
+impl Mirror {
+    /// Size of the top left region which is generated, the rest of an image of `width` x `height`
+    /// pixels is mirrored from it.
+    pub fn source_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let (half_width, half_height) = (width.div_ceil(2), height.div_ceil(2));
+        match self {
+            Mirror::None => (width, height),
+            Mirror::Horizontal => (half_width, height),
+            Mirror::Vertical => (width, half_height),
+            Mirror::Quad => (half_width, half_height),
+        }
+    }
+}
+
+impl FromStr for Mirror {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "none" => Ok(Mirror::None),
+            "horizontal" => Ok(Mirror::Horizontal),
+            "vertical" => Ok(Mirror::Vertical),
+            "quad" => Ok(Mirror::Quad),
+            _ => Err(format!(
+                "{s} is not a mirror mode. Available are: none, horizontal, vertical, quad"
+            )),
+        }
+    }
+}
+
+/// Distribution of the white noise values between 0 and [`GenParams::noise_magnitude`], see
+/// [`white_noise`].
+///
+/// The shapes other than `Uniform` average several uniform values, so values around half the
+/// magnitude are more likely and the grain is less harsh at the same magnitude.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum NoiseShape {
+    /// every value is equally likely
+    #[default]
+    Uniform,
+    /// mean of two uniform values, the likelihood falls linearly towards both ends
+    Triangular,
+    /// mean of four uniform values, close to a normal distribution but bounded
+    Bell,
+}
+
+impl NoiseShape {
+    /// Step out of `granularity` evenly spaced ones drawn from the random `bits`.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use map_generation::generator::NoiseShape;
+    ///
+    /// for shape in [NoiseShape::Uniform, NoiseShape::Triangular, NoiseShape::Bell] {
+    ///     assert_eq!(shape.step(0, 1000), 0);
+    ///     assert_eq!(shape.step(u64::MAX, 1000), 999);
+    /// }
+    /// // half of all bits set is the middle of every shape
+    /// assert_eq!(NoiseShape::Bell.step(0x8000_8000_8000_8000, 1000), 500);
+    /// ```
+    pub fn step(self, bits: u64, granularity: u32) -> u64 {
+        let granularity = granularity as u64;
+        // the sum of `count` values of `width` bits each, scaled to `granularity` steps
+        let scaled = |sum: u64, count: u32, width: u32| {
+            let range = (count as u128) << width;
+            let step = sum as u128 * granularity as u128 / range;
+            step as u64
+        };
+
+        match self {
+            NoiseShape::Uniform => ((bits >> 32) * granularity) >> 32,
+            NoiseShape::Triangular => scaled((bits >> 32) + (bits & 0xffff_ffff), 2, 32),
+            NoiseShape::Bell => {
+                let sum = (0..4).map(|idx| (bits >> (idx * 16)) & 0xffff).sum();
+                scaled(sum, 4, 16)
+            }
+        }
+    }
+}
+
+impl FromStr for NoiseShape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "uniform" => Ok(NoiseShape::Uniform),
+            "triangular" => Ok(NoiseShape::Triangular),
+            "bell" => Ok(NoiseShape::Bell),
+            _ => Err(format!(
+                "{s} is not a noise shape. Available are: uniform, triangular, bell"
+            )),
+        }
+    }
+}
+
+/// Parameters controlling how the height field is generated.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct GenParams {
+    /// value to seed noise function with
+    pub seed: u64,
+    /// width of generated image in pixels
+    pub width: u32,
+    /// height of generated image in pixels
+    pub height: u32,
+    /// level used as the minimum level. Used to generate higher terrain images.
+    pub base_level: f64,
+    /// level of white noise applied to the Perlin noise to break up Perlin noise evenness.
+    pub noise_strength: f64,
+    /// noise strength of pixels in each terrain band, indexed by `TerrainKind as usize`. Bands
+    /// without an entry use `noise_strength`. Pixels are classified by the bands of the gradient
+    /// the image is colored with before the noise is applied, so the noise may push a pixel into a
+    /// neighboring band. Functions without a gradient use `Gradient::default()`.
+    pub band_noise_strength: [Option<f64>; TerrainKind::COUNT],
+    /// noise strength following a curve over the height before the noise is applied, used instead
+    /// of `noise_strength` if set. Bands with an entry in `band_noise_strength` still use that.
+    pub noise_strength_curve: Option<NoiseCurve>,
+    /// number of distinct white noise values, see [`white_noise`]
+    pub noise_granularity: u32,
+    /// largest white noise value before it is scaled by the noise strength, see [`white_noise`]
+    pub noise_magnitude: f64,
+    /// distribution of the white noise values, see [`NoiseShape`]
+    pub noise_shape: NoiseShape,
+    /// number of threads used to generate the image. If None, available CPUs - 1 is used.
+    pub thread_count: Option<usize>,
+    /// fewest pixels a thread generates. Small images use fewer threads than `thread_count`, down
+    /// to a single one, as starting threads would take longer than generating their few pixels.
+    pub min_thread_chunk: usize,
+    /// seed of a second height field blended with the first one. If None, only `seed` is used.
+    pub seed_b: Option<u64>,
+    /// share of the second height field in the result. 0.0 is only `seed`, 1.0 only `seed_b`.
+    pub blend: f64,
+    /// factor by which heights are spread out from or pulled towards 0.5. 1.0 leaves them unchanged.
+    pub contrast: f64,
+    /// exponent applied to heights. Values above 1.0 favor low, below 1.0 high elevations.
+    pub bias: f64,
+    /// how strongly heights above [`PEAK_RARITY_KNEE`] are pushed down, after contrast and bias.
+    /// The land is reshaped exponentially, so each step towards the highest level is rarer than the
+    /// one before and high peaks become scarce. Unlike `contrast` and `bias` the coast and low land
+    /// stay as they are. 0.0 leaves the heights unchanged.
+    ///
+    /// ```rust
+    /// use map_generation::generator::{generate_heightmap, GenParams};
+    ///
+    /// let peak_share = |peak_rarity: f64| {
+    ///     let params = GenParams {
+    ///         peak_rarity,
+    ///         ..GenParams::new(12345, 200, 200, 0.5, 0.1)
+    ///     };
+    ///     let mut heights = vec![0.0; 200 * 200];
+    ///     generate_heightmap(&mut heights, &params).unwrap();
+    ///     heights.iter().filter(|height| **height > 0.9).count() as f64 / heights.len() as f64
+    /// };
+    ///
+    /// let shares: Vec<f64> = [0.0, 0.5, 1.0, 2.0].into_iter().map(peak_share).collect();
+    /// assert!(shares.windows(2).all(|pair| pair[1] <= pair[0]));
+    /// assert!(shares[3] < shares[0]);
+    /// ```
+    pub peak_rarity: f64,
+    /// whether the image wraps around at its edges, so it can be tiled without visible seams
+    pub seamless: bool,
+    /// width and height in pixels after which the world wraps around, so areas rendered beyond
+    /// the east edge continue at the west edge and likewise for north and south. Unlike `seamless`
+    /// it is independent of the image size, so a world can span many tiles. Takes precedence over
+    /// `seamless`.
+    pub world_size: Option<(u32, u32)>,
+    /// whether only the left and right edges wrap around, as on a cylindrical map of a planet.
+    /// Continents continue across the antimeridian, while the top and bottom edges stay distinct
+    /// poles. Ignored if `seamless` or `world_size` wrap both axes. Rotation is ignored, equal area
+    /// sampling shrinks the circles of latitude towards the poles.
+    ///
+    /// ```rust
+    /// use map_generation::generator::{GenParams, Generator};
+    /// use map_generation::gradient::Gradient;
+    ///
+    /// let mut params = GenParams::new(12345, 100, 50, 0.2, 0.0);
+    /// params.antimeridian_wrap = true;
+    /// let generator = Generator::new(params, Gradient::default());
+    ///
+    /// // the column right of the image is its first column again
+    /// let (mut first, mut beyond) = (vec![0.0; 50], vec![0.0; 50]);
+    /// generator.render_heights((0, 0), (1, 50), &mut first).unwrap();
+    /// generator.render_heights((100, 0), (1, 50), &mut beyond).unwrap();
+    /// assert_eq!(first, beyond);
+    ///
+    /// // while the row below the image is not its first row
+    /// let (mut top, mut below) = (vec![0.0; 100], vec![0.0; 100]);
+    /// generator.render_heights((0, 0), (100, 1), &mut top).unwrap();
+    /// generator.render_heights((0, 50), (100, 1), &mut below).unwrap();
+    /// assert_ne!(top, below);
+    /// ```
+    pub antimeridian_wrap: bool,
+    /// angle in degrees by which the noise field is rotated about the image center. Ignored for
+    /// wrapping images, as rotating would break the wrapping.
+    pub rotation: f64,
+    /// whether the noise is sampled more sparsely towards the top and bottom edges, so that
+    /// features cover roughly the same area on a globe wherever they are. This only makes sense
+    /// when the image is read as an equirectangular map, where every row is a circle of latitude
+    /// and rows near the poles are stretched. Ignored for wrapping images.
+    pub equal_area: bool,
+    /// whether to trade accuracy for speed by approximating heights far from band limits.
+    ///
+    /// The coarsest octaves are evaluated on a grid and interpolated in between. Where the finer
+    /// octaves cannot move a height into another band of the gradient the image is colored with,
+    /// or of `Gradient::default()` for functions without one, they are skipped, so large uniform
+    /// areas like open oceans lose their fine detail but keep their band. Heights close to
+    /// band limits are computed exactly, which keeps coastlines unchanged. How close is decided by
+    /// bounds on the skipped octaves and on the interpolation error, so small images whose grid
+    /// points lie far apart in noise space gain little. Ignored for wrapping images, equal area
+    /// sampling, noise other than Perlin noise and octave blend modes other than
+    /// [`OctaveBlend::Add`].
+    pub fast: bool,
+    /// how the octaves of the noise are combined, see [`OctaveBlend`]
+    pub octave_blend: OctaveBlend,
+    /// largest distance in noise space by which each octave is shifted. Octaves otherwise share
+    /// their origin, so their features can line up and form faint grid patterns. Each octave is
+    /// shifted along every axis by its own amount derived from the seed, in noise lattice cells, so
+    /// 0.5 already decorrelates them. Unlike a different seed, the noise itself stays the same.
+    /// 0.0 leaves the heights unchanged.
+    ///
+    /// ```rust
+    /// use map_generation::generator::{generate_heightmap, GenParams};
+    ///
+    /// let generate = |octave_jitter: Option<f64>| {
+    ///     let mut params = GenParams::new(12345, 100, 100, 0.2, 0.1);
+    ///     if let Some(octave_jitter) = octave_jitter {
+    ///         params.octave_jitter = octave_jitter;
+    ///     }
+    ///     let mut heights = vec![0.0; 100 * 100];
+    ///     generate_heightmap(&mut heights, &params).unwrap();
+    ///     heights
+    /// };
+    ///
+    /// assert_eq!(generate(Some(0.0)), generate(None));
+    /// assert_ne!(generate(Some(0.5)), generate(None));
+    /// ```
+    pub octave_jitter: f64,
+    /// generator deriving the jitter from the seed, i.e. the white noise of every pixel and the
+    /// octave shifts of [`GenParams::octave_jitter`]
+    pub jitter_rng: JitterRng,
+    /// symmetry of the height field. Applied by [`generate_heightmap`], tiles rendered with a
+    /// [`Generator`] are never mirrored.
+    pub mirror: Mirror,
+    /// size in pixels of the largest features, which makes the noise frequency independent of the
+    /// image size. If None, the largest features span the shorter side of the image, so changing
+    /// the resolution scales the map instead of showing more or less of it.
+    pub feature_scale: Option<f64>,
+    /// number of height samples per pixel along each axis. Each pixel averages the colors of
+    /// `aa_factor * aa_factor` samples, multiplying the cost of generation by the same amount.
+    /// 1 disables antialiasing.
+    pub aa_factor: u32,
+    /// time after which generation is stopped with [`GenerationError::TimedOut`]. It is checked
+    /// once per row, so generation may run slightly longer.
+    pub timeout: Option<Duration>,
+}
+
+impl GenParams {
+    /// Parameters of the height field sampled for antialiasing.
+    ///
+    /// Dimensions are multiplied by `aa_factor`, which keeps the noise frequency relative to the
+    /// image the same, so the samples cover the same map at a higher resolution.
+    pub fn supersampled(&self) -> Self {
+        let factor = self.aa_factor.max(1);
+        Self {
+            width: self.width.saturating_mul(factor),
+            height: self.height.saturating_mul(factor),
+            feature_scale: self.feature_scale.map(|scale| scale * factor as f64),
+            aa_factor: 1,
+            ..*self
+        }
+    }
+
+    /// Applies contrast and bias to a height, after base level and noise. Neutral values are
+    /// skipped to keep the heights bit-for-bit unchanged.
+    fn shape(&self, mut height: f64) -> f64 {
+        if self.contrast != 1.0 {
+            height = 0.5 + (height - 0.5) * self.contrast;
+        }
+        if self.bias != 1.0 {
+            height = height.clamp(0.0, 1.0).powf(self.bias);
+        }
+        if self.peak_rarity > 0.0 && height > PEAK_RARITY_KNEE {
+            let t = (height - PEAK_RARITY_KNEE) / (1.0 - PEAK_RARITY_KNEE);
+            let t = (self.peak_rarity * t).exp_m1() / self.peak_rarity.exp_m1();
+            height = PEAK_RARITY_KNEE + t * (1.0 - PEAK_RARITY_KNEE);
+        }
+        height
+    }
+
+    /// Whether any band has its own noise strength.
+    fn has_band_noise(&self) -> bool {
+        self.band_noise_strength.iter().any(Option::is_some)
+    }
+
+    pub fn new(seed: u64, width: u32, height: u32, base_level: f64, noise_strength: f64) -> Self {
+        Self {
+            seed,
+            width,
+            height,
+            base_level,
+            noise_strength,
+            noise_granularity: DEFAULT_NOISE_GRANULARITY,
+            noise_magnitude: WHITE_NOISE_RANGE,
+            noise_shape: NoiseShape::Uniform,
+            band_noise_strength: [None; TerrainKind::COUNT],
+            noise_strength_curve: None,
+            thread_count: None,
+            min_thread_chunk: DEFAULT_MIN_THREAD_CHUNK,
+            seamless: false,
+            world_size: None,
+            antimeridian_wrap: false,
+            rotation: 0.0,
+            equal_area: false,
+            feature_scale: None,
+            fast: false,
+            octave_blend: OctaveBlend::Add,
+            octave_jitter: 0.0,
+            jitter_rng: JitterRng::SeedHash,
+            mirror: Mirror::None,
+            aa_factor: 1,
+            timeout: None,
+            contrast: 1.0,
+            bias: 1.0,
+            peak_rarity: 0.0,
+            seed_b: None,
+            blend: 0.5,
+        }
+    }
+}
+
+/// Piecewise linear curve of the noise strength over elevation, see
+/// [`GenParams::noise_strength_curve`].
+///
+/// # Examples
+/// ```rust
+/// use map_generation::generator::NoiseCurve;
+///
+/// let curve = NoiseCurve::new(&[(0.2, 0.0), (0.6, 0.4)]).unwrap();
+///
+/// assert_eq!(curve.strength(0.1), 0.0);
+/// assert!((curve.strength(0.5) - 0.3).abs() < 1e-9);
+/// assert_eq!(curve.strength(0.9), 0.4);
+/// assert!(NoiseCurve::new(&[(0.6, 0.4), (0.2, 0.0)]).is_err());
+/// ```
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct NoiseCurve {
+    /// elevation and noise strength of each point, sorted by elevation. Only the first `len` are
+    /// used.
+    points: [(f64, f64); NoiseCurve::MAX_POINTS],
+    len: usize,
+}
+
+impl NoiseCurve {
+    /// Largest number of points a curve may have.
+    pub const MAX_POINTS: usize = 16;
+
+    /// Curve through `points` of elevation and noise strength.
+    ///
+    /// # Errors
+    /// Fails if there are no or more than [`NoiseCurve::MAX_POINTS`] points, an elevation is not
+    /// within 0 and 1, the elevations are not ascending or a strength is negative.
+    pub fn new(points: &[(f64, f64)]) -> Result<Self, String> {
+        if points.is_empty() || points.len() > Self::MAX_POINTS {
+            return Err(format!(
+                "Noise curve must have between 1 and {} points!",
+                Self::MAX_POINTS
+            ));
+        }
+        if let Some((elevation, _)) = points
+            .iter()
+            .find(|(elevation, _)| !(0.0..=1.0).contains(elevation))
+        {
+            return Err(format!(
+                "Curve elevation {elevation} must be between 0 and 1!"
+            ));
+        }
+        if points.windows(2).any(|pair| pair[0].0 >= pair[1].0) {
+            return Err("Curve elevations must be in ascending order!".to_string());
+        }
+        if points
+            .iter()
+            .any(|(_, strength)| strength.is_nan() || *strength < 0.0)
+        {
+            return Err("Noise strength must not be negative!".to_string());
+        }
+
+        let mut curve = Self {
+            points: [(0.0, 0.0); Self::MAX_POINTS],
+            len: points.len(),
+        };
+        curve.points[..points.len()].copy_from_slice(points);
+        Ok(curve)
+    }
+
+    /// Elevation and noise strength of each point.
+    pub fn points(&self) -> &[(f64, f64)] {
+        &self.points[..self.len]
+    }
+
+    /// Noise strength at `elevation`, interpolated linearly between the neighboring points.
+    /// Elevations beyond the first or last point get the strength of that point.
+    pub fn strength(&self, elevation: f64) -> f64 {
+        let points = self.points();
+        let upper = points.partition_point(|(point, _)| *point < elevation);
+
+        match (upper.checked_sub(1), points.get(upper)) {
+            (Some(lower), Some((end, end_strength))) => {
+                let (start, start_strength) = points[lower];
+                let factor = (elevation - start) / (end - start);
+                start_strength + (end_strength - start_strength) * factor
+            }
+            (None, Some((_, strength))) => *strength,
+            (_, None) => points[points.len() - 1].1,
+        }
+    }
+}
+
+/// Errors which can occur while generating an image.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GenerationError {
+    /// The image would have more than [`MAX_PIXELS`] pixels.
+    TooLarge { width: u32, height: u32 },
+    /// The provided buffer does not have the length the image needs.
+    BufferSize { expected: usize, actual: usize },
+    /// Generation took longer than [`GenParams::timeout`].
+    TimedOut,
+    /// Generation was stopped through the flag passed to [`generate_heightmap_cancellable`].
+    Cancelled,
+    /// Samples can not be split into rows of `width` pixels with `factor` samples along each axis,
+    /// as one of them is 0, see [`color_supersampled`].
+    ZeroSampling { width: u32, factor: u32 },
+}
+
+impl std::fmt::Display for GenerationError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GenerationError::TooLarge { width, height } => write!(
+                f,
+                "Image of {width}x{height} pixels is too large, at most {MAX_PIXELS} pixels are supported"
+            ),
+            GenerationError::BufferSize { expected, actual } => {
+                write!(f, "Buffer must be {expected} bytes long, got {actual}")
+            }
+            GenerationError::TimedOut => write!(f, "Generation timed out"),
+            GenerationError::Cancelled => write!(f, "Generation was cancelled"),
+            GenerationError::ZeroSampling { width, factor } => write!(
+                f,
+                "Can not color rows of {width} pixels with {factor} samples along each axis"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for GenerationError {}
 
 /// Generates a `Vec<u8>` containing red, green, and blue value between 0 and 255.
 ///
@@ -10,113 +640,2051 @@ use rand::Rng;
 /// The level is in the including range of 0.0 to 1.0.
 ///
 /// # Arguments
-///   * `seed` value to seed noise function with
-///   * `width` width of generated image in pixels
-///   * `height` height of generated image in pixels
-///   * `base_level` level used as the minimum level. Used to generate higher terrain images.
-///   * `noise_strength` level of white noise applied to the Perlin noise to break up Perlin noise evenness.
+///   * `image_data` mutable reference to the result buffer. It must already have the correct length.
+///   * `params` parameters of the generated height field, see [`GenParams`]
 ///   * `gradient` level and color gradient to color the generated image accordingly. If None, `Gradient::default()` is used
-///   * `thread_count` number of threads used to generate the image. If None, available CPUs - 1 is used.
-///   * `image_data` mutable reference to the result vector. It must already have the correct capacity.
+///
+/// # Panics
+/// Panics if the image is too large or `image_data` has the wrong length. Use [`generate_into`]
+/// to handle these cases.
 ///
 /// # Examples
 /// ```rust
-/// use generator::generate;
+/// use map_generation::generator::{self, generate, GenParams};
 ///
 /// const WIDTH: u32 = 100;
 /// const HEIGHT: u32 = 100;
 ///
-/// let mut image_data = Vec::with_capacity((width * height) as usize);
+/// let mut image_data = vec![0; generator::buffer_len(WIDTH, HEIGHT).unwrap()];
 ///
-/// generate(12345, width, height, 0.2, 0.0, None, &mut image_data);
+/// generate(&mut image_data, &GenParams::new(12345, WIDTH, HEIGHT, 0.2, 0.0), None);
 /// ```
 ///
-pub fn generate(
-    image_data: &mut Vec<u8>,
-    seed: u64,
-    width: u32,
-    height: u32,
-    base_level: f64,
-    noise_strength: f64,
-    gradient: Option<Gradient>,
-    thread_count: Option<usize>,
-) {
-    let gradient = gradient.unwrap_or(Gradient::default());
-    let perlin = Perlin::new(seed as u32);
-
-    let thread_count = thread_count.unwrap_or(num_cpus::get() - 1);
-
-    let mut steps: [f64; SCALES.len()] = [0.0; SCALES.len()];
-    for (idx, scale) in SCALES.iter().enumerate() {
-        let step_x = *scale as f64 / width as f64;
-        let step_y = *scale as f64 / height as f64;
-        steps[idx] = f64::min(step_x, step_y);
-    }
-
-    let image_slice = &mut image_data[..];
-
-    let area_size = (width * height) as usize / thread_count;
-
-    let _ = crossbeam::scope(|scope| {
-        for (area, slice) in image_slice.chunks_mut(area_size * 3).enumerate() {
-            let perlin = perlin.clone();
-            let gradient = gradient.clone();
-            scope.spawn(move |_| {
-                job(
-                    slice,
-                    area * area_size,
-                    area_size,
-                    &steps,
-                    perlin,
-                    gradient,
-                    width as usize,
-                    base_level,
-                    noise_strength,
-                )
-            });
+pub fn generate(image_data: &mut [u8], params: &GenParams, gradient: Option<Gradient>) {
+    let gradient = gradient.unwrap_or_default();
+
+    if let Err(err) = generate_into(image_data, &gradient, params) {
+        panic!("{err}");
+    }
+}
+
+/// Generates an image into an existing buffer, colored with the given gradient.
+///
+/// Same as [`generate`], but lets the caller reuse buffers and gradients between calls.
+///
+/// # Errors
+/// Returns [`GenerationError::BufferSize`] if `buffer` is not exactly `width * height * 3` bytes
+/// long and [`GenerationError::TooLarge`] if the dimensions exceed [`MAX_PIXELS`].
+///
+/// # Examples
+/// ```rust
+/// use map_generation::generator::{self, generate_into, GenParams};
+/// use map_generation::gradient::Gradient;
+///
+/// let gradient = Gradient::default();
+/// let mut buffer = vec![0; generator::buffer_len(100, 100).unwrap()];
+///
+/// for seed in 0..3 {
+///     let params = GenParams::new(seed, 100, 100, 0.2, 0.0);
+///     generate_into(&mut buffer, &gradient, &params).unwrap();
+/// }
+/// ```
+///
+pub fn generate_into(
+    buffer: &mut [u8],
+    gradient: &Gradient,
+    params: &GenParams,
+) -> Result<(), GenerationError> {
+    Generator::new(*params, gradient.clone()).render((0, 0), (params.width, params.height), buffer)
+}
+
+/// Generates an image into an existing buffer, colored by a custom function.
+///
+/// The height field is generated as usual, then `color_fn` is called with the level of every
+/// pixel, or every sample when antialiasing, to get its color. It is called from the worker
+/// threads, hence it must be `Sync`. [`GenParams::band_noise_strength`] and [`GenParams::fast`]
+/// classify heights by the bands of `Gradient::default()`, use a [`Generator`] with another
+/// gradient to classify them by its bands instead.
+///
+/// # Errors
+/// Same as [`generate_into`].
+///
+/// # Examples
+/// ```rust
+/// use map_generation::generator::{self, generate_with, GenParams};
+///
+/// let mut buffer = vec![0; generator::buffer_len(100, 100).unwrap()];
+/// let params = GenParams::new(12345, 100, 100, 0.2, 0.0);
+///
+/// // black and white map with the coast at level 0.6
+/// generate_with(&mut buffer, &params, |height| {
+///     if height < 0.6 {
+///         image::Rgb([0, 0, 0])
+///     } else {
+///         image::Rgb([255, 255, 255])
+///     }
+/// })
+/// .unwrap();
+/// ```
+///
+pub fn generate_with<F>(
+    buffer: &mut [u8],
+    params: &GenParams,
+    color_fn: F,
+) -> Result<(), GenerationError>
+where
+    F: Fn(f64) -> image::Rgb<u8> + Sync,
+{
+    Generator::new(*params, Gradient::default()).render_with(
+        (0, 0),
+        (params.width, params.height),
+        buffer,
+        color_fn,
+    )
+}
+
+/// Generates the raw height field the image colors are derived from.
+///
+/// Every entry is the level of one pixel in row-major order and lies within the range of 0.0 to
+/// 1.0. Heights are classified by the bands of `Gradient::default()` like in [`generate_with`],
+/// [`Generator::render_heights`] classifies them by the gradient of the generator.
+///
+/// # Errors
+/// Returns [`GenerationError::TimedOut`] if [`GenParams::timeout`] is exceeded. The content of
+/// `heights` is incomplete in that case.
+///
+/// # Examples
+/// ```rust
+/// use map_generation::generator::{generate_heightmap, GenParams};
+///
+/// let mut heights = vec![0.0; 100 * 100];
+///
+/// generate_heightmap(&mut heights, &GenParams::new(12345, 100, 100, 0.2, 0.0)).unwrap();
+/// ```
+///
+pub fn generate_heightmap(heights: &mut [f64], params: &GenParams) -> Result<(), GenerationError> {
+    generate_heightmap_cancellable(heights, params, &AtomicBool::new(false)).map(|_| ())
+}
+
+/// Same as [`generate_heightmap`], but stops early once `cancel` is set from another thread.
+///
+/// The flag is checked once per row. Returns [`GenerationError::Cancelled`] if it was set, and
+/// how many samples had to be clamped into range otherwise.
+pub fn generate_heightmap_cancellable(
+    heights: &mut [f64],
+    params: &GenParams,
+    cancel: &AtomicBool,
+) -> Result<ClampCounts, GenerationError> {
+    Generator::new(*params, Gradient::default()).render_heightmap_cancellable(heights, cancel)
+}
+
+/// Generates the height field row by row and hands every finished row to `row_fn`, without
+/// holding the whole height field in memory.
+///
+/// `row_fn` is called with the index of the row and its heights, which are the same as those of
+/// [`generate_heightmap`]. It is called from the worker threads, hence it must be `Send + Sync`,
+/// and rows arrive in no particular order. Each thread only keeps the row it currently works on,
+/// so this suits consumers writing the heights elsewhere anyway, like into a GPU texture.
+///
+/// # Errors
+/// Returns [`GenerationError::TooLarge`] if the dimensions exceed [`MAX_PIXELS`] and
+/// [`GenerationError::TimedOut`] if [`GenParams::timeout`] is exceeded, after which no further
+/// rows are passed to `row_fn`.
+///
+/// # Examples
+/// The rows add up to the same height field as generating it at once.
+/// ```rust
+/// use map_generation::generator::{generate_heightmap, generate_streaming, GenParams};
+/// use std::sync::atomic::{AtomicU64, Ordering};
+///
+/// let params = GenParams::new(12345, 100, 80, 0.2, 0.1);
+/// let checksum = |y: u32, row: &[f64]| {
+///     row.iter().enumerate().fold(0u64, |sum, (x, height)| {
+///         let hash = height.to_bits() ^ ((y as u64) << 32 | x as u64);
+///         sum.wrapping_add(hash.wrapping_mul(0x9e3779b97f4a7c15))
+///     })
+/// };
+///
+/// let streamed = AtomicU64::new(0);
+/// generate_streaming(&params, |y, row| {
+///     streamed.fetch_add(checksum(y, row), Ordering::Relaxed);
+/// })
+/// .unwrap();
+///
+/// let mut heights = vec![0.0; 100 * 80];
+/// generate_heightmap(&mut heights, &params).unwrap();
+/// let expected = heights
+///     .chunks(100)
+///     .enumerate()
+///     .fold(0u64, |sum, (y, row)| sum.wrapping_add(checksum(y as u32, row)));
+///
+/// assert_eq!(streamed.into_inner(), expected);
+/// ```
+pub fn generate_streaming<F>(params: &GenParams, row_fn: F) -> Result<(), GenerationError>
+where
+    F: Fn(u32, &[f64]) + Send + Sync,
+{
+    let (width, height) = (params.width, params.height);
+    pixel_count(width, height).ok_or(GenerationError::TooLarge { width, height })?;
+
+    // rows are spread over the threads here, the generator renders each row on the current one
+    let deadline = params.timeout.map(|timeout| Instant::now() + timeout);
+    let generator = Generator::new(
+        GenParams {
+            thread_count: Some(1),
+            timeout: None,
+            ..*params
+        },
+        Gradient::default(),
+    );
+    let (source_width, source_height) = params.mirror.source_size(width, height);
+    let thread_count = effective_thread_count(
+        pixel_count(width, height).unwrap(),
+        params.thread_count,
+        params.min_thread_chunk,
+    )
+    .min(height.max(1) as usize);
+    let next_row = AtomicUsize::new(0);
+    let timed_out = AtomicBool::new(false);
+
+    let worker = || {
+        let mut source = vec![0.0; source_width as usize];
+        let mut row = vec![0.0; width as usize];
+
+        loop {
+            let y = next_row.fetch_add(1, Ordering::Relaxed) as u32;
+            if y >= height || timed_out.load(Ordering::Relaxed) {
+                return;
+            }
+            if deadline.is_some_and(|deadline| Instant::now() >= deadline) {
+                timed_out.store(true, Ordering::Relaxed);
+                return;
+            }
+
+            // rows beyond the mirrored source are reflected back into it like in generate_heightmap
+            let source_y = if y < source_height { y } else { height - 1 - y };
+            generator
+                .render_heights((0, source_y), (source_width, 1), &mut source)
+                .expect("the image size is valid and rows are rendered without a timeout");
+            for (x, height) in row.iter_mut().enumerate() {
+                let source_x = if x < source.len() {
+                    x
+                } else {
+                    width as usize - 1 - x
+                };
+                *height = source[source_x];
+            }
+
+            row_fn(y, &row);
         }
-    });
+    };
+
+    run_jobs((0..thread_count).map(|_| &worker))
+        .unwrap_or_else(|panic| std::panic::resume_unwind(panic));
+
+    if timed_out.into_inner() {
+        Err(GenerationError::TimedOut)
+    } else {
+        Ok(())
+    }
 }
 
-fn job(
-    image: &mut [u8],
-    start: usize,
-    amount: usize,
-    steps: &[f64; SCALES.len()],
-    perlin: Perlin,
+/// Reusable generation context for rendering many tiles or frames of the same map.
+///
+/// Holds everything which only depends on the parameters, like the noise functions and their
+/// sampling steps, so it is set up once instead of on every call of [`generate_into`].
+///
+/// Positions passed to [`Generator::render`] are pixels of the full image described by the
+/// parameters. Rendering all tiles of an image gives the same result as rendering it at once.
+///
+/// # Examples
+/// ```rust
+/// use map_generation::generator::{self, GenParams, Generator};
+/// use map_generation::gradient::Gradient;
+///
+/// let generator = Generator::new(GenParams::new(12345, 200, 200, 0.2, 0.0), Gradient::default());
+/// let mut tile = vec![0; generator::buffer_len(100, 100).unwrap()];
+///
+/// for (x, y) in [(0, 0), (100, 0), (0, 100), (100, 100)] {
+///     generator.render((x, y), (100, 100), &mut tile).unwrap();
+/// }
+/// ```
+pub struct Generator {
+    params: GenParams,
     gradient: Gradient,
-    width: usize,
-    base_level: f64,
-    noise_strength: f64,
-) {
-    for idx in 0..std::cmp::min(image.len() / 3, amount) {
-        let x = (start + idx) % width;
-        let y = (start + idx) / width;
-        let mut value: f64 = 0.0;
+    steps: [f64; SCALES.len()],
+    perlin: Perlin,
+    perlin_b: Option<Perlin>,
+    /// noise replacing `perlin` and `perlin_b` when sampling the plane, see [`Generator::with_noise`]
+    noise: Option<(PlaneNoise, Option<PlaneNoise>)>,
+    /// clamped samples of all renders so far, see [`Generator::clamp_counts`]
+    clamped: ClampCounters,
+    /// heights of the last [`Generator::render`], reused by the next one
+    scratch: Mutex<Vec<f64>>,
+}
+
+/// Noise function sampled by the octave loop of a [`Generator`], see [`Generator::with_noise`].
+pub type PlaneNoise = Box<dyn NoiseFn<f64, 2> + Send + Sync>;
+
+impl Generator {
+    pub fn new(params: GenParams, gradient: Gradient) -> Self {
+        let steps = octave_steps(&params);
+
+        let perlin_b = match params.seed_b {
+            Some(seed_b) if params.blend > 0.0 => Some(Perlin::new(noise_seed(seed_b))),
+            _ => None,
+        };
 
-        for layer_idx in 0..SCALES.len() {
-            let step = steps[layer_idx];
-            let x = step * x as f64;
-            let y = step * y as f64;
-            value += perlin.get([x, y]) * WEIGHTS[layer_idx];
+        Self {
+            params,
+            gradient,
+            steps,
+            perlin: Perlin::new(noise_seed(params.seed)),
+            perlin_b,
+            noise: None,
+            clamped: ClampCounters::default(),
+            scratch: Mutex::default(),
         }
+    }
 
-        value += 0.5;
+    /// Samples the noise created by `make_noise` instead of Perlin noise.
+    ///
+    /// `make_noise` is called with the seed of each height field, so the second field of
+    /// [`GenParams::seed_b`] gets its own noise as well. The noise is sampled at the same
+    /// coordinates and weighted the same as Perlin noise, so it should return values in roughly the
+    /// same range of -0.5 to 0.5. Wrapping images still use Perlin noise, as they need to sample
+    /// four dimensional noise.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use map_generation::generator::{self, GenParams, Generator};
+    /// use map_generation::gradient::Gradient;
+    /// use noise::OpenSimplex;
+    ///
+    /// let generator = Generator::new(GenParams::new(12345, 100, 100, 0.2, 0.0), Gradient::default())
+    ///     .with_noise(OpenSimplex::new);
+    /// let mut image = vec![0; generator::buffer_len(100, 100).unwrap()];
+    ///
+    /// generator.render((0, 0), (100, 100), &mut image).unwrap();
+    /// ```
+    pub fn with_noise<F, N>(mut self, make_noise: F) -> Self
+    where
+        F: Fn(u32) -> N,
+        N: NoiseFn<f64, 2> + Send + Sync + 'static,
+    {
+        let noise: PlaneNoise = Box::new(make_noise(noise_seed(self.params.seed)));
+        let noise_b = self.perlin_b.map(|_| {
+            let seed_b = self.params.seed_b.unwrap_or_default();
+            Box::new(make_noise(noise_seed(seed_b))) as PlaneNoise
+        });
 
-        let noise_value = rand::thread_rng().gen_range(0..1000) as f64 / 100000.0;
+        self.noise = Some((noise, noise_b));
+        self
+    }
 
-        // map value to be inside valid range
-        value = base_level
-            + value * (1.0 - base_level)
-            // and apply noise
-            + noise_value * noise_strength;
+    /// Number of samples clamped to [`MIN_HEIGHT`] or [`MAX_HEIGHT`] over all renders of this
+    /// generator so far.
+    pub fn clamp_counts(&self) -> ClampCounts {
+        self.clamped.counts()
+    }
 
-        // limit values to be within range
-        value = value.clamp(0.0000001, 0.99999999);
+    pub fn params(&self) -> &GenParams {
+        &self.params
+    }
+
+    pub fn gradient(&self) -> &Gradient {
+        &self.gradient
+    }
+
+    /// Computes a single pixel and returns every intermediate value, e.g. to debug the color of a
+    /// specific location without rendering the whole image.
+    ///
+    /// The computation is the same as for rendering. Antialiasing is not taken into account, the
+    /// pixel is computed from a single sample. [`GenParams::mirror`] is applied like in
+    /// [`generate_heightmap`]. Returns `None` if the pixel is outside of the image.
+    pub fn inspect(&self, x: u32, y: u32) -> Option<PixelInspection> {
+        let (width, height) = (self.params.width, self.params.height);
+        if x >= width || y >= height {
+            return None;
+        }
+        let (source_width, source_height) = self.params.mirror.source_size(width, height);
+        let x = if x < source_width { x } else { width - 1 - x };
+        let y = if y < source_height { y } else { height - 1 - y };
+
+        let cancel = AtomicBool::new(false);
+        let stop = Stop {
+            cancel: &cancel,
+            deadline: None,
+            timed_out: AtomicBool::new(false),
+        };
+        let window = Window {
+            x: 0,
+            y: 0,
+            width: width as usize,
+        };
+        let (field, field_b) = self.fields(window, 1, &stop);
+
+        let trace = compute_pixel(&field, x as usize, y as usize);
+        let trace_b = field_b.map(|field_b| compute_pixel(&field_b, x as usize, y as usize));
+        let height = match trace_b {
+            Some(trace_b) => {
+                trace.height * (1.0 - self.params.blend) + trace_b.height * self.params.blend
+            }
+            None => trace.height,
+        };
+
+        Some(PixelInspection {
+            trace,
+            trace_b,
+            height,
+            band: self.gradient.get_terrain_kind(height),
+            color: self.gradient.lerp_color(height),
+        })
+    }
+
+    /// Renders the colors of the area of `size` pixels starting at `offset` into `buffer`.
+    ///
+    /// # Errors
+    /// Same as [`generate_into`], with the buffer length checked against `size`.
+    pub fn render(
+        &self,
+        offset: (u32, u32),
+        size: (u32, u32),
+        buffer: &mut [u8],
+    ) -> Result<(), GenerationError> {
+        self.render_with(offset, size, buffer, |height| {
+            self.gradient.lerp_color(height)
+        })
+    }
+
+    /// Same as [`Generator::render`], but colored by `color_fn` instead of the gradient, see
+    /// [`generate_with`].
+    ///
+    /// The heights are computed into a buffer kept by the generator, so rendering many tiles or
+    /// frames of the same size allocates it once. Renders running at the same time on other
+    /// threads use a buffer of their own.
+    pub fn render_with<F>(
+        &self,
+        offset: (u32, u32),
+        size: (u32, u32),
+        buffer: &mut [u8],
+        color_fn: F,
+    ) -> Result<(), GenerationError>
+    where
+        F: Fn(f64) -> image::Rgb<u8> + Sync,
+    {
+        let (width, height) = size;
+        let expected =
+            buffer_len(width, height).ok_or(GenerationError::TooLarge { width, height })?;
+
+        if buffer.len() != expected {
+            return Err(GenerationError::BufferSize {
+                expected,
+                actual: buffer.len(),
+            });
+        }
+
+        // nothing to sample, even rows without pixels can not be supersampled
+        if expected == 0 {
+            return Ok(());
+        }
+
+        let factor = self.params.aa_factor.max(1);
+        let mut locked = self.scratch.try_lock();
+        let mut own = Vec::new();
+        let heights = match locked.as_deref_mut() {
+            Ok(scratch) => scratch,
+            Err(_) => &mut own,
+        };
+
+        if factor > 1 {
+            let (width, height) = (width.saturating_mul(factor), height.saturating_mul(factor));
+            let pixel_count =
+                pixel_count(width, height).ok_or(GenerationError::TooLarge { width, height })?;
+
+            heights.resize(pixel_count, 0.0);
+            let window = Window {
+                x: offset.0 as usize * factor as usize,
+                y: offset.1 as usize * factor as usize,
+                width: width as usize,
+            };
+            self.fill(heights, window, factor, &AtomicBool::new(false))?;
+
+            color_supersampled_with(
+                buffer,
+                heights,
+                size.0,
+                factor,
+                &color_fn,
+                self.params.thread_count,
+                self.params.min_thread_chunk,
+            )?;
+        } else {
+            heights.resize(expected / 3, 0.0);
+            self.render_heights(offset, size, heights)?;
+
+            color_heights_with(
+                buffer,
+                heights,
+                &color_fn,
+                self.params.thread_count,
+                self.params.min_thread_chunk,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Renders the heights of the area of `size` pixels starting at `offset` into `heights`.
+    ///
+    /// # Errors
+    /// Same as [`generate_heightmap`]. Additionally returns [`GenerationError::BufferSize`] if
+    /// `heights` does not hold exactly one height per pixel of `size`.
+    pub fn render_heights(
+        &self,
+        offset: (u32, u32),
+        size: (u32, u32),
+        heights: &mut [f64],
+    ) -> Result<(), GenerationError> {
+        self.render_heights_cancellable(offset, size, heights, &AtomicBool::new(false))
+    }
+
+    /// Same as [`generate_heightmap_cancellable`], with heights classified by the gradient of the
+    /// generator. The clamp counts only cover this render, unless other renders of the generator
+    /// run at the same time.
+    pub fn render_heightmap_cancellable(
+        &self,
+        heights: &mut [f64],
+        cancel: &AtomicBool,
+    ) -> Result<ClampCounts, GenerationError> {
+        let params = &self.params;
+        let (width, height) = (params.width, params.height);
+        let before = self.clamp_counts();
+        let clamped = || {
+            let after = self.clamp_counts();
+            ClampCounts {
+                low: after.low - before.low,
+                high: after.high - before.high,
+                total: after.total - before.total,
+            }
+        };
+
+        if params.mirror == Mirror::None {
+            self.render_heights_cancellable((0, 0), (width, height), heights, cancel)?;
+            return Ok(clamped());
+        }
+
+        let expected =
+            pixel_count(width, height).ok_or(GenerationError::TooLarge { width, height })?;
+        if heights.len() != expected {
+            return Err(GenerationError::BufferSize {
+                expected,
+                actual: heights.len(),
+            });
+        }
+
+        let (source_width, source_height) = params.mirror.source_size(width, height);
+        // the source is at most as large as the image, so its size is valid as well
+        let mut source = vec![0.0; pixel_count(source_width, source_height).unwrap()];
+        self.render_heights_cancellable(
+            (0, 0),
+            (source_width, source_height),
+            &mut source,
+            cancel,
+        )?;
+
+        let (width, source_width) = (width as usize, source_width as usize);
+        let source_height = source_height as usize;
+        for (idx, height) in heights.iter_mut().enumerate() {
+            let (x, y) = (idx % width, idx / width);
+            // positions beyond the source are reflected back into it
+            let x = if x < source_width { x } else { width - 1 - x };
+            let y = if y < source_height {
+                y
+            } else {
+                params.height as usize - 1 - y
+            };
+            *height = source[y * source_width + x];
+        }
+
+        Ok(clamped())
+    }
+
+    /// Same as [`Generator::render_heights`], but stops early once `cancel` is set.
+    pub fn render_heights_cancellable(
+        &self,
+        offset: (u32, u32),
+        size: (u32, u32),
+        heights: &mut [f64],
+        cancel: &AtomicBool,
+    ) -> Result<(), GenerationError> {
+        let (width, height) = size;
+        let expected =
+            pixel_count(width, height).ok_or(GenerationError::TooLarge { width, height })?;
+
+        if heights.len() != expected {
+            return Err(GenerationError::BufferSize {
+                expected,
+                actual: heights.len(),
+            });
+        }
+
+        let window = Window {
+            x: offset.0 as usize,
+            y: offset.1 as usize,
+            width: width as usize,
+        };
+        self.fill(heights, window, 1, cancel)
+    }
+
+    /// Fills `heights` with the window of the map sampled `factor` times per pixel and axis.
+    fn fill(
+        &self,
+        heights: &mut [f64],
+        window: Window,
+        factor: u32,
+        cancel: &AtomicBool,
+    ) -> Result<(), GenerationError> {
+        let stop = Stop {
+            cancel,
+            deadline: self.params.timeout.map(|timeout| Instant::now() + timeout),
+            timed_out: AtomicBool::new(false),
+        };
+
+        let (field, field_b) = self.fields(window, factor, &stop);
+
+        match field_b {
+            Some(field_b) => {
+                field.fill(heights)?;
+
+                let mut heights_b = vec![0.0; heights.len()];
+                field_b.fill(&mut heights_b)?;
+
+                blend_heights(heights, &heights_b, self.params.blend);
+                Ok(())
+            }
+            None => field.fill(heights),
+        }
+    }
+
+    /// Height fields for `window`, the second one is only returned if it is blended in.
+    fn fields<'a>(
+        &'a self,
+        window: Window,
+        factor: u32,
+        stop: &'a Stop<'a>,
+    ) -> (Field<'a>, Option<Field<'a>>) {
+        let (noise, noise_b): (&PlaneNoiseRef, Option<&PlaneNoiseRef>) = match &self.noise {
+            Some((noise, noise_b)) => (noise.as_ref(), noise_b.as_deref().map(|noise| noise as _)),
+            None => (&self.perlin, self.perlin_b.as_ref().map(|noise| noise as _)),
+        };
+
+        let field = Field {
+            params: &self.params,
+            steps: self.steps.map(|step| step / factor as f64),
+            offsets: octave_offsets(&self.params, self.params.seed),
+            perlin: self.perlin,
+            noise,
+            perlin_plane: self.noise.is_none(),
+            sampling: Sampling::new(&self.params, factor),
+            gradient: &self.gradient,
+            band_noise: self.params.has_band_noise(),
+            seed: self.params.seed,
+            window,
+            stop,
+            clamped: &self.clamped,
+        };
+
+        let field_b = match (self.perlin_b, noise_b) {
+            (Some(perlin_b), Some(noise_b)) => Some(Field {
+                perlin: perlin_b,
+                noise: noise_b,
+                seed: self.params.seed_b.unwrap_or_default(),
+                offsets: octave_offsets(&self.params, self.params.seed_b.unwrap_or_default()),
+                ..field
+            }),
+            _ => None,
+        };
+
+        (field, field_b)
+    }
+}
+
+/// Distance in noise space between neighboring pixels for each octave of the image of `params`.
+///
+/// The noise frequency is relative to [`GenParams::feature_scale`] if set and to the shorter side
+/// of the image otherwise.
+///
+/// # Examples
+/// With a fixed feature scale, a larger image shows more of the same map instead of a scaled up
+/// version of it.
+/// ```rust
+/// use map_generation::generator::{generate_heightmap, GenParams};
+///
+/// let mut small_params = GenParams::new(12345, 100, 80, 0.2, 0.1);
+/// small_params.feature_scale = Some(150.0);
+/// let large_params = GenParams {
+///     width: 200,
+///     height: 160,
+///     ..small_params
+/// };
+///
+/// let mut small = vec![0.0; 100 * 80];
+/// let mut large = vec![0.0; 200 * 160];
+/// generate_heightmap(&mut small, &small_params).unwrap();
+/// generate_heightmap(&mut large, &large_params).unwrap();
+///
+/// for y in 0..80 {
+///     assert_eq!(small[y * 100..(y + 1) * 100], large[y * 200..y * 200 + 100]);
+/// }
+/// ```
+pub fn octave_steps(params: &GenParams) -> [f64; SCALES.len()] {
+    match params.feature_scale {
+        Some(feature_scale) => SCALES.map(|scale| scale / feature_scale),
+        None => {
+            SCALES.map(|scale| f64::min(scale / params.width as f64, scale / params.height as f64))
+        }
+    }
+}
+
+/// Shift in noise space of each octave of the field of `seed`, up to the octave jitter of
+/// `params` along each axis.
+///
+/// All offsets are 0.0 if the jitter is, so the sampled coordinates stay exactly the same.
+fn octave_offsets(params: &GenParams, seed: u64) -> [[f64; 4]; SCALES.len()] {
+    let jitter = params.octave_jitter;
+    std::array::from_fn(|octave| {
+        std::array::from_fn(|axis| {
+            if jitter == 0.0 {
+                return 0.0;
+            }
+            let key = (octave as u64, axis as u8, "octave-offset");
+            (params.jitter_rng.unit(seed, &key) * 2.0 - 1.0) * jitter
+        })
+    })
+}
+
+/// Height of the pixel at `x`, `y` of a single height field, sampled from `perlin`.
+///
+/// This is exactly the computation used for every pixel while rendering, so the height field of
+/// `GenParams::new(seed, width, height, base_level, noise_strength)` is reproduced by passing its
+/// [`octave_steps`] and `Perlin::new(noise_seed(seed))`. All other parameters keep the defaults of
+/// [`GenParams::new`], use [`Generator::inspect`] for pixels of other parameters.
+///
+/// # Examples
+/// ```rust
+/// use map_generation::generator::{self, compute_height, octave_steps, GenParams};
+/// use noise::Perlin;
+///
+/// let params = GenParams::new(12345, 100, 100, 0.2, 0.1);
+/// let mut heights = vec![0.0; 100 * 100];
+/// generator::generate_heightmap(&mut heights, &params).unwrap();
+///
+/// let steps = octave_steps(&params);
+/// let height = compute_height(10, 20, &steps, &Perlin::new(12345), 0.2, 0.1, 12345);
+/// assert_eq!(height, heights[20 * 100 + 10]);
+/// ```
+pub fn compute_height<N>(
+    x: usize,
+    y: usize,
+    steps: &[f64; SCALES.len()],
+    perlin: &N,
+    base_level: f64,
+    noise_strength: f64,
+    seed: u64,
+) -> f64
+where
+    N: NoiseFn<f64, 2> + Sync,
+{
+    // the dimensions only matter for wrapping, rotated and equal area sampling
+    let params = &GenParams::new(seed, 1, 1, base_level, noise_strength);
+    let cancel = AtomicBool::new(false);
+    let stop = Stop {
+        cancel: &cancel,
+        deadline: None,
+        timed_out: AtomicBool::new(false),
+    };
+    let gradient = Gradient::default();
+    let field = Field {
+        params,
+        steps: *steps,
+        offsets: octave_offsets(params, seed),
+        perlin: Perlin::new(noise_seed(seed)),
+        noise: perlin,
+        perlin_plane: false,
+        sampling: Sampling::new(params, 1),
+        gradient: &gradient,
+        band_noise: params.has_band_noise(),
+        seed,
+        window: Window {
+            x: 0,
+            y: 0,
+            width: params.width as usize,
+        },
+        stop: &stop,
+        clamped: &ClampCounters::default(),
+    };
+
+    compute_pixel(&field, x, y).height
+}
+
+/// Intermediate values of the computation of one height, see [`Generator::inspect`].
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PixelTrace {
+    /// noise value of each octave, already multiplied by its weight
+    pub octaves: [f64; SCALES.len()],
+    /// octaves combined by [`GenParams::octave_blend`], shifted by 0.5 so it is centered on the
+    /// middle of the level range
+    pub octave_sum: f64,
+    /// white noise value before it is scaled by the noise strength
+    pub white_noise: f64,
+    /// height after base level and white noise are applied, before contrast and bias
+    pub leveled: f64,
+    /// final height of the field
+    pub height: f64,
+}
+
+/// Result of [`Generator::inspect`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct PixelInspection {
+    /// computation of the height field of `seed`
+    pub trace: PixelTrace,
+    /// computation of the height field of `seed_b`, if it is blended in
+    pub trace_b: Option<PixelTrace>,
+    /// final height of the pixel
+    pub height: f64,
+    /// index of the gradient band the height falls into
+    pub band: Option<usize>,
+    /// color of the pixel
+    pub color: image::Rgb<u8>,
+}
+
+/// Mapping from sample positions to noise coordinates, the same for every sample of a field.
+#[derive(Debug, Clone, Copy)]
+struct Sampling {
+    /// center of the image in samples, which rotation is applied around
+    center: (f64, f64),
+    /// sine and cosine of the rotation angle, `None` if not rotated
+    rotation: Option<(f64, f64)>,
+    /// size of the area after which the noise repeats, if it wraps at all
+    wrap: Option<(u32, u32)>,
+    /// width after which the noise repeats horizontally, if only that axis wraps
+    wrap_x: Option<u32>,
+    /// height of the image in samples if the sampling is adjusted by latitude
+    equal_area: Option<f64>,
+}
+
+impl Sampling {
+    fn new(params: &GenParams, factor: u32) -> Self {
+        let (image_width, image_height) = (
+            params.width.saturating_mul(factor),
+            params.height.saturating_mul(factor),
+        );
+        let wrap = match params.world_size {
+            Some((width, height)) => Some((
+                width.saturating_mul(factor).max(1),
+                height.saturating_mul(factor).max(1),
+            )),
+            None => params.seamless.then_some((image_width, image_height)),
+        };
+
+        Self {
+            center: (image_width as f64 / 2.0, image_height as f64 / 2.0),
+            // an angle of 0 skips the rotation, keeping the sample coordinates exact
+            rotation: (params.rotation != 0.0).then(|| params.rotation.to_radians().sin_cos()),
+            wrap_x: (wrap.is_none() && params.antimeridian_wrap).then_some(image_width.max(1)),
+            wrap,
+            equal_area: params.equal_area.then_some(image_height as f64),
+        }
+    }
+
+    /// Length of the circle of latitude of row `y` relative to the equator if the sampling is
+    /// adjusted by latitude, 1.0 otherwise.
+    #[inline]
+    fn latitude_scale(&self, y: usize) -> f64 {
+        match self.equal_area {
+            Some(height) => (((y as f64 + 0.5) / height - 0.5) * std::f64::consts::PI).cos(),
+            None => 1.0,
+        }
+    }
+}
+
+/// Position of a sample, see [`Sampling::position`].
+#[derive(Debug, Clone, Copy)]
+struct Position {
+    /// column of the sample, wrapped around if the field wraps
+    x: usize,
+    /// row of the sample, wrapped around if the field wraps
+    y: usize,
+    /// coordinates in the noise plane before scaling by the octave step
+    sample: (f64, f64),
+}
+
+impl Sampling {
+    /// Wraps the sample at `x`, `y` around and maps it to the noise plane.
+    #[inline]
+    fn position(&self, x: usize, y: usize) -> Position {
+        let (x, y) = match (self.wrap, self.wrap_x) {
+            (Some((width, height)), _) => (x % width as usize, y % height as usize),
+            (None, Some(width)) => (x % width as usize, y),
+            (None, None) => (x, y),
+        };
+
+        let (sample_x, sample_y) = match self.rotation {
+            Some((sin, cos)) => {
+                let (dx, dy) = (x as f64 - self.center.0, y as f64 - self.center.1);
+                (
+                    self.center.0 + dx * cos - dy * sin,
+                    self.center.1 + dx * sin + dy * cos,
+                )
+            }
+            None => (x as f64, y as f64),
+        };
+
+        // a row at latitude φ is a circle cos φ times as long as the equator, so its samples are
+        // pulled together towards the center column by the same factor
+        let sample_x = match self.equal_area {
+            Some(_) if self.wrap.is_none() => {
+                self.center.0 + (sample_x - self.center.0) * self.latitude_scale(y)
+            }
+            _ => sample_x,
+        };
+
+        Position {
+            x,
+            y,
+            sample: (sample_x, sample_y),
+        }
+    }
+}
+
+/// Borrowed noise function shared by the worker threads of a [`Field`].
+type PlaneNoiseRef<'a> = dyn NoiseFn<f64, 2> + Sync + 'a;
+
+/// Area of the image a height buffer covers. Positions are in samples of the full image.
+#[derive(Debug, Clone, Copy)]
+struct Window {
+    x: usize,
+    y: usize,
+    width: usize,
+}
+
+/// Everything the worker threads need to compute the heights of one noise field.
+#[derive(Clone, Copy)]
+struct Field<'a> {
+    params: &'a GenParams,
+    steps: [f64; SCALES.len()],
+    /// shift of each octave in noise space, see [`GenParams::octave_jitter`]
+    offsets: [[f64; 4]; SCALES.len()],
+    /// used for four dimensional sampling of wrapping images
+    perlin: Perlin,
+    /// used for sampling the plane
+    noise: &'a PlaneNoiseRef<'a>,
+    /// whether `noise` is Perlin noise, whose slope and curvature bound the error of a
+    /// [`CoarseGrid`]
+    perlin_plane: bool,
+    sampling: Sampling,
+    /// classifies heights for [`GenParams::band_noise_strength`] and [`GenParams::fast`]
+    gradient: &'a Gradient,
+    /// whether any band has its own noise strength
+    band_noise: bool,
+    seed: u64,
+    window: Window,
+    stop: &'a Stop<'a>,
+    clamped: &'a ClampCounters,
+}
+
+impl Field<'_> {
+    /// Weighted noise of the octave at `idx` at `position`.
+    #[inline]
+    fn octave(&self, idx: usize, position: Position) -> f64 {
+        let step = self.steps[idx];
+        let offset = self.offsets[idx];
+        let noise = match (self.sampling.wrap, self.sampling.wrap_x) {
+            (Some((width, height)), _) => seamless_noise(
+                self.perlin,
+                step,
+                position.x,
+                position.y,
+                (width, height),
+                offset,
+            ),
+            (None, Some(width)) => cylinder_noise(
+                self.perlin,
+                step,
+                position.x,
+                position.y,
+                width,
+                self.sampling.latitude_scale(position.y),
+                offset,
+            ),
+            (None, None) => self.noise.get([
+                step * position.sample.0 + offset[0],
+                step * position.sample.1 + offset[1],
+            ]),
+        };
+        noise * WEIGHTS[idx]
+    }
+
+    /// Strength of the white noise added to `level`, a height before the noise is applied.
+    #[inline]
+    fn noise_strength(&self, level: f64) -> f64 {
+        let params = self.params;
+        let strength = match &params.noise_strength_curve {
+            Some(curve) => curve.strength(level),
+            None => params.noise_strength,
+        };
+        if !self.band_noise {
+            return strength;
+        }
+
+        self.gradient
+            .terrain_kind(level)
+            .and_then(|kind| {
+                params
+                    .band_noise_strength
+                    .get(kind as usize)
+                    .copied()
+                    .flatten()
+            })
+            .unwrap_or(strength)
+    }
+
+    fn fill(&self, heights: &mut [f64]) -> Result<(), GenerationError> {
+        let thread_count = effective_thread_count(
+            heights.len(),
+            self.params.thread_count,
+            self.params.min_thread_chunk,
+        );
+        let area_size = area_size(heights.len(), thread_count);
+
+        let _ = run_jobs(
+            heights
+                .chunks_mut(area_size)
+                .enumerate()
+                .map(|(area, slice)| move || job(slice, area * area_size, self)),
+        );
+
+        self.stop.result()
+    }
+}
+
+/// Shared state of the worker threads telling them to stop early.
+struct Stop<'a> {
+    cancel: &'a AtomicBool,
+    deadline: Option<Instant>,
+    timed_out: AtomicBool,
+}
+
+impl Stop<'_> {
+    /// Whether work should stop, either because of cancellation or the deadline passed.
+    fn check(&self) -> bool {
+        if self.cancel.load(Ordering::Relaxed) || self.timed_out.load(Ordering::Relaxed) {
+            return true;
+        }
+
+        if self
+            .deadline
+            .is_some_and(|deadline| Instant::now() >= deadline)
+        {
+            self.timed_out.store(true, Ordering::Relaxed);
+            return true;
+        }
+
+        false
+    }
+
+    fn result(&self) -> Result<(), GenerationError> {
+        if self.timed_out.load(Ordering::Relaxed) {
+            Err(GenerationError::TimedOut)
+        } else if self.cancel.load(Ordering::Relaxed) {
+            Err(GenerationError::Cancelled)
+        } else {
+            Ok(())
+        }
+    }
+}
+
+/// Number of samples whose height was out of range and clamped, see [`Generator::clamp_counts`].
+///
+/// High counts mean the base level and noise strength push many heights past the ends of the
+/// range, which flattens the lowest and highest terrain.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ClampCounts {
+    /// samples raised to [`MIN_HEIGHT`]
+    pub low: usize,
+    /// samples lowered to [`MAX_HEIGHT`]
+    pub high: usize,
+    /// all computed samples, including those of a blended second field
+    pub total: usize,
+}
+
+/// Counters of clamped samples shared by the worker threads, each adds its sums once done.
+#[derive(Debug, Default)]
+struct ClampCounters {
+    low: AtomicUsize,
+    high: AtomicUsize,
+    total: AtomicUsize,
+}
+
+impl ClampCounters {
+    fn add(&self, low: usize, high: usize, total: usize) {
+        self.low.fetch_add(low, Ordering::Relaxed);
+        self.high.fetch_add(high, Ordering::Relaxed);
+        self.total.fetch_add(total, Ordering::Relaxed);
+    }
+
+    fn counts(&self) -> ClampCounts {
+        ClampCounts {
+            low: self.low.load(Ordering::Relaxed),
+            high: self.high.load(Ordering::Relaxed),
+            total: self.total.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// Linearly blends `other` into `heights` per pixel.
+///
+/// A `factor` of 0.0 keeps `heights` unchanged, 1.0 replaces them with `other`.
+pub fn blend_heights(heights: &mut [f64], other: &[f64], factor: f64) {
+    for (height, other) in heights.iter_mut().zip(other) {
+        *height = *height * (1.0 - factor) + other * factor;
+    }
+}
+
+/// White noise value in the range `[0, params.noise_magnitude)` of the pixel at `x`, `y`.
+///
+/// The value only depends on seed and position, so it is the same no matter how the image is
+/// split between threads. It is distributed by [`GenParams::noise_shape`] over
+/// [`GenParams::noise_granularity`] evenly spaced steps, fewer steps result in coarser grain. A
+/// granularity of 0 is treated as 1 and always yields 0. The random bits are drawn from
+/// [`GenParams::jitter_rng`].
+///
+/// # Examples
+/// ```rust
+/// use map_generation::generator::{white_noise, GenParams, NoiseShape};
+///
+/// let mut params = GenParams::new(12345, 100, 100, 0.2, 0.1);
+/// params.noise_magnitude = 0.05;
+/// params.noise_shape = NoiseShape::Bell;
+///
+/// let value = white_noise(params.seed, 3, 4, &params);
+/// assert!((0.0..0.05).contains(&value));
+/// ```
+pub fn white_noise(seed: u64, x: usize, y: usize, params: &GenParams) -> f64 {
+    let granularity = params.noise_granularity.max(1);
+
+    let hash = params.jitter_rng.bits(seed, &(x as u64, y as u64));
+    let step = params.noise_shape.step(hash, granularity);
+
+    step as f64 / granularity as f64 * params.noise_magnitude
+}
+
+/// Colors a height field using the given gradient.
+///
+/// # Arguments
+///   * `image_data` mutable reference to the result buffer. It must be three times as long as `heights`.
+///   * `heights` height field in row-major order as returned by [`generate_heightmap`]
+///   * `gradient` level and color gradient to color the height field with
+///   * `thread_count` number of threads used to color the image. If None, available CPUs - 1 is used.
+pub fn color_heights(
+    image_data: &mut [u8],
+    heights: &[f64],
+    gradient: &Gradient,
+    thread_count: Option<usize>,
+) {
+    color_heights_with(
+        image_data,
+        heights,
+        &|height| gradient.lerp_color(height),
+        thread_count,
+        DEFAULT_MIN_THREAD_CHUNK,
+    );
+}
+
+/// Colors a height field of `width * height` levels into a new image buffer.
+///
+/// Unlike [`color_heights`], it allocates the buffer itself and checks the dimensions, so a height
+/// field from [`generate_heightmap`] can be modified, e.g. eroded by an external tool, and colored
+/// afterwards. An unmodified height field results in the same image as [`generate`].
+///
+/// # Errors
+/// Returns [`GenerationError::TooLarge`] if the dimensions exceed [`MAX_PIXELS`] and
+/// [`GenerationError::BufferSize`] if `heights` does not hold exactly `width * height` levels.
+///
+/// # Examples
+/// ```rust
+/// use map_generation::generator::{self, color_heightmap, generate, generate_heightmap, GenParams};
+/// use map_generation::gradient::Gradient;
+///
+/// let params = GenParams::new(12345, 100, 80, 0.2, 0.1);
+/// let mut heights = vec![0.0; generator::pixel_count(100, 80).unwrap()];
+/// generate_heightmap(&mut heights, &params).unwrap();
+/// let colored = color_heightmap(&heights, 100, 80, &Gradient::default()).unwrap();
+///
+/// let mut generated = vec![0; generator::buffer_len(100, 80).unwrap()];
+/// generate(&mut generated, &params, None);
+/// assert_eq!(colored, generated);
+/// ```
+pub fn color_heightmap(
+    heights: &[f64],
+    width: u32,
+    height: u32,
+    gradient: &Gradient,
+) -> Result<Vec<u8>, GenerationError> {
+    let expected = pixel_count(width, height).ok_or(GenerationError::TooLarge { width, height })?;
+    if heights.len() != expected {
+        return Err(GenerationError::BufferSize {
+            expected,
+            actual: heights.len(),
+        });
+    }
+
+    let mut image_data = vec![0; expected * 3];
+    color_heights(&mut image_data, heights, gradient, None);
+    Ok(image_data)
+}
+
+/// Colors a height field by calling `color_fn` for each height, see [`color_heights`].
+///
+/// Each thread colors at least `min_thread_chunk` pixels if possible, see
+/// [`GenParams::min_thread_chunk`].
+pub fn color_heights_with<F>(
+    image_data: &mut [u8],
+    heights: &[f64],
+    color_fn: &F,
+    thread_count: Option<usize>,
+    min_thread_chunk: usize,
+) where
+    F: Fn(f64) -> image::Rgb<u8> + Sync,
+{
+    let thread_count = effective_thread_count(heights.len(), thread_count, min_thread_chunk);
+    let area_size = area_size(heights.len(), thread_count);
+
+    let _ = run_jobs(
+        image_data
+            .chunks_mut(area_size * 3)
+            .zip(heights.chunks(area_size))
+            .map(|(image, heights)| {
+                move || {
+                    for (pixel, height) in image.chunks_exact_mut(3).zip(heights) {
+                        pixel.copy_from_slice(&color_fn(*height).0);
+                    }
+                }
+            }),
+    );
+}
+
+/// Colors a supersampled height field, averaging `factor * factor` samples into each pixel.
+///
+/// # Arguments
+///   * `image_data` mutable reference to the result buffer of an image `width` pixels wide
+///   * `heights` height field `width * factor` samples wide in row-major order
+///   * `width` width of the resulting image in pixels
+///   * `factor` number of samples per pixel along each axis
+///   * `gradient` level and color gradient to color the samples with
+///   * `thread_count` number of threads used to color the image. If None, available CPUs - 1 is used.
+///
+/// # Errors
+/// Returns [`GenerationError::ZeroSampling`] if `width` or `factor` is 0 and
+/// [`GenerationError::BufferSize`] if `heights` does not hold `factor` sample rows per row of
+/// `image_data`.
+///
+/// # Examples
+/// Pixels across a band limit get a color between those of both bands.
+/// ```rust
+/// use map_generation::generator::color_supersampled;
+/// use map_generation::gradient::Gradient;
+///
+/// let gradient = Gradient {
+///     blend_width: 0.0,
+///     ..Gradient::default()
+/// };
+/// // two pixels, the first one entirely deep water, the second one half water
+/// let heights = [0.1, 0.1, 0.1, 0.5, 0.1, 0.1, 0.1, 0.5];
+/// let mut image = [0; 6];
+/// color_supersampled(&mut image, &heights, 2, 2, &gradient, None).unwrap();
+///
+/// let (deep_water, water) = (gradient.colors[0].0, gradient.colors[1].0);
+/// assert_eq!(image[..3], deep_water);
+/// for channel in 0..3 {
+///     let mixed = (deep_water[channel] as u32 + water[channel] as u32) / 2;
+///     assert_eq!(image[3 + channel] as u32, mixed);
+/// }
+/// ```
+pub fn color_supersampled(
+    image_data: &mut [u8],
+    heights: &[f64],
+    width: u32,
+    factor: u32,
+    gradient: &Gradient,
+    thread_count: Option<usize>,
+) -> Result<(), GenerationError> {
+    color_supersampled_with(
+        image_data,
+        heights,
+        width,
+        factor,
+        &|height| gradient.lerp_color(height),
+        thread_count,
+        DEFAULT_MIN_THREAD_CHUNK,
+    )
+}
+
+/// Colors a supersampled height field by calling `color_fn` for each sample, see
+/// [`color_supersampled`].
+///
+/// Each thread colors at least `min_thread_chunk` samples if possible, see
+/// [`GenParams::min_thread_chunk`].
+///
+/// # Errors
+/// Same as [`color_supersampled`].
+pub fn color_supersampled_with<F>(
+    image_data: &mut [u8],
+    heights: &[f64],
+    width: u32,
+    factor: u32,
+    color_fn: &F,
+    thread_count: Option<usize>,
+    min_thread_chunk: usize,
+) -> Result<(), GenerationError>
+where
+    F: Fn(f64) -> image::Rgb<u8> + Sync,
+{
+    if width == 0 || factor == 0 {
+        return Err(GenerationError::ZeroSampling { width, factor });
+    }
+
+    let width = width as usize;
+    let factor = factor as usize;
+    let sample_width = width * factor;
+    let samples = (factor * factor) as u32;
+
+    // the image holds whole rows and the height field `factor * factor` samples per pixel
+    let row_len = width * 3;
+    if !image_data.len().is_multiple_of(row_len) {
+        return Err(GenerationError::BufferSize {
+            expected: image_data.len().next_multiple_of(row_len),
+            actual: image_data.len(),
+        });
+    }
+    let expected = image_data.len() / 3 * factor * factor;
+    if heights.len() != expected {
+        return Err(GenerationError::BufferSize {
+            expected,
+            actual: heights.len(),
+        });
+    }
+
+    let thread_count = effective_thread_count(heights.len(), thread_count, min_thread_chunk);
+    let rows = image_data.len() / 3 / width;
+    let area_rows = area_size(rows, thread_count);
+
+    let _ = run_jobs(
+        image_data
+            .chunks_mut(area_rows * width * 3)
+            .enumerate()
+            .map(|(area, image)| {
+                move || {
+                    for (idx, pixel) in image.chunks_exact_mut(3).enumerate() {
+                        let x = idx % width;
+                        let y = area * area_rows + idx / width;
+
+                        let mut sum = [0u32; 3];
+                        for sample_y in y * factor..(y + 1) * factor {
+                            for sample_x in x * factor..(x + 1) * factor {
+                                let height = heights[sample_y * sample_width + sample_x];
+                                let color = color_fn(height).0;
+                                for channel in 0..3 {
+                                    sum[channel] += color[channel] as u32;
+                                }
+                            }
+                        }
+
+                        for channel in 0..3 {
+                            pixel[channel] = (sum[channel] / samples) as u8;
+                        }
+                    }
+                }
+            }),
+    );
+
+    Ok(())
+}
+
+/// Falls back to available CPUs - 1, but never to less than one thread.
+fn resolve_thread_count(thread_count: Option<usize>) -> usize {
+    thread_count
+        .unwrap_or(num_cpus::get().saturating_sub(1))
+        .max(1)
+}
+
+/// Runs `jobs` in parallel and waits until all are done.
+///
+/// Called within [`rayon::ThreadPool::install`], e.g. of a pool from [`thread_pool`], the jobs run
+/// on the threads of that pool. Otherwise a thread is started for each job. A single job runs on
+/// the calling thread. Returns the panic of a job if one panicked, except on a pool, where the
+/// panic is propagated right away.
+fn run_jobs<J>(jobs: impl IntoIterator<Item = J>) -> std::thread::Result<()>
+where
+    J: FnOnce() + Send,
+{
+    let mut jobs: Vec<J> = jobs.into_iter().collect();
+    if jobs.len() <= 1 {
+        return match jobs.pop() {
+            Some(job) => std::panic::catch_unwind(std::panic::AssertUnwindSafe(job)),
+            None => Ok(()),
+        };
+    }
+
+    if rayon::current_thread_index().is_some() {
+        rayon::scope(|scope| {
+            for job in jobs {
+                scope.spawn(move |_| job());
+            }
+        });
+        Ok(())
+    } else {
+        crossbeam::scope(|scope| {
+            for job in jobs {
+                scope.spawn(move |_| job());
+            }
+        })
+    }
+}
+
+/// Thread pool to share between many renders, so threads are started once instead of for every
+/// image, which matters for many small images.
+///
+/// Renders run within [`rayon::ThreadPool::install`] of the pool use its threads. The pool has as
+/// many threads as [`GenParams::thread_count`] resolves `thread_count` to.
+///
+/// # Examples
+/// ```rust
+/// use map_generation::generator::{self, GenParams};
+///
+/// let pool = generator::thread_pool(Some(2)).unwrap();
+/// let heights: Vec<Vec<f64>> = pool.install(|| {
+///     (0..10)
+///         .map(|seed| {
+///             let mut heights = vec![0.0; 64 * 64];
+///             generator::generate_heightmap(&mut heights, &GenParams::new(seed, 64, 64, 0.2, 0.1))
+///                 .unwrap();
+///             heights
+///         })
+///         .collect()
+/// });
+///
+/// let mut unpooled = vec![0.0; 64 * 64];
+/// generator::generate_heightmap(&mut unpooled, &GenParams::new(9, 64, 64, 0.2, 0.1)).unwrap();
+/// assert_eq!(heights[9], unpooled);
+/// ```
+pub fn thread_pool(
+    thread_count: Option<usize>,
+) -> Result<rayon::ThreadPool, rayon::ThreadPoolBuildError> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(resolve_thread_count(thread_count))
+        .thread_name(|idx| format!("map-generation-{idx}"))
+        .build()
+}
+
+/// Number of threads working on `pixel_count` pixels, so that each gets at least `min_chunk`
+/// pixels if possible. `thread_count` is resolved like [`GenParams::thread_count`].
+fn effective_thread_count(
+    pixel_count: usize,
+    thread_count: Option<usize>,
+    min_chunk: usize,
+) -> usize {
+    resolve_thread_count(thread_count)
+        .min(pixel_count / min_chunk.max(1))
+        .max(1)
+}
+
+/// Number of pixels of an image with `pixel_count` pixels each thread generates, the last thread
+/// may get fewer. `thread_count` and `min_chunk` are resolved like [`GenParams::thread_count`]
+/// and [`GenParams::min_thread_chunk`].
+///
+/// Useful to visualize how an image is split between threads.
+///
+/// # Examples
+/// ```rust
+/// use map_generation::generator::thread_area_size;
+///
+/// // tiny images are generated by a single thread
+/// assert_eq!(thread_area_size(64 * 64, Some(16), 4096), 64 * 64);
+/// assert_eq!(thread_area_size(1024 * 1024, Some(16), 4096), 1024 * 1024 / 16);
+/// ```
+pub fn thread_area_size(
+    pixel_count: usize,
+    thread_count: Option<usize>,
+    min_chunk: usize,
+) -> usize {
+    area_size(
+        pixel_count,
+        effective_thread_count(pixel_count, thread_count, min_chunk),
+    )
+}
+
+/// Number of pixels each thread works on. The last area may be smaller.
+fn area_size(pixel_count: usize, thread_count: usize) -> usize {
+    pixel_count.div_ceil(thread_count).max(1)
+}
+
+fn job(heights: &mut [f64], start: usize, field: &Field) {
+    let window = field.window;
+    // the coarse grid relies on summed octaves of planar Perlin noise, sampled evenly
+    let sampling = &field.sampling;
+    let coarse_allowed = field.params.octave_blend == OctaveBlend::Add
+        && field.perlin_plane
+        && sampling.wrap.is_none()
+        && sampling.wrap_x.is_none()
+        && sampling.equal_area.is_none();
+    let mut coarse = (field.params.fast && coarse_allowed).then(|| CoarseGrid::new(field));
+    let (mut low, mut high) = (0, 0);
+
+    for (idx, height) in heights.iter_mut().enumerate() {
+        let x = window.x + (start + idx) % window.width;
+        let y = window.y + (start + idx) / window.width;
+
+        if (idx == 0 || x == window.x) && field.stop.check() {
+            field.clamped.add(low, high, idx);
+            return;
+        }
+
+        *height = match coarse
+            .as_mut()
+            .and_then(|coarse| coarse.height(field, x, y))
+        {
+            Some(height) => height.clamp(MIN_HEIGHT, MAX_HEIGHT),
+            None => compute_pixel(field, x, y).height,
+        };
+
+        // values at the limits were clamped, an exact hit is practically impossible
+        if *height <= MIN_HEIGHT {
+            low += 1;
+        } else if *height >= MAX_HEIGHT {
+            high += 1;
+        }
+    }
+
+    field.clamped.add(low, high, heights.len());
+}
+
+/// Distance between the points of a [`CoarseGrid`] in samples.
+const FAST_BLOCK: usize = 8;
+/// Number of octaves evaluated on a [`CoarseGrid`], starting with the coarsest.
+const COARSE_OCTAVES: usize = 5;
+/// Largest slope of Perlin noise, just above the exact 2.75 * √2 found by evaluating every
+/// combination of the gradients at the corners of a cell.
+const PERLIN_MAX_SLOPE: f64 = 3.9;
+/// Largest second derivative of Perlin noise along any direction, just above the 17.76 found the
+/// same way.
+const PERLIN_MAX_CURVATURE: f64 = 18.0;
+
+/// Sum of the coarse octaves evaluated every [`FAST_BLOCK`] samples, which heights far from band
+/// limits are interpolated from, see [`GenParams::fast`].
+///
+/// It holds the two grid rows around the current row of samples, as a job walks its samples row
+/// by row.
+struct CoarseGrid {
+    /// grid column of the first entry of a row
+    first_column: usize,
+    /// grid row of `top`, `bottom` is the one below it
+    row: usize,
+    top: Vec<Option<f64>>,
+    bottom: Vec<Option<f64>>,
+    /// largest difference between an interpolated and an exact height, from the skipped octaves
+    /// and the interpolation of the coarse ones
+    margin: f64,
+}
+
+impl CoarseGrid {
+    fn new(field: &Field) -> Self {
+        let columns = field.window.width / FAST_BLOCK + 3;
+
+        // blocks span at most their size in samples along each axis, also when rotated. Bilinear
+        // interpolation over a side `h` misses at most h² / 8 times the curvature per axis, and
+        // never more than the slope times the distance to the farthest corner.
+        let interpolation_error: f64 = (0..COARSE_OCTAVES)
+            .map(|idx| {
+                let side = field.steps[idx] * FAST_BLOCK as f64;
+                let second_order = side * side / 4.0 * PERLIN_MAX_CURVATURE;
+                let first_order = side * std::f64::consts::SQRT_2 * PERLIN_MAX_SLOPE;
+                WEIGHTS[idx] * second_order.min(first_order)
+            })
+            .sum();
+
+        // the noise strength may depend on the level, which is only approximated
+        let params = field.params;
+        let strengths: Vec<f64> = match &params.noise_strength_curve {
+            Some(curve) => curve
+                .points()
+                .iter()
+                .map(|(_, strength)| *strength)
+                .collect(),
+            None => vec![params.noise_strength],
+        };
+        let strengths = strengths
+            .into_iter()
+            .chain(params.band_noise_strength.into_iter().flatten());
+        let (min, max) = strengths.fold(
+            (f64::INFINITY, f64::NEG_INFINITY),
+            |(min, max), strength| (min.min(strength), max.max(strength)),
+        );
+        let noise_spread = max - min;
+
+        Self {
+            first_column: field.window.x / FAST_BLOCK,
+            row: usize::MAX,
+            top: vec![None; columns],
+            bottom: vec![None; columns],
+            margin: (WEIGHTS[COARSE_OCTAVES..].iter().sum::<f64>() + interpolation_error)
+                * (1.0 - field.params.base_level)
+                + params.noise_magnitude * noise_spread,
+        }
+    }
+
+    /// Approximated height of the sample at `x`, `y`. `None` if the skipped octaves or the
+    /// interpolation might move it into another band, it has to be computed exactly then.
+    fn height(&mut self, field: &Field, x: usize, y: usize) -> Option<f64> {
+        let (column, row) = (x / FAST_BLOCK, y / FAST_BLOCK);
+        if row != self.row {
+            if row == self.row.wrapping_add(1) {
+                std::mem::swap(&mut self.top, &mut self.bottom);
+                self.bottom.fill(None);
+            } else {
+                self.top.fill(None);
+                self.bottom.fill(None);
+            }
+            self.row = row;
+        }
+
+        let idx = column - self.first_column;
+        let top_left = Self::corner(field, &mut self.top[idx], column, row);
+        let top_right = Self::corner(field, &mut self.top[idx + 1], column + 1, row);
+        let bottom_left = Self::corner(field, &mut self.bottom[idx], column, row + 1);
+        let bottom_right = Self::corner(field, &mut self.bottom[idx + 1], column + 1, row + 1);
+
+        let fx = (x % FAST_BLOCK) as f64 / FAST_BLOCK as f64;
+        let fy = (y % FAST_BLOCK) as f64 / FAST_BLOCK as f64;
+        let top = top_left + (top_right - top_left) * fx;
+        let bottom = bottom_left + (bottom_right - bottom_left) * fx;
+        let coarse = top + (bottom - top) * fy;
+
+        let params = field.params;
+        let level = params.base_level + (coarse + 0.5) * (1.0 - params.base_level);
+        let noise = white_noise(field.seed, x, y, params);
+        let height = level + noise * field.noise_strength(level);
+
+        let band = field
+            .gradient
+            .get_terrain_kind(params.shape(height - self.margin))?;
+        let upper_band = field
+            .gradient
+            .get_terrain_kind(params.shape(height + self.margin))?;
+
+        (band == upper_band).then(|| params.shape(height))
+    }
+
+    /// Sum of the coarse octaves at the grid point `column`, `row`, computed once.
+    fn corner(field: &Field, corner: &mut Option<f64>, column: usize, row: usize) -> f64 {
+        *corner.get_or_insert_with(|| {
+            let position = field
+                .sampling
+                .position(column * FAST_BLOCK, row * FAST_BLOCK);
+            (0..COARSE_OCTAVES)
+                .map(|idx| field.octave(idx, position))
+                .sum()
+        })
+    }
+}
+
+/// Computes the height of the sample at `x`, `y` of `field`, keeping the intermediate values.
+#[inline]
+fn compute_pixel(field: &Field, x: usize, y: usize) -> PixelTrace {
+    let params = field.params;
+    let position = field.sampling.position(x, y);
+
+    let mut octaves = [0.0; SCALES.len()];
+    for (idx, octave) in octaves.iter_mut().enumerate() {
+        *octave = field.octave(idx, position);
+    }
+
+    let mut value = params.octave_blend.combine(&octaves);
+    value += 0.5;
+    let octave_sum = value;
+
+    let noise_value = white_noise(field.seed, position.x, position.y, params);
+
+    // map value to be inside valid range
+    value = params.base_level + value * (1.0 - params.base_level);
+    // and apply noise of the band the value falls into before the noise is added
+    value += noise_value * field.noise_strength(value);
+    let leveled = value;
+
+    value = params.shape(value);
+
+    PixelTrace {
+        octaves,
+        octave_sum,
+        white_noise: noise_value,
+        leveled,
+        // limit values to be within range
+        height: value.clamp(MIN_HEIGHT, MAX_HEIGHT),
+    }
+}
+
+/// Samples 3D noise on a cylinder, so only the horizontal axis repeats after `width` pixels.
+///
+/// Like [`seamless_noise`], but the vertical axis is sampled linearly. The circumference of the
+/// circle of each row is multiplied by `latitude_scale`. The cylinder is moved by `offset`.
+fn cylinder_noise(
+    perlin: Perlin,
+    step: f64,
+    x: usize,
+    y: usize,
+    width: u32,
+    latitude_scale: f64,
+    offset: [f64; 4],
+) -> f64 {
+    use std::f64::consts::TAU;
+
+    let radius = step * width as f64 / TAU * latitude_scale;
+    let angle = TAU * x as f64 / width as f64;
+
+    perlin.get([
+        radius * angle.cos() + offset[0],
+        radius * angle.sin() + offset[1],
+        step * y as f64 + offset[2],
+    ])
+}
+
+/// Samples 4D noise on a torus, so both axes repeat after `width` and `height` pixels.
+///
+/// Each axis is mapped onto a circle whose circumference matches the distance covered by the
+/// planar sampling, so features keep roughly the same size as without wrapping. The torus is moved
+/// by `offset`.
+fn seamless_noise(
+    perlin: Perlin,
+    step: f64,
+    x: usize,
+    y: usize,
+    (width, height): (u32, u32),
+    offset: [f64; 4],
+) -> f64 {
+    use std::f64::consts::TAU;
+
+    let radius_x = step * width as f64 / TAU;
+    let radius_y = step * height as f64 / TAU;
+    let angle_x = TAU * x as f64 / width as f64;
+    let angle_y = TAU * y as f64 / height as f64;
+
+    perlin.get([
+        radius_x * angle_x.cos() + offset[0],
+        radius_x * angle_x.sin() + offset[1],
+        radius_y * angle_y.cos() + offset[2],
+        radius_y * angle_y.sin() + offset[3],
+    ])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn thread_count_does_not_change_image() {
+        let render = |thread_count| {
+            let params = GenParams {
+                thread_count: Some(thread_count),
+                min_thread_chunk: 1,
+                ..GenParams::new(12345, 300, 200, 0.2, 0.1)
+            };
+            let mut buffer = vec![0; buffer_len(300, 200).unwrap()];
+            generate_into(&mut buffer, &Gradient::default(), &params).unwrap();
+            buffer
+        };
+
+        let single = render(1);
+        assert_eq!(render(3), single);
+        assert_eq!(render(8), single);
+    }
+
+    #[test]
+    fn compute_height_is_stable() {
+        let steps = octave_steps(&GenParams::new(12345, 100, 100, 0.2, 0.1));
+        let perlin = Perlin::new(noise_seed(12345));
+        let height = |x, y, base_level, noise_strength| {
+            compute_height(x, y, &steps, &perlin, base_level, noise_strength, 12345)
+        };
+
+        // all octaves vanish at the origin, leaving the middle of the range and the white noise
+        assert_eq!(height(0, 0, 0.2, 0.1), 0.6005230000000001);
+        assert_eq!(height(10, 20, 0.2, 0.1), 0.5923646522260644);
+        assert_eq!(height(99, 57, 0.2, 0.1), 0.6246232042091444);
+        assert_eq!(height(10, 20, 0.6, 0.0), 0.7957978261130322);
+    }
+
+    #[test]
+    fn inspect_outside_of_image() {
+        let params = GenParams {
+            mirror: Mirror::Quad,
+            ..GenParams::new(12345, 31, 21, 0.2, 0.1)
+        };
+        let generator = Generator::new(params, Gradient::default());
+        let mut heights = vec![0.0; 31 * 21];
+        generate_heightmap(&mut heights, &params).unwrap();
+
+        assert_eq!(
+            generator.inspect(30, 20).unwrap().height,
+            heights[20 * 31 + 30]
+        );
+        assert!(generator.inspect(31, 0).is_none());
+        assert!(generator.inspect(0, 21).is_none());
+        assert!(generator.inspect(u32::MAX, u32::MAX).is_none());
+    }
+
+    #[test]
+    fn pixel_count_limit() {
+        assert_eq!(pixel_count(65536, 65536), None);
+        assert_eq!(pixel_count(65536, 65537), None);
+        assert_eq!(pixel_count(u32::MAX, u32::MAX), None);
+        assert_eq!(pixel_count(65536, 65535), Some(65536 * 65535));
+        assert_eq!(pixel_count(u32::MAX, 1), Some(MAX_PIXELS as usize));
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn generate_into_limit() {
+        let generate = |width, height| {
+            let params = GenParams::new(12345, width, height, 0.2, 0.1);
+            generate_into(&mut [], &Gradient::default(), &params)
+        };
+
+        for (width, height) in [(65536, 65536), (65537, 65536), (u32::MAX, u32::MAX)] {
+            assert_eq!(
+                generate(width, height),
+                Err(GenerationError::TooLarge { width, height })
+            );
+        }
+        // accepted, only the empty buffer is too short
+        assert_eq!(
+            generate(65536, 65535),
+            Err(GenerationError::BufferSize {
+                expected: 65536 * 65535 * 3,
+                actual: 0,
+            })
+        );
+    }
+
+    #[test]
+    fn mirrored_heights_are_symmetric() {
+        for (width, height) in [(40, 30), (41, 31), (1, 1), (2, 3)] {
+            let heightmap = |mirror| {
+                let params = GenParams {
+                    mirror,
+                    ..GenParams::new(12345, width, height, 0.2, 0.1)
+                };
+                let mut heights = vec![0.0; pixel_count(width, height).unwrap()];
+                generate_heightmap(&mut heights, &params).unwrap();
+
+                let streamed = Mutex::new(vec![0.0; heights.len()]);
+                generate_streaming(&params, |y, row| {
+                    let start = y as usize * width as usize;
+                    streamed.lock().unwrap()[start..start + row.len()].copy_from_slice(row);
+                })
+                .unwrap();
+                assert_eq!(streamed.into_inner().unwrap(), heights);
+
+                heights
+            };
+            let (width, height) = (width as usize, height as usize);
+            let plain = heightmap(Mirror::None);
+
+            for mirror in [Mirror::Horizontal, Mirror::Vertical, Mirror::Quad] {
+                let heights = heightmap(mirror);
+                let at = |x: usize, y: usize| heights[y * width + x];
+                let mirrors_x = matches!(mirror, Mirror::Horizontal | Mirror::Quad);
+                let mirrors_y = matches!(mirror, Mirror::Vertical | Mirror::Quad);
+
+                for y in 0..height {
+                    for x in 0..width {
+                        if mirrors_x {
+                            assert_eq!(at(x, y), at(width - 1 - x, y), "{mirror:?} {x},{y}");
+                        }
+                        if mirrors_y {
+                            assert_eq!(at(x, y), at(x, height - 1 - y), "{mirror:?} {x},{y}");
+                        }
+                    }
+                }
+
+                // the generated part is the same as without mirroring
+                let (source_width, source_height) = mirror.source_size(width as u32, height as u32);
+                for y in 0..source_height as usize {
+                    for x in 0..source_width as usize {
+                        assert_eq!(at(x, y), plain[y * width + x]);
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn noise_shapes_favor_the_middle() {
+        let middle_share = |noise_shape| {
+            let params = GenParams {
+                noise_magnitude: 0.2,
+                noise_shape,
+                ..GenParams::new(12345, 100, 100, 0.2, 0.1)
+            };
+            let values: Vec<f64> = (0..10_000)
+                .map(|idx| white_noise(params.seed, idx % 100, idx / 100, &params))
+                .collect();
+            assert!(values.iter().all(|value| (0.0..0.2).contains(value)));
+            values
+                .iter()
+                .filter(|value| (0.05..0.15).contains(*value))
+                .count() as f64
+                / values.len() as f64
+        };
+
+        // half, three quarters and eleven twelfths of the values lie in the middle half
+        let uniform = middle_share(NoiseShape::Uniform);
+        let triangular = middle_share(NoiseShape::Triangular);
+        let bell = middle_share(NoiseShape::Bell);
+        assert!((uniform - 0.5).abs() < 0.03, "{uniform}");
+        assert!((triangular - 0.75).abs() < 0.03, "{triangular}");
+        assert!(bell > triangular + 0.05, "{bell}");
+    }
+
+    #[test]
+    fn band_noise_follows_custom_gradient() {
+        // deep water reaches up to 0.55 instead of 0.4
+        let mut gradient = Gradient::default();
+        gradient.terrain_limits[TerrainKind::DeepWater as usize][1] = 0.55;
+        gradient.terrain_limits[TerrainKind::Water as usize][0] = 0.55;
+
+        let clean = GenParams::new(12345, 64, 64, 0.2, 0.0);
+        let mut noisy = clean;
+        noisy.band_noise_strength[TerrainKind::DeepWater as usize] = Some(5.0);
+
+        let mut levels = vec![0.0; 64 * 64];
+        generate_heightmap(&mut levels, &clean).unwrap();
+        let render = |params| {
+            let mut buffer = vec![0; buffer_len(64, 64).unwrap()];
+            generate_into(&mut buffer, &gradient, &params).unwrap();
+            buffer
+        };
+        let (clean, noisy) = (render(clean), render(noisy));
+
+        let changed: Vec<f64> = levels
+            .iter()
+            .zip(clean.chunks(3).zip(noisy.chunks(3)))
+            .filter(|(_, (clean, noisy))| clean != noisy)
+            .map(|(level, _)| *level)
+            .collect();
+        assert!(changed.iter().all(|level| *level < 0.55));
+        // including pixels which are water in the default gradient
+        assert!(changed.iter().any(|level| *level >= 0.4));
+    }
+
+    #[test]
+    fn fast_keeps_bands_of_custom_gradient() {
+        // the default gradient has no limit in the deep water, so the heights around the trench
+        // would be approximated if they were classified by the default bands. Hard bands make each
+        // color stand for one band.
+        let mut gradient = Gradient {
+            blend_width: 0.0,
+            ..Gradient::default()
+        };
+        gradient.insert_band(0.2, image::Rgb([0, 0, 60]), "trench");
+
+        let exact = GenParams::new(12345, 256, 144, 0.0, 0.1);
+        let fast = GenParams {
+            fast: true,
+            ..exact
+        };
+        let render = |params| {
+            let mut buffer = vec![0; buffer_len(256, 144).unwrap()];
+            generate_into(&mut buffer, &gradient, &params).unwrap();
+            buffer
+        };
+
+        assert!(render(exact) == render(fast));
+    }
+
+    #[test]
+    fn antialiasing_blends_band_limits() {
+        let gradient = Gradient {
+            blend_width: 0.0,
+            ..Gradient::default()
+        };
+        let render = |aa_factor| {
+            let params = GenParams {
+                aa_factor,
+                ..GenParams::new(12345, 120, 80, 0.2, 0.0)
+            };
+            let mut buffer = vec![0; buffer_len(120, 80).unwrap()];
+            Generator::new(params, gradient.clone())
+                .render((0, 0), (120, 80), &mut buffer)
+                .unwrap();
+            buffer
+        };
+        let band_colors = |buffer: &[u8]| {
+            buffer
+                .chunks_exact(3)
+                .filter(|pixel| gradient.colors.iter().any(|color| color.0 == **pixel))
+                .count()
+        };
+
+        // hard bands only give band colors, the pixels on band limits get mixed ones with --aa 2
+        let (aliased, smoothed) = (render(1), render(2));
+        assert_eq!(band_colors(&aliased), 120 * 80);
+        let mixed = 120 * 80 - band_colors(&smoothed);
+        assert!(mixed > 0 && mixed < 120 * 80 / 4, "{mixed} mixed pixels");
+    }
+
+    #[test]
+    fn supersampling_checks_dimensions() {
+        let gradient = Gradient::default();
+        let color = |image: &mut [u8], heights: &[f64], width, factor| {
+            color_supersampled(image, heights, width, factor, &gradient, None)
+        };
+
+        assert_eq!(
+            color(&mut [], &[], 0, 2),
+            Err(GenerationError::ZeroSampling {
+                width: 0,
+                factor: 2
+            })
+        );
+        assert_eq!(
+            color(&mut [0; 6], &[0.5; 8], 2, 0),
+            Err(GenerationError::ZeroSampling {
+                width: 2,
+                factor: 0
+            })
+        );
+        assert_eq!(
+            color(&mut [0; 6], &[0.5; 6], 2, 2),
+            Err(GenerationError::BufferSize {
+                expected: 8,
+                actual: 6
+            })
+        );
+        assert_eq!(
+            color(&mut [0; 9], &[0.5; 12], 2, 2),
+            Err(GenerationError::BufferSize {
+                expected: 12,
+                actual: 9
+            })
+        );
+        assert_eq!(color(&mut [0; 12], &[0.5; 16], 2, 2), Ok(()));
 
-        let [r, g, b] = gradient.lerp_color(value).0;
-        image[idx * 3] = r;
-        image[idx * 3 + 1] = g;
-        image[idx * 3 + 2] = b;
+        // images without pixels are rendered without sampling them
+        let params = GenParams {
+            aa_factor: 2,
+            ..GenParams::new(12345, 0, 10, 0.2, 0.1)
+        };
+        assert_eq!(generate_into(&mut [], &gradient, &params), Ok(()));
     }
 }
@@ -1,3 +1,7 @@
+use std::str::FromStr;
+
+use crate::ramp::ColorRamp;
+
 pub const SCALES: [f64; 7] = [1.0, 2.0, 4.0, 8.0, 16.0, 32.0, 64.0];
 pub const WEIGHTS: [f64; 7] = [0.35, 0.2, 0.15, 0.075, 0.075, 0.025, 0.025];
 
@@ -18,6 +22,24 @@ pub enum TerrainKind {
 }
 
 impl TerrainKind {
+    /// Number of terrain kinds besides [`TerrainKind::Undefined`].
+    pub const COUNT: usize = 8;
+
+    /// Name of the terrain kind in kebab-case, e.g. `flat-land`.
+    pub fn name(&self) -> &'static str {
+        match self {
+            TerrainKind::Undefined => "undefined",
+            TerrainKind::DeepWater => "deep-water",
+            TerrainKind::Water => "water",
+            TerrainKind::ShallowWater => "shallow-water",
+            TerrainKind::Shore => "shore",
+            TerrainKind::FlatLand => "flat-land",
+            TerrainKind::HighLand => "high-land",
+            TerrainKind::Mountains => "mountains",
+            TerrainKind::MountainTop => "mountain-top",
+        }
+    }
+
     pub fn before(&self) -> Self {
         match self {
             TerrainKind::Undefined => TerrainKind::Undefined,
@@ -63,145 +85,683 @@ impl From<usize> for TerrainKind {
     }
 }
 
+/// Parses the names returned by [`TerrainKind::name`] as well as the variant names.
+///
+/// Parsing ignores case, `-`, `_`, and spaces, so `flat-land`, `FlatLand`, and `flat_land` are all
+/// parsed as [`TerrainKind::FlatLand`].
+impl FromStr for TerrainKind {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let normalized: String = s
+            .chars()
+            .filter(|c| !matches!(c, '-' | '_' | ' '))
+            .map(|c| c.to_ascii_lowercase())
+            .collect();
+
+        match normalized.as_str() {
+            "undefined" => Ok(TerrainKind::Undefined),
+            "deepwater" => Ok(TerrainKind::DeepWater),
+            "water" => Ok(TerrainKind::Water),
+            "shallowwater" => Ok(TerrainKind::ShallowWater),
+            "shore" => Ok(TerrainKind::Shore),
+            "flatland" => Ok(TerrainKind::FlatLand),
+            "highland" => Ok(TerrainKind::HighLand),
+            "mountains" => Ok(TerrainKind::Mountains),
+            "mountaintop" => Ok(TerrainKind::MountainTop),
+            _ => Err(format!("{s} is not a terrain kind.")),
+        }
+    }
+}
+
+/// Height bands and the colors they are painted with.
+///
+/// Bands are ordered from lowest to highest and indexed from 0. The default gradient has one band
+/// per [`TerrainKind`], so band indices match `TerrainKind as usize` there, but any number of bands
+/// can be created with [`Gradient::insert_band`].
 #[derive(Clone)]
 pub struct Gradient {
     pub terrain_limits: Vec<[f64; 2]>,
     pub terrain_centers: Vec<f64>,
     pub colors: Vec<image::Rgb<u8>>,
+    /// name of each band, e.g. `flat-land`
+    pub names: Vec<String>,
+    /// bands each band blends into below and above its center in [`Gradient::lerp_color`]. These
+    /// are the adjacent bands, except in the default gradient, whose bands blend like
+    /// [`TerrainKind::before`] and [`TerrainKind::after`], e.g. the shore into the water.
+    pub blend_neighbors: Vec<[usize; 2]>,
+    /// continuous color map used instead of the bands when coloring, if set
+    pub ramp: Option<ColorRamp>,
+    /// whether [`Gradient::lerp_color`] samples [`Gradient::continuous_color`] instead of blending
+    /// each band with its neighbors only
+    pub continuous: bool,
+    /// share of the way from a band center to its limit over which [`Gradient::lerp_color`] blends
+    /// into the neighboring band, from 0.0 for hard bands like [`Gradient::get_color`] to 1.0 for
+    /// blending all the way from the center
+    pub blend_width: f64,
 }
 
 #[allow(dead_code, unused)]
 impl Gradient {
+    /// Bands are named after the [`TerrainKind`] with the same index, additional bands `band-<index>`.
     pub fn new(terrain_limits: Vec<[f64; 2]>, colors: Vec<image::Rgb<u8>>) -> Self {
         let terrain_centers = Gradient::calc_centers(&terrain_limits);
+        let names = (0..terrain_limits.len())
+            .map(|idx| match TerrainKind::from(idx) {
+                TerrainKind::Undefined => format!("band-{idx}"),
+                kind => kind.name().to_string(),
+            })
+            .collect();
+        let blend_neighbors = (0..terrain_limits.len())
+            .map(|idx| Gradient::adjacent(idx, terrain_limits.len()))
+            .collect();
+
         Self {
             terrain_limits,
             terrain_centers,
             colors,
+            names,
+            blend_neighbors,
+            ramp: None,
+            continuous: false,
+            blend_width: 1.0,
         }
     }
 
+    /// Gradient without any bands, to be filled with [`Gradient::insert_band`].
+    pub fn empty() -> Self {
+        Gradient::new(Vec::new(), Vec::new())
+    }
+
+    /// Default bands, but colored by the given continuous color map.
+    pub fn from_ramp(ramp: ColorRamp) -> Self {
+        Self {
+            ramp: Some(ramp),
+            ..Gradient::default()
+        }
+    }
+
+    /// Default bands, but colored with the given colors, one per band from lowest to highest.
+    ///
+    /// # Panics
+    /// Panics if the number of colors does not match the number of bands.
+    pub fn from_colors(colors: Vec<image::Rgb<u8>>) -> Self {
+        let default = Gradient::default();
+        assert_eq!(
+            colors.len(),
+            default.band_count(),
+            "Expected one color per terrain band"
+        );
+
+        Self { colors, ..default }
+    }
+
+    /// Adds a band ending at `limit`.
+    ///
+    /// The new band starts where the band below it ends, or at 0.0 if it is the lowest band. A band
+    /// containing `limit` is split, its upper part keeps its color and name. If a band already ends
+    /// at `limit`, its color and name are replaced instead. The new band blends into the bands
+    /// next to it, which blend into the new band on its side.
+    pub fn insert_band(&mut self, limit: f64, color: image::Rgb<u8>, name: impl Into<String>) {
+        let idx = self.terrain_limits.partition_point(|[_, max]| *max < limit);
+
+        if self
+            .terrain_limits
+            .get(idx)
+            .is_some_and(|[_, max]| *max == limit)
+        {
+            self.colors[idx] = color;
+            self.names[idx] = name.into();
+            return;
+        }
+
+        let min = match idx.checked_sub(1) {
+            Some(below) => self.terrain_limits[below][1],
+            None => 0.0,
+        };
+        if let Some(split) = self.terrain_limits.get_mut(idx) {
+            split[0] = limit;
+        }
+
+        self.terrain_limits.insert(idx, [min, limit]);
+        self.colors.insert(idx, color);
+        self.names.insert(idx, name.into());
+        self.terrain_centers = Gradient::calc_centers(&self.terrain_limits);
+
+        for neighbors in &mut self.blend_neighbors {
+            for neighbor in neighbors {
+                *neighbor += (*neighbor >= idx) as usize;
+            }
+        }
+        self.blend_neighbors
+            .insert(idx, Gradient::adjacent(idx, self.band_count()));
+        // neighbors which blended across the new band or into themselves blend into it now
+        if let Some(below) = idx.checked_sub(1) {
+            let after = &mut self.blend_neighbors[below][1];
+            if *after == idx + 1 || *after == below {
+                *after = idx;
+            }
+        }
+        if let Some([before, _]) = self.blend_neighbors.get_mut(idx + 1) {
+            if idx.checked_sub(1) == Some(*before) || *before == idx + 1 {
+                *before = idx;
+            }
+        }
+    }
+
+    /// Checks that the gradient has bands which cover a continuous range within 0.0 and 1.0 and one
+    /// color and name per band.
+    ///
+    /// # Errors
+    /// Returns a description of every problem found.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use map_generation::gradient::Gradient;
+    ///
+    /// assert!(Gradient::default().validate().is_ok());
+    /// assert_eq!(Gradient::empty().validate().unwrap_err().len(), 1);
+    /// ```
+    pub fn validate(&self) -> Result<(), Vec<String>> {
+        let mut problems = Vec::new();
+
+        if self.terrain_limits.is_empty() {
+            problems.push("The gradient must contain at least one band".to_string());
+        }
+        if self.colors.len() != self.band_count() {
+            problems.push(format!(
+                "The gradient has {} colors for {} bands",
+                self.colors.len(),
+                self.band_count()
+            ));
+        }
+        if self.names.len() != self.band_count() {
+            problems.push(format!(
+                "The gradient has {} names for {} bands",
+                self.names.len(),
+                self.band_count()
+            ));
+        }
+        if self.blend_neighbors.len() != self.band_count() {
+            problems.push(format!(
+                "The gradient has {} blend neighbors for {} bands",
+                self.blend_neighbors.len(),
+                self.band_count()
+            ));
+        }
+
+        for (idx, [min, max]) in self.terrain_limits.iter().enumerate() {
+            let name = self.band_name(idx).unwrap_or_default();
+            for limit in [min, max] {
+                if !(0.0..=1.0).contains(limit) {
+                    problems.push(format!("Band limit {limit} must be between 0 and 1"));
+                }
+            }
+            if let Some(neighbors) = self.blend_neighbors.get(idx) {
+                if neighbors
+                    .iter()
+                    .any(|neighbor| *neighbor >= self.band_count())
+                {
+                    problems.push(format!(
+                        "Band {name} blends into a band which does not exist"
+                    ));
+                }
+            }
+            if min > max {
+                problems.push(format!(
+                    "Band {name} starts at {min} above its end at {max}"
+                ));
+            }
+            if let Some([next_min, _]) = self.terrain_limits.get(idx + 1) {
+                if next_min != max {
+                    problems.push(format!(
+                        "Band {name} ends at {max}, but the next band starts at {next_min}"
+                    ));
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(problems)
+        }
+    }
+
+    /// Number of bands.
+    pub fn band_count(&self) -> usize {
+        self.terrain_limits.len()
+    }
+
+    /// Name of the band at `idx`, if it exists.
+    pub fn band_name(&self, idx: usize) -> Option<&str> {
+        self.names.get(idx).map(String::as_str)
+    }
+
+    /// Color of the band containing `height`. Heights outside of all bands get the color of the
+    /// closest band.
+    ///
+    /// # Panics
+    /// Panics if the gradient has no bands.
     pub fn get_color(&self, height: f64) -> image::Rgb<u8> {
-        let kind = self.get_terrain_kind(height).unwrap();
-        self.colors[kind as usize]
+        self.colors[self.closest_band(height)]
     }
 
+    /// Color of `height`, blended between the color of its band and the neighboring band on the
+    /// side of its center `height` lies on, see [`Gradient::blend_neighbors`].
+    ///
+    /// At the exact center of a band the color is that band's color, towards the center of a
+    /// neighboring band it moves linearly to the neighbor's color. With a `blend_width` below 1.0
+    /// the color stays the band's color until that share of the way to the band limit is left and
+    /// only blends in the rest, meeting the neighbor's blend at the limit. Uses the color ramp
+    /// instead, if set, and [`Gradient::continuous_color`] if `continuous` is set.
+    ///
+    /// # Panics
+    /// Panics if the gradient has no bands.
+    ///
+    /// # Examples
+    /// The color passes through each band center without a jump.
+    /// ```rust
+    /// use map_generation::gradient::Gradient;
+    ///
+    /// let gradient = Gradient::default();
+    ///
+    /// for (center, color) in gradient.terrain_centers.iter().zip(&gradient.colors) {
+    ///     assert_eq!(gradient.lerp_color(*center), *color);
+    ///     for offset in [1e-12, 1e-9, 1e-6] {
+    ///         assert_eq!(gradient.lerp_color(center - offset), *color);
+    ///         assert_eq!(gradient.lerp_color(center + offset), *color);
+    ///     }
+    /// }
+    /// ```
+    ///
+    /// Narrowing the blend width moves the colors towards those of [`Gradient::get_color`]. The
+    /// narrower the width, the fewer heights are blended and the less they differ from their band.
+    /// ```rust
+    /// use map_generation::gradient::Gradient;
+    ///
+    /// let softness = |blend_width: f64| {
+    ///     let gradient = Gradient {
+    ///         blend_width,
+    ///         ..Gradient::default()
+    ///     };
+    ///     let (mut blended, mut difference) = (0, 0);
+    ///     for step in 0..=1000 {
+    ///         let height = step as f64 / 1000.0;
+    ///         let (soft, hard) = (gradient.lerp_color(height).0, gradient.get_color(height).0);
+    ///         let channels = (0..3).map(|channel| soft[channel].abs_diff(hard[channel]) as u32);
+    ///         let channel_difference: u32 = channels.sum();
+    ///         blended += (channel_difference > 0) as u32;
+    ///         difference += channel_difference;
+    ///     }
+    ///     (blended, difference)
+    /// };
+    ///
+    /// assert_eq!(softness(0.0), (0, 0));
+    /// let widths = [0.0, 0.25, 0.5, 0.75, 1.0].map(softness);
+    /// assert!(widths.windows(2).all(|pair| pair[0].0 < pair[1].0 && pair[0].1 < pair[1].1));
+    /// ```
     pub fn lerp_color(&self, height: f64) -> image::Rgb<u8> {
-        let kind = self.get_terrain_kind(height).unwrap();
-        let kind_before = kind.before();
-        let kind_after = kind.after();
+        if let Some(ramp) = self.ramp {
+            return ramp.color(height);
+        }
+        if self.continuous {
+            return self.continuous_color(height);
+        }
 
-        let color = self.colors[kind as usize];
-        let color_before = self.colors[kind_before as usize];
-        let color_after = self.colors[kind_after as usize];
+        let idx = self.closest_band(height);
+        let [idx_before, idx_after] = self
+            .blend_neighbors
+            .get(idx)
+            .copied()
+            .unwrap_or_else(|| Gradient::adjacent(idx, self.band_count()));
 
-        let dist_self = height - self.terrain_centers[kind as usize];
+        let color = self.colors[idx];
+        let color_before = self.colors[idx_before];
+        let color_after = self.colors[idx_after];
+
+        let dist_self = height - self.terrain_centers[idx];
         let dist_self_abs = dist_self.abs();
-        let dist_before = (height - self.terrain_centers[kind_before as usize]).abs();
-        let dist_after = (height - self.terrain_centers[kind_after as usize]).abs();
+        // also avoids dividing 0 by 0 in the outermost bands, which have no neighbor on one side
+        if dist_self_abs == 0.0 {
+            return color;
+        }
+        let dist_before = (height - self.terrain_centers[idx_before]).abs();
+        let dist_after = (height - self.terrain_centers[idx_after]).abs();
 
         if dist_self < 0.0 {
             // height is closer to before
             let factor_before = dist_self_abs / (dist_before + dist_self_abs);
+            let factor_before = self.narrow_blend(factor_before, idx, idx_before);
             Gradient::_lerp_colors(&color_before, factor_before, &color)
         } else {
             // height is closer to after
             let factor_after = dist_self_abs / (dist_after + dist_self_abs);
+            let factor_after = self.narrow_blend(factor_after, idx, idx_after);
             Gradient::_lerp_colors(&color_after, factor_after, &color)
         }
     }
 
-    pub fn get_terrain_kind(&self, height: f64) -> Result<TerrainKind, ()> {
-        if let Some(kind) = self
-            .terrain_limits
-            .iter()
-            .position(|([min, max])| height > *min && height < *max)
-        {
-            Ok(TerrainKind::from(kind))
+    /// Scales `factor`, the share of the color of band `neighbor` in a color of band `idx`, to the
+    /// blend width.
+    ///
+    /// The factor the full blend reaches at the limit between the bands stays the same, so the
+    /// colors of both bands still meet there, but it is only approached from the point at which
+    /// `blend_width` of the way to the limit are left.
+    fn narrow_blend(&self, factor: f64, idx: usize, neighbor: usize) -> f64 {
+        if self.blend_width >= 1.0 || neighbor == idx {
+            return factor;
+        }
+        if self.blend_width <= 0.0 {
+            return 0.0;
+        }
+
+        let limit = if neighbor < idx {
+            self.terrain_limits[idx][0]
         } else {
-            panic!("Tried to get terrain kind of invalid height: {height}");
+            self.terrain_limits[idx][1]
+        };
+        let to_limit = (limit - self.terrain_centers[idx]).abs();
+        let across_limit = to_limit + (self.terrain_centers[neighbor] - limit).abs();
+        if across_limit == 0.0 {
+            return factor;
+        }
+        let at_limit = to_limit / across_limit;
+
+        ((factor - at_limit * (1.0 - self.blend_width)) / self.blend_width).max(0.0)
+    }
+
+    /// Color of a piecewise linear function through the colors of all bands at their centers.
+    ///
+    /// Between two band centers the color moves straight from one band color to the other, no
+    /// matter which band `height` falls into, so there is no jump at the band limits. Heights below
+    /// the lowest or above the highest center get the color of that band.
+    ///
+    /// # Panics
+    /// Panics if the gradient has no bands.
+    ///
+    /// # Examples
+    /// Sweeping from 0.0 to 1.0, every channel moves steadily towards the next band color and
+    /// passes the band limits without a jump.
+    /// ```rust
+    /// use map_generation::gradient::Gradient;
+    ///
+    /// let gradient = Gradient::default();
+    /// let color = |height: f64| gradient.continuous_color(height).0.map(i32::from);
+    ///
+    /// for (idx, pair) in gradient.terrain_centers.windows(2).enumerate() {
+    ///     let target = gradient.colors[idx + 1].0.map(i32::from);
+    ///     let mut previous = color(pair[0]);
+    ///     for step in 1..=100 {
+    ///         let current = color(pair[0] + (pair[1] - pair[0]) * step as f64 / 100.0);
+    ///         for channel in 0..3 {
+    ///             let direction = (target[channel] - previous[channel]).signum();
+    ///             assert!((current[channel] - previous[channel]) * direction >= 0);
+    ///         }
+    ///         previous = current;
+    ///     }
+    ///     assert_eq!(previous, target);
+    /// }
+    ///
+    /// for [_, limit] in &gradient.terrain_limits[..gradient.band_count() - 1] {
+    ///     let (below, above) = (color(limit - 1e-9), color(*limit));
+    ///     assert!((0..3).all(|channel| (below[channel] - above[channel]).abs() <= 1));
+    /// }
+    /// ```
+    pub fn continuous_color(&self, height: f64) -> image::Rgb<u8> {
+        let centers = &self.terrain_centers;
+        let after = centers.partition_point(|center| *center <= height);
+
+        if after == 0 {
+            return self.colors[0];
         }
+        if after == centers.len() {
+            return self.colors[after - 1];
+        }
+
+        // centers[after - 1] <= height < centers[after], so the segment is never empty
+        let (start, end) = (centers[after - 1], centers[after]);
+        let factor = (height - start) / (end - start);
+        let (from, to) = (self.colors[after - 1].0, self.colors[after].0);
+
+        image::Rgb(std::array::from_fn(|channel| {
+            let (from, to) = (from[channel] as f64, to[channel] as f64);
+            (from + (to - from) * factor).round() as u8
+        }))
+    }
+
+    /// Index of the band containing `height`, if any.
+    ///
+    /// Bands include their lower limit but not their upper one, except for the highest band which
+    /// includes both. So a height on the limit between two bands belongs to the upper band.
+    pub fn get_terrain_kind(&self, height: f64) -> Option<usize> {
+        let last = self.band_count().checked_sub(1)?;
+
+        self.terrain_limits
+            .iter()
+            .enumerate()
+            .position(|(idx, [min, max])| {
+                height >= *min && (height < *max || (idx == last && height == *max))
+            })
+    }
+
+    /// [`TerrainKind`] of `height`, only available if the gradient has one band per terrain kind
+    /// like the default gradient does.
+    pub fn terrain_kind(&self, height: f64) -> Option<TerrainKind> {
+        if self.band_count() != TerrainKind::MountainTop as usize + 1 {
+            return None;
+        }
+
+        self.get_terrain_kind(height).map(TerrainKind::from)
+    }
+
+    /// Index of the band containing `height`, or of the band closest to it if it is outside of all
+    /// bands.
+    fn closest_band(&self, height: f64) -> usize {
+        self.get_terrain_kind(height).unwrap_or_else(|| {
+            let below_all = self
+                .terrain_limits
+                .first()
+                .is_some_and(|[min, _]| height < *min);
+
+            if below_all {
+                0
+            } else {
+                self.band_count().saturating_sub(1)
+            }
+        })
     }
 
     fn _lerp_colors(one: &image::Rgb<u8>, factor: f64, other: &image::Rgb<u8>) -> image::Rgb<u8> {
         let factor_inverse = 1.0 - factor;
         let [r_one, g_one, b_one] = one.0;
         let [r_other, g_other, b_other] = other.0;
-        let r = (r_one as f64 * factor + r_other as f64 * factor_inverse) as u8;
-        let g = (g_one as f64 * factor + g_other as f64 * factor_inverse) as u8;
-        let b = (b_one as f64 * factor + b_other as f64 * factor_inverse) as u8;
+        // rounding instead of truncating keeps colors next to a band center from dropping by one
+        let r = (r_one as f64 * factor + r_other as f64 * factor_inverse).round() as u8;
+        let g = (g_one as f64 * factor + g_other as f64 * factor_inverse).round() as u8;
+        let b = (b_one as f64 * factor + b_other as f64 * factor_inverse).round() as u8;
         image::Rgb([r, g, b])
     }
 
-    fn calc_centers(terrain_limits: &Vec<[f64; 2]>) -> Vec<f64> {
-        let mut terrain_centers = vec![0.0; TerrainKind::MountainTop as usize + 1];
+    /// Bands next to band `idx` of `count` bands, or `idx` itself at either end.
+    fn adjacent(idx: usize, count: usize) -> [usize; 2] {
+        [
+            idx.saturating_sub(1),
+            (idx + 1).min(count.saturating_sub(1)),
+        ]
+    }
 
-        terrain_centers[TerrainKind::DeepWater as usize] = terrain_limits
-            [TerrainKind::DeepWater as usize]
-            .iter()
-            .sum::<f64>()
-            / 2.0;
-        terrain_centers[TerrainKind::Water as usize] = terrain_limits[TerrainKind::Water as usize]
+    fn calc_centers(terrain_limits: &[[f64; 2]]) -> Vec<f64> {
+        terrain_limits
             .iter()
-            .sum::<f64>()
-            / 2.0;
-        terrain_centers[TerrainKind::ShallowWater as usize] = terrain_limits
-            [TerrainKind::ShallowWater as usize]
-            .iter()
-            .sum::<f64>()
-            / 2.0;
-        terrain_centers[TerrainKind::Shore as usize] = terrain_limits[TerrainKind::Shore as usize]
-            .iter()
-            .sum::<f64>()
-            / 2.0;
-        terrain_centers[TerrainKind::FlatLand as usize] = terrain_limits
-            [TerrainKind::FlatLand as usize]
-            .iter()
-            .sum::<f64>()
-            / 2.0;
-        terrain_centers[TerrainKind::HighLand as usize] = terrain_limits
-            [TerrainKind::HighLand as usize]
-            .iter()
-            .sum::<f64>()
-            / 2.0;
-        terrain_centers[TerrainKind::Mountains as usize] = terrain_limits
-            [TerrainKind::Mountains as usize]
-            .iter()
-            .sum::<f64>()
-            / 2.0;
-        terrain_centers[TerrainKind::MountainTop as usize] = terrain_limits
-            [TerrainKind::MountainTop as usize]
-            .iter()
-            .sum::<f64>()
-            / 2.0;
+            .map(|limits| limits.iter().sum::<f64>() / 2.0)
+            .collect()
+    }
+}
+
+/// Colors of a [`Gradient`] precomputed at evenly spaced heights from 0.0 to 1.0.
+///
+/// Looking up a color only indexes the table instead of finding the band and interpolating, which
+/// is faster for large images. In exchange every height gets the color of the closest entry.
+#[derive(Clone)]
+pub struct ColorLut {
+    colors: Vec<image::Rgb<u8>>,
+}
+
+impl ColorLut {
+    /// Table of `size` colors of `gradient`, the first at 0.0 and the last at 1.0. A `size` below 2
+    /// is raised to 2.
+    pub fn new(gradient: &Gradient, size: usize) -> Self {
+        let last = size.max(2) - 1;
+        let colors = (0..=last)
+            .map(|idx| gradient.lerp_color(idx as f64 / last as f64))
+            .collect();
+
+        Self { colors }
+    }
 
-        terrain_centers
+    /// Color of the entry closest to `height`. Heights outside of 0.0 to 1.0 get the color of the
+    /// first or last entry.
+    pub fn color(&self, height: f64) -> image::Rgb<u8> {
+        let last = self.colors.len() - 1;
+        let idx = (height * last as f64).round().clamp(0.0, last as f64) as usize;
+        self.colors[idx]
     }
 }
 
 impl Default for Gradient {
     fn default() -> Self {
-        let mut terrain_limits = vec![[0.0, 0.0]; TerrainKind::MountainTop as usize + 1];
-        let mut colors = vec![image::Rgb::<u8>([0, 0, 0]); TerrainKind::MountainTop as usize + 1];
-
-        terrain_limits[TerrainKind::DeepWater as usize] = [0.0, 0.4];
-        terrain_limits[TerrainKind::Water as usize] = [0.4, 0.6];
-        terrain_limits[TerrainKind::ShallowWater as usize] = [0.6, 0.63];
-        terrain_limits[TerrainKind::Shore as usize] = [0.63, 0.64];
-        terrain_limits[TerrainKind::FlatLand as usize] = [0.64, 0.8];
-        terrain_limits[TerrainKind::HighLand as usize] = [0.8, 0.9];
-        terrain_limits[TerrainKind::Mountains as usize] = [0.9, 0.98];
-        terrain_limits[TerrainKind::MountainTop as usize] = [0.98, 1.0];
-
-        colors[TerrainKind::DeepWater as usize] = image::Rgb([0, 64, 106]);
-        colors[TerrainKind::Water as usize] = image::Rgb([0, 117, 119]);
-        colors[TerrainKind::ShallowWater as usize] = image::Rgb([180, 240, 251]);
-        colors[TerrainKind::Shore as usize] = image::Rgb([194, 178, 128]);
-        colors[TerrainKind::FlatLand as usize] = image::Rgb([72, 111, 56]);
-        colors[TerrainKind::HighLand as usize] = image::Rgb([111, 130, 70]);
-        colors[TerrainKind::Mountains as usize] = image::Rgb([79, 79, 79]);
-        colors[TerrainKind::MountainTop as usize] = image::Rgb([253, 254, 255]);
-
-        Gradient::new(terrain_limits, colors)
+        let mut gradient = Gradient::empty();
+
+        gradient.insert_band(0.4, image::Rgb([0, 64, 106]), TerrainKind::DeepWater.name());
+        gradient.insert_band(0.6, image::Rgb([0, 117, 119]), TerrainKind::Water.name());
+        gradient.insert_band(
+            0.63,
+            image::Rgb([180, 240, 251]),
+            TerrainKind::ShallowWater.name(),
+        );
+        gradient.insert_band(0.64, image::Rgb([194, 178, 128]), TerrainKind::Shore.name());
+        gradient.insert_band(0.8, image::Rgb([72, 111, 56]), TerrainKind::FlatLand.name());
+        gradient.insert_band(
+            0.9,
+            image::Rgb([111, 130, 70]),
+            TerrainKind::HighLand.name(),
+        );
+        gradient.insert_band(
+            0.98,
+            image::Rgb([79, 79, 79]),
+            TerrainKind::Mountains.name(),
+        );
+        gradient.insert_band(
+            1.0,
+            image::Rgb([253, 254, 255]),
+            TerrainKind::MountainTop.name(),
+        );
+        // keeps the blending of the terrain kinds, e.g. the shore blends into the water
+        gradient.blend_neighbors = (0..TerrainKind::COUNT)
+            .map(|idx| {
+                let kind = TerrainKind::from(idx);
+                [kind.before() as usize, kind.after() as usize]
+            })
+            .collect();
+
+        gradient
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_shore_blends_into_water() {
+        let gradient = Gradient::default();
+        let shore = TerrainKind::Shore as usize;
+
+        assert_eq!(
+            gradient.blend_neighbors[shore],
+            [TerrainKind::Water as usize, TerrainKind::FlatLand as usize]
+        );
+        // a 30th of the way from the shore center towards the water center
+        assert_eq!(gradient.lerp_color(0.6305), image::Rgb([188, 176, 128]));
+    }
+
+    #[test]
+    fn inserted_band_becomes_blend_neighbor() {
+        let mut gradient = Gradient::default();
+        gradient.insert_band(0.7, image::Rgb([90, 120, 60]), "lowland");
+
+        let (shore, lowland) = (TerrainKind::Shore as usize, TerrainKind::FlatLand as usize);
+        assert_eq!(gradient.band_name(lowland), Some("lowland"));
+        assert_eq!(
+            gradient.blend_neighbors[shore],
+            [TerrainKind::Water as usize, lowland]
+        );
+        assert_eq!(gradient.blend_neighbors[lowland], [shore, lowland + 1]);
+        assert_eq!(
+            gradient.blend_neighbors[lowland + 1],
+            [lowland, lowland + 2]
+        );
+        assert!(gradient.validate().is_ok());
+    }
+
+    #[test]
+    fn terrain_kind_names_parse_back() {
+        let kinds = (0..TerrainKind::COUNT)
+            .map(TerrainKind::from)
+            .chain([TerrainKind::Undefined]);
+
+        for kind in kinds {
+            let name = kind.name();
+            assert_eq!(name.parse(), Ok(kind));
+            assert_eq!(name.to_uppercase().parse(), Ok(kind));
+            assert_eq!(name.replace('-', "_").parse(), Ok(kind));
+            assert_eq!(format!("{kind:?}").parse(), Ok(kind));
+        }
+        assert_eq!("Flat-LAND".parse(), Ok(TerrainKind::FlatLand));
+        assert_eq!("Mountain Top".parse(), Ok(TerrainKind::MountainTop));
+        assert!("lava".parse::<TerrainKind>().is_err());
+    }
+
+    #[test]
+    fn terrain_kind_at_band_limits() {
+        let gradient = Gradient::default();
+        let kind = |height| gradient.get_terrain_kind(height).map(TerrainKind::from);
+
+        // bands include their lower limit, only the highest one its upper limit as well
+        assert_eq!(kind(0.0), Some(TerrainKind::DeepWater));
+        assert_eq!(kind(0.4), Some(TerrainKind::Water));
+        assert_eq!(kind(0.4 - 1e-12), Some(TerrainKind::DeepWater));
+        assert_eq!(kind(0.63), Some(TerrainKind::Shore));
+        assert_eq!(kind(0.64 - 1e-12), Some(TerrainKind::Shore));
+        assert_eq!(kind(0.64), Some(TerrainKind::FlatLand));
+        assert_eq!(kind(0.98), Some(TerrainKind::MountainTop));
+        assert_eq!(kind(1.0), Some(TerrainKind::MountainTop));
+
+        assert_eq!(kind(-1e-12), None);
+        assert_eq!(kind(1.0 + 1e-12), None);
+        assert_eq!(kind(f64::NAN), None);
+    }
+
+    #[test]
+    fn terrain_kind_of_custom_gradient() {
+        let gray = image::Rgb([128, 128, 128]);
+        let gradient = Gradient::new(vec![[0.2, 0.5], [0.5, 0.7]], vec![gray; 2]);
+
+        assert_eq!(gradient.get_terrain_kind(0.1), None);
+        assert_eq!(gradient.get_terrain_kind(0.2), Some(0));
+        assert_eq!(gradient.get_terrain_kind(0.5), Some(1));
+        assert_eq!(gradient.get_terrain_kind(0.7), Some(1));
+        assert_eq!(gradient.get_terrain_kind(0.8), None);
+        assert_eq!(gradient.terrain_kind(0.3), None);
+
+        let mut gradient = Gradient::empty();
+        assert_eq!(gradient.get_terrain_kind(0.5), None);
+        gradient.insert_band(1.0, gray, "land");
+        gradient.insert_band(0.3, gray, "sea");
+        assert_eq!(gradient.get_terrain_kind(0.0), Some(0));
+        assert_eq!(gradient.get_terrain_kind(0.3), Some(1));
+        assert_eq!(gradient.get_terrain_kind(1.0), Some(1));
+        assert_eq!(gradient.band_name(1), Some("land"));
     }
 }
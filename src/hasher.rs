@@ -0,0 +1,128 @@
+//! Hasher used to turn seeds and pixel positions into reproducible numbers.
+
+use std::{
+    hash::{Hash, Hasher},
+    str::FromStr,
+};
+
+/// Simple multiplicative hasher.
+///
+/// Unlike [`std::collections::hash_map::DefaultHasher`] its output is guaranteed to stay the same
+/// between Rust versions, so a seed always produces the same image.
+pub struct SeedHasher {
+    hash: u64,
+    p: u64,
+}
+
+impl SeedHasher {
+    pub fn new() -> Self {
+        Self {
+            hash: 99876516661,
+            p: 779126527,
+        }
+    }
+}
+
+impl Default for SeedHasher {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Hasher for SeedHasher {
+    fn finish(&self) -> u64 {
+        self.hash
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        for b in bytes {
+            self.hash = (self.hash ^ *b as u64).wrapping_mul(self.p);
+        }
+    }
+}
+
+/// Hashes `value` with a [`SeedHasher`] and mixes the bits of the result.
+///
+/// Values which only differ in a few input bits, like neighboring positions, are mapped to nearly
+/// linear patterns by the multiplicative hasher. Folding the high bits down removes them, so the
+/// result is suitable as a random number.
+pub fn mixed_hash<T: Hash>(value: &T) -> u64 {
+    let mut hasher = SeedHasher::new();
+    value.hash(&mut hasher);
+
+    let mut hash = hasher.finish();
+    hash ^= hash >> 29;
+    hash = hash.wrapping_mul(0xbf58476d1ce4e5b9);
+    hash ^ (hash >> 32)
+}
+
+/// Random number generator deriving the jitter of a field from its seed, i.e. the white noise of
+/// every pixel and the octave shifts, see
+/// [`GenParams::jitter_rng`](crate::generator::GenParams::jitter_rng).
+///
+/// Every backend is deterministic, a seed always gets the same jitter.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum JitterRng {
+    /// [`mixed_hash`] of the seed and key
+    #[default]
+    SeedHash,
+    /// SplitMix64 started at the seed mixed with the key, a common lightweight generator
+    SplitMix,
+}
+
+impl JitterRng {
+    /// Number in `0.0..1.0` for `seed` and `key`, the key telling apart the numbers of one seed.
+    ///
+    /// # Examples
+    /// ```rust
+    /// use map_generation::hasher::JitterRng;
+    ///
+    /// for rng in [JitterRng::SeedHash, JitterRng::SplitMix] {
+    ///     let value = rng.unit(12345, &("octave", 2));
+    ///     assert!((0.0..1.0).contains(&value));
+    ///     assert_eq!(value, rng.unit(12345, &("octave", 2)));
+    ///     assert_ne!(value, rng.unit(12345, &("octave", 3)));
+    /// }
+    /// ```
+    pub fn unit<T: Hash>(self, seed: u64, key: &T) -> f64 {
+        // the 53 high bits fill the mantissa exactly
+        (self.bits(seed, key) >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// Uniformly distributed random bits for `seed` and `key`, see [`JitterRng::unit`].
+    pub fn bits<T: Hash>(self, seed: u64, key: &T) -> u64 {
+        match self {
+            JitterRng::SeedHash => mixed_hash(&(seed, key)),
+            JitterRng::SplitMix => {
+                let mut hasher = SeedHasher::new();
+                key.hash(&mut hasher);
+                split_mix(seed ^ hasher.finish())
+            }
+        }
+    }
+}
+
+impl FromStr for JitterRng {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "seed-hash" => Ok(JitterRng::SeedHash),
+            "split-mix" => Ok(JitterRng::SplitMix),
+            _ => Err(format!(
+                "{s} is not a jitter RNG. Available are: seed-hash, split-mix"
+            )),
+        }
+    }
+}
+
+/// Next output of a SplitMix64 generator in state `state`.
+fn split_mix(state: u64) -> u64 {
+    let mut z = state.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
@@ -0,0 +1,125 @@
+/// Height field of a generated image together with its dimensions.
+///
+/// Heights are stored in row-major order. Passes which need access to neighboring heights should
+/// use [`HeightField::sample`], so edges are handled the same way everywhere.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeightField {
+    pub data: Vec<f64>,
+    pub width: u32,
+    pub height: u32,
+}
+
+impl HeightField {
+    /// Creates a height field of the given dimensions with all heights set to 0.
+    ///
+    /// # Panics
+    /// Panics if the field would have more than [`crate::generator::MAX_PIXELS`] pixels.
+    pub fn new(width: u32, height: u32) -> Self {
+        let pixel_count = crate::generator::pixel_count(width, height)
+            .expect("Height field dimensions are too large!");
+
+        Self {
+            data: vec![0.0; pixel_count],
+            width,
+            height,
+        }
+    }
+
+    /// Wraps existing heights. Returns `None` if `data` does not match the dimensions.
+    pub fn from_vec(data: Vec<f64>, width: u32, height: u32) -> Option<Self> {
+        if crate::generator::pixel_count(width, height)? != data.len() {
+            return None;
+        }
+
+        Some(Self {
+            data,
+            width,
+            height,
+        })
+    }
+
+    /// Height at the given position, which must be inside the field.
+    pub fn get(&self, x: u32, y: u32) -> f64 {
+        self.data[y as usize * self.width as usize + x as usize]
+    }
+
+    /// Height at the given position, which may be outside the field.
+    ///
+    /// Positions outside are wrapped around to the opposite edge if `wrap` is set, which is what
+    /// seamless maps need. Otherwise they are clamped to the nearest edge.
+    pub fn sample(&self, x: i64, y: i64, wrap: bool) -> f64 {
+        let (width, height) = (self.width as i64, self.height as i64);
+
+        let (x, y) = if wrap {
+            (x.rem_euclid(width), y.rem_euclid(height))
+        } else {
+            (x.clamp(0, width - 1), y.clamp(0, height - 1))
+        };
+
+        self.get(x as u32, y as u32)
+    }
+
+    /// Change of the height per pixel along the x and y axis at the given position, the central
+    /// difference of its neighbors. Edges are handled like in [`HeightField::sample`].
+    pub fn slope(&self, x: i64, y: i64, wrap: bool) -> (f64, f64) {
+        let slope_x = (self.sample(x + 1, y, wrap) - self.sample(x - 1, y, wrap)) / 2.0;
+        let slope_y = (self.sample(x, y + 1, wrap) - self.sample(x, y - 1, wrap)) / 2.0;
+        (slope_x, slope_y)
+    }
+
+    /// Averages blocks of `factor * factor` heights into one, e.g. to get the field of an
+    /// antialiased image from its supersampled field. Incomplete blocks at the edges are dropped.
+    pub fn downsample(&self, factor: u32) -> HeightField {
+        let factor = factor.max(1);
+        let mut result =
+            HeightField::new((self.width / factor).max(1), (self.height / factor).max(1));
+
+        for y in 0..result.height {
+            for x in 0..result.width {
+                let mut sum = 0.0;
+                let mut samples = 0;
+                for sample_y in y * factor..((y + 1) * factor).min(self.height) {
+                    for sample_x in x * factor..((x + 1) * factor).min(self.width) {
+                        sum += self.get(sample_x, sample_y);
+                        samples += 1;
+                    }
+                }
+                result.data[y as usize * result.width as usize + x as usize] = sum / samples as f64;
+            }
+        }
+
+        result
+    }
+
+    /// Local roughness, the standard deviation of the heights within `radius` pixels of each
+    /// position.
+    ///
+    /// The window is a square of `2 * radius + 1` pixels per side, edges are handled like in
+    /// [`HeightField::sample`]. Flat areas have a roughness close to 0. Larger radii result in
+    /// smoother maps, but the cost grows with the area of the window.
+    pub fn roughness(&self, radius: u32, wrap: bool) -> HeightField {
+        let radius = radius as i64;
+        let window_size = ((2 * radius + 1) * (2 * radius + 1)) as f64;
+        let mut result = HeightField::new(self.width, self.height);
+
+        for y in 0..self.height as i64 {
+            for x in 0..self.width as i64 {
+                let mut sum = 0.0;
+                let mut sum_squared = 0.0;
+                for sample_y in y - radius..=y + radius {
+                    for sample_x in x - radius..=x + radius {
+                        let height = self.sample(sample_x, sample_y, wrap);
+                        sum += height;
+                        sum_squared += height * height;
+                    }
+                }
+
+                let mean = sum / window_size;
+                let variance = (sum_squared / window_size - mean * mean).max(0.0);
+                result.data[(y * self.width as i64 + x) as usize] = variance.sqrt();
+            }
+        }
+
+        result
+    }
+}
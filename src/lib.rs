@@ -1,2 +1,11 @@
+pub mod contour;
+pub mod export;
 pub mod generator;
 pub mod gradient;
+pub mod hasher;
+pub mod heightfield;
+pub mod overlay;
+pub mod palette;
+pub mod postprocess;
+pub mod ramp;
+pub mod stats;
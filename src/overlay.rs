@@ -0,0 +1,314 @@
+//! Passes which modify the colors of a generated image.
+
+use crate::{
+    gradient::{Gradient, TerrainKind},
+    heightfield::HeightField,
+};
+
+/// Sets every pixel whose entry in `mask` is `true` to `color`.
+///
+/// `image` holds RGB values and must contain one pixel per entry of `mask`.
+pub fn fill(image: &mut [u8], mask: &[bool], color: image::Rgb<u8>) {
+    for (pixel, masked) in image.chunks_exact_mut(3).zip(mask) {
+        if *masked {
+            pixel.copy_from_slice(&color.0);
+        }
+    }
+}
+
+/// Blends every pixel towards `color` by `strength * (1 - height)`, so low terrain is hazier than
+/// high terrain.
+///
+/// `image` holds RGB values and must contain one pixel per entry of `heights`. A `strength` of 0
+/// leaves the image unchanged, 1 turns the lowest heights fully into `color`.
+pub fn depth_tint(image: &mut [u8], heights: &[f64], color: image::Rgb<u8>, strength: f64) {
+    if strength == 0.0 {
+        return;
+    }
+
+    for (pixel, height) in image.chunks_exact_mut(3).zip(heights) {
+        let factor = (strength * (1.0 - height)).clamp(0.0, 1.0);
+        for (channel, tint) in pixel.iter_mut().zip(color.0) {
+            *channel = (*channel as f64 + (tint as f64 - *channel as f64) * factor).round() as u8;
+        }
+    }
+}
+
+/// Height range above the snow line over which snow fades in, see [`snow_line`].
+pub const SNOW_FADE: f64 = 0.02;
+
+/// Covers terrain above a snow line depending on latitude with `color`.
+///
+/// The snow line lies at `equator_level` in the vertical center of the image and drops linearly
+/// by `polar_drop` towards the top and bottom edges, so peaks need to be higher to carry snow the
+/// closer they are to the equator. Snow fades in over [`SNOW_FADE`] above the line instead of
+/// starting abruptly. `image` holds RGB values and must contain one pixel per entry of `heights`,
+/// which is `width` pixels wide.
+pub fn snow_line(
+    image: &mut [u8],
+    heights: &[f64],
+    width: usize,
+    equator_level: f64,
+    polar_drop: f64,
+    color: image::Rgb<u8>,
+) {
+    let rows = heights.len() / width.max(1);
+    let half = (rows.saturating_sub(1) as f64 / 2.0).max(1.0);
+
+    for (idx, (pixel, height)) in image.chunks_exact_mut(3).zip(heights).enumerate() {
+        // 0.0 at the equator, 1.0 at the poles
+        let latitude = ((idx / width.max(1)) as f64 - half).abs() / half;
+        let line = equator_level - polar_drop * latitude.min(1.0);
+
+        let factor = ((height - line) / SNOW_FADE).clamp(0.0, 1.0);
+        if factor == 0.0 {
+            continue;
+        }
+
+        for (channel, snow) in pixel.iter_mut().zip(color.0) {
+            *channel = (*channel as f64 + (snow as f64 - *channel as f64) * factor).round() as u8;
+        }
+    }
+}
+
+/// Colors shores and flat land `dry` where `moisture` is below `threshold` and `wet` otherwise,
+/// e.g. to tell deserts from vegetated land.
+///
+/// Cells are classified by the bands of `gradient`. `image` holds RGB values and must contain one
+/// pixel per entry of `heights` and `moisture`, which is a second height field whose values are
+/// read as moisture.
+pub fn moisture(
+    image: &mut [u8],
+    heights: &[f64],
+    moisture: &[f64],
+    gradient: &Gradient,
+    threshold: f64,
+    dry: image::Rgb<u8>,
+    wet: image::Rgb<u8>,
+) {
+    for ((pixel, height), moisture) in image.chunks_exact_mut(3).zip(heights).zip(moisture) {
+        if matches!(
+            gradient.terrain_kind(*height),
+            Some(TerrainKind::Shore | TerrainKind::FlatLand)
+        ) {
+            let color = if *moisture < threshold { dry } else { wet };
+            pixel.copy_from_slice(&color.0);
+        }
+    }
+}
+
+/// Direction towards the light of [`flat_shading`], from the top left at an elevation of 45°.
+const LIGHT: [f64; 3] = [-0.5, -0.5, std::f64::consts::FRAC_1_SQRT_2];
+/// Vertical exaggeration of the terrain relative to the longer side of the height field.
+const RELIEF: f64 = 0.05;
+/// Largest change in brightness caused by [`flat_shading`].
+pub const SHADING_STRENGTH: f64 = 0.3;
+
+/// Shades slopes by how much they face the light, quantized into `levels` brightness steps for a
+/// cel-shaded look.
+///
+/// Slopes facing the top left are brightened and slopes facing away darkened by at most
+/// [`SHADING_STRENGTH`]. A single level leaves the image unchanged. Edges wrap around if `wrap` is
+/// set, as needed for seamless maps. `image` holds RGB values and must contain one pixel per
+/// height of `heights`.
+pub fn flat_shading(image: &mut [u8], heights: &HeightField, levels: u32, wrap: bool) {
+    if levels <= 1 {
+        return;
+    }
+
+    let relief = heights.width.max(heights.height) as f64 * RELIEF;
+    let width = heights.width.max(1) as usize;
+    for (idx, pixel) in image.chunks_exact_mut(3).enumerate() {
+        let (x, y) = ((idx % width) as i64, (idx / width) as i64);
+        let (slope_x, slope_y) = heights.slope(x, y, wrap);
+
+        let normal = [-slope_x * relief, -slope_y * relief, 1.0];
+        let length = normal.iter().map(|axis| axis * axis).sum::<f64>().sqrt();
+        let light = normal
+            .iter()
+            .zip(LIGHT)
+            .map(|(axis, light)| axis * light)
+            .sum::<f64>()
+            / length;
+
+        // 0.0 for slopes facing away from the light, 0.5 for flat ground, 1.0 facing the light
+        let illumination = ((light - LIGHT[2]) / (1.0 - LIGHT[2]) * 0.5 + 0.5).clamp(0.0, 1.0);
+        let step = ((illumination * levels as f64) as u32).min(levels - 1);
+        let stepped = step as f64 / (levels - 1) as f64;
+        let factor = 1.0 + (stepped * 2.0 - 1.0) * SHADING_STRENGTH;
+
+        for channel in pixel.iter_mut() {
+            *channel = (*channel as f64 * factor).round().min(255.0) as u8;
+        }
+    }
+}
+
+/// Share of its region color in each cell, see [`regions`].
+pub const REGION_TINT: f64 = 0.3;
+
+/// Tints every region of `regions` in a distinct color and draws `border` on cells next to another
+/// region, see [`crate::postprocess::regions`].
+///
+/// `image` holds RGB values and must contain one pixel per entry of `regions`, which is `width`
+/// pixels wide. Cells without a region are left unchanged.
+pub fn regions(image: &mut [u8], regions: &[Option<usize>], width: usize, border: image::Rgb<u8>) {
+    let width = width.max(1);
+
+    for (idx, (pixel, region)) in image.chunks_exact_mut(3).zip(regions).enumerate() {
+        let Some(region) = region else {
+            continue;
+        };
+
+        let (x, y) = (idx % width, idx / width);
+        let neighbors = [
+            (x > 0).then(|| idx - 1),
+            (x + 1 < width).then_some(idx + 1),
+            (y > 0).then(|| idx - width),
+            Some(idx + width),
+        ];
+        let on_border = neighbors
+            .into_iter()
+            .flatten()
+            .filter_map(|neighbor| regions.get(neighbor).copied().flatten())
+            .any(|neighbor| neighbor != *region);
+
+        if on_border {
+            pixel.copy_from_slice(&border.0);
+            continue;
+        }
+
+        for (channel, tint) in pixel.iter_mut().zip(region_color(*region)) {
+            *channel =
+                (*channel as f64 + (tint as f64 - *channel as f64) * REGION_TINT).round() as u8;
+        }
+    }
+}
+
+/// Fully saturated color of the region at `idx`. Hues are spread by the golden ratio, so
+/// consecutive regions never look alike.
+fn region_color(idx: usize) -> [u8; 3] {
+    let hue = (idx as f64 * 0.618_033_988_749_895).fract() * 6.0;
+    let rising = (hue.fract() * 255.0).round() as u8;
+    let falling = 255 - rising;
+
+    match hue as u8 {
+        0 => [255, rising, 0],
+        1 => [falling, 255, 0],
+        2 => [0, 255, rising],
+        3 => [0, falling, 255],
+        4 => [rising, 0, 255],
+        _ => [255, 0, falling],
+    }
+}
+
+/// Colors cycled through by [`tint_chunks`].
+const CHUNK_TINTS: [[u8; 3]; 6] = [
+    [255, 0, 0],
+    [0, 255, 0],
+    [0, 0, 255],
+    [255, 255, 0],
+    [0, 255, 255],
+    [255, 0, 255],
+];
+
+/// Tints consecutive runs of `chunk_len` pixels in alternating colors, making the areas of an
+/// image handled by different threads visible. See [`crate::generator::thread_area_size`].
+pub fn tint_chunks(image: &mut [u8], chunk_len: usize) {
+    const STRENGTH: f64 = 0.25;
+
+    for (idx, chunk) in image.chunks_mut(chunk_len.max(1) * 3).enumerate() {
+        let tint = CHUNK_TINTS[idx % CHUNK_TINTS.len()];
+        for pixel in chunk.chunks_exact_mut(3) {
+            for (channel, tint) in pixel.iter_mut().zip(tint) {
+                *channel = (*channel as f64 + (tint as f64 - *channel as f64) * STRENGTH) as u8;
+            }
+        }
+    }
+}
+
+/// Digits 0 to 9, each five rows of three pixels with the leftmost pixel in the highest bit.
+const DIGITS: [[u8; 5]; 10] = [
+    [0b111, 0b101, 0b101, 0b101, 0b111],
+    [0b010, 0b110, 0b010, 0b010, 0b111],
+    [0b111, 0b001, 0b111, 0b100, 0b111],
+    [0b111, 0b001, 0b111, 0b001, 0b111],
+    [0b101, 0b101, 0b111, 0b001, 0b001],
+    [0b111, 0b100, 0b111, 0b001, 0b111],
+    [0b111, 0b100, 0b111, 0b101, 0b111],
+    [0b111, 0b001, 0b001, 0b001, 0b001],
+    [0b111, 0b101, 0b111, 0b101, 0b111],
+    [0b111, 0b101, 0b111, 0b001, 0b111],
+];
+
+/// Size in pixels of each glyph pixel of the labels drawn by [`label`].
+pub const LABEL_SCALE: usize = 2;
+
+/// Draws `number` in white on a black box with its top left corner at `left`, `top`.
+///
+/// `image` holds RGB values and is `width` pixels wide. Parts of the label outside of the image
+/// are cut off.
+pub fn label(image: &mut [u8], width: usize, left: usize, top: usize, number: u32) {
+    let width = width.max(1);
+    let height = image.len() / 3 / width;
+    let digits: Vec<usize> = number
+        .to_string()
+        .bytes()
+        .map(|digit| (digit - b'0') as usize)
+        .collect();
+    // one glyph pixel of padding around and between the digits
+    let label_width = (digits.len() * 4 + 1) * LABEL_SCALE;
+    let label_height = 7 * LABEL_SCALE;
+
+    for y in 0..label_height.min(height.saturating_sub(top)) {
+        for x in 0..label_width.min(width.saturating_sub(left)) {
+            let (glyph_x, glyph_y) = (x / LABEL_SCALE, y / LABEL_SCALE);
+            let lit = glyph_x % 4 != 0
+                && (1..=5).contains(&glyph_y)
+                && DIGITS[digits[glyph_x / 4]][glyph_y - 1] & (0b100 >> (glyph_x % 4 - 1)) != 0;
+            let idx = ((top + y) * width + left + x) * 3;
+            image[idx..idx + 3].fill(if lit { 255 } else { 0 });
+        }
+    }
+}
+
+/// Draws a coordinate grid with lines every `spacing` pixels, blended into the image by `opacity`.
+///
+/// With `labels` set, each line is labeled with its pixel coordinate: vertical lines at the top
+/// edge and horizontal lines at the left edge. `image` holds RGB values and is `width` pixels
+/// wide.
+pub fn grid(
+    image: &mut [u8],
+    width: usize,
+    spacing: usize,
+    color: image::Rgb<u8>,
+    opacity: f64,
+    labels: bool,
+) {
+    let width = width.max(1);
+    let spacing = spacing.max(1);
+    let height = image.len() / 3 / width;
+
+    for (idx, pixel) in image.chunks_exact_mut(3).enumerate() {
+        let (x, y) = (idx % width, idx / width);
+        if !x.is_multiple_of(spacing) && !y.is_multiple_of(spacing) {
+            continue;
+        }
+
+        for (channel, line) in pixel.iter_mut().zip(color.0) {
+            *channel = (*channel as f64 + (line as f64 - *channel as f64) * opacity).round() as u8;
+        }
+    }
+
+    if !labels {
+        return;
+    }
+
+    // labels sit right of vertical lines and below horizontal lines, the corner is left to the
+    // label of the first vertical line
+    for x in (0..width).step_by(spacing) {
+        label(image, width, x + 1, 0, x as u32);
+    }
+    for y in (spacing..height).step_by(spacing) {
+        label(image, width, 0, y + 1, y as u32);
+    }
+}
@@ -0,0 +1,284 @@
+//! Built-in band colors and extraction of dominant colors from images.
+
+use std::{collections::HashMap, str::FromStr};
+
+use crate::gradient::{Gradient, TerrainKind};
+
+/// Named sets of colors for the default terrain bands.
+///
+/// The variants for color vision deficiencies keep the bands of the default gradient, but replace
+/// hues which are hard to tell apart with ones that differ along the blue-yellow axis and in
+/// lightness.
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum Palette {
+    /// colors of [`Gradient::default`]
+    #[default]
+    Default,
+    /// for missing or weak green cones. Shore and land are told apart by a yellow to brown
+    /// lightness ramp instead of tan and green
+    Deuteranopia,
+    /// for missing or weak red cones, which also makes reds look darker. Avoids reds entirely and
+    /// steps land from yellow over olive to gray
+    Protanopia,
+    /// blue-heavy colors with teal lowlands, for maps that are mostly ocean
+    WaterWorld,
+    /// sandy shores and lowlands turning into red-brown rock
+    Desert,
+    /// gray-green tundra and icy highlands under cold, dark water
+    Arctic,
+}
+
+/// Band colors of [`Palette::Deuteranopia`], from deep water to mountain tops.
+const DEUTERANOPIA_COLORS: [[u8; 3]; TerrainKind::COUNT] = [
+    [0, 45, 110],
+    [0, 90, 181],
+    [153, 204, 255],
+    [240, 228, 66],
+    [204, 153, 51],
+    [150, 100, 40],
+    [90, 70, 60],
+    [250, 250, 250],
+];
+
+/// Band colors of [`Palette::Protanopia`], from deep water to mountain tops.
+const PROTANOPIA_COLORS: [[u8; 3]; TerrainKind::COUNT] = [
+    [0, 40, 100],
+    [0, 100, 170],
+    [140, 200, 240],
+    [240, 228, 66],
+    [190, 170, 60],
+    [130, 120, 90],
+    [70, 70, 80],
+    [250, 250, 250],
+];
+
+/// Band colors of [`Palette::WaterWorld`], from deep water to mountain tops.
+const WATER_WORLD_COLORS: [[u8; 3]; TerrainKind::COUNT] = [
+    [8, 24, 68],
+    [16, 58, 120],
+    [64, 140, 190],
+    [200, 210, 170],
+    [60, 130, 120],
+    [80, 120, 110],
+    [90, 100, 110],
+    [230, 240, 250],
+];
+
+/// Band colors of [`Palette::Desert`], from deep water to mountain tops.
+const DESERT_COLORS: [[u8; 3]; TerrainKind::COUNT] = [
+    [20, 60, 90],
+    [30, 100, 120],
+    [120, 190, 180],
+    [238, 214, 160],
+    [222, 184, 120],
+    [196, 150, 96],
+    [150, 110, 80],
+    [240, 225, 200],
+];
+
+/// Band colors of [`Palette::Arctic`], from deep water to mountain tops.
+const ARCTIC_COLORS: [[u8; 3]; TerrainKind::COUNT] = [
+    [20, 40, 70],
+    [40, 80, 110],
+    [150, 190, 210],
+    [180, 190, 190],
+    [120, 135, 130],
+    [150, 160, 165],
+    [190, 200, 210],
+    [250, 252, 255],
+];
+
+impl Palette {
+    /// Default bands colored with the colors of this palette.
+    pub fn gradient(&self) -> Gradient {
+        let colors = match self {
+            Palette::Default => return Gradient::default(),
+            Palette::Deuteranopia => DEUTERANOPIA_COLORS,
+            Palette::Protanopia => PROTANOPIA_COLORS,
+            Palette::WaterWorld => WATER_WORLD_COLORS,
+            Palette::Desert => DESERT_COLORS,
+            Palette::Arctic => ARCTIC_COLORS,
+        };
+
+        Gradient::from_colors(colors.into_iter().map(image::Rgb).collect())
+    }
+}
+
+impl FromStr for Palette {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "default" => Ok(Palette::Default),
+            "deuteranopia" => Ok(Palette::Deuteranopia),
+            "protanopia" => Ok(Palette::Protanopia),
+            "water-world" => Ok(Palette::WaterWorld),
+            "desert" => Ok(Palette::Desert),
+            "arctic" => Ok(Palette::Arctic),
+            _ => Err(format!(
+                "{s} is not a palette. Available are: default, deuteranopia, protanopia, \
+                 water-world, desert, arctic"
+            )),
+        }
+    }
+}
+
+/// Extracts `count` dominant colors of `image` using median cut, ordered from dark to bright.
+///
+/// The pixels are recursively split at the median of the color channel with the widest range,
+/// each resulting group contributes its average color. If the image has too few distinct colors to
+/// be split `count` times, the brightest color is repeated to fill the palette. Returns an empty
+/// palette for an empty image.
+pub fn extract_palette(image: &image::RgbImage, count: usize) -> Vec<image::Rgb<u8>> {
+    median_cut(image.pixels().map(|pixel| pixel.0).collect(), count)
+}
+
+/// Reduces the RGB buffer `image` to at most `count` distinct colors.
+///
+/// The palette is extracted with median cut like in [`extract_palette`] and every pixel is
+/// replaced by the closest palette color. A `count` of 0 leaves the image unchanged.
+pub fn quantize(image: &mut [u8], count: usize) {
+    let pixels: Vec<[u8; 3]> = image
+        .chunks_exact(3)
+        .map(|pixel| [pixel[0], pixel[1], pixel[2]])
+        .collect();
+    let palette = median_cut(pixels, count);
+    if palette.is_empty() {
+        return;
+    }
+
+    // images usually contain far fewer distinct colors than pixels
+    let mut closest: HashMap<[u8; 3], [u8; 3]> = HashMap::new();
+    for pixel in image.chunks_exact_mut(3) {
+        let color = [pixel[0], pixel[1], pixel[2]];
+        let replacement = closest.entry(color).or_insert_with(|| {
+            palette
+                .iter()
+                .min_by_key(|candidate| distance(&color, &candidate.0))
+                .unwrap()
+                .0
+        });
+        pixel.copy_from_slice(replacement);
+    }
+}
+
+fn median_cut(pixels: Vec<[u8; 3]>, count: usize) -> Vec<image::Rgb<u8>> {
+    if pixels.is_empty() || count == 0 {
+        return Vec::new();
+    }
+
+    let mut boxes = vec![pixels];
+    while boxes.len() < count {
+        // split the box with the widest channel range, boxes of a single color cannot be split
+        let Some((idx, channel, _)) = boxes
+            .iter()
+            .enumerate()
+            .map(|(idx, pixels)| {
+                let (channel, range) = widest_channel(pixels);
+                (idx, channel, range)
+            })
+            .filter(|(_, _, range)| *range > 0)
+            .max_by_key(|(_, _, range)| *range)
+        else {
+            break;
+        };
+
+        let mut pixels = boxes.swap_remove(idx);
+        pixels.sort_unstable_by_key(|pixel| pixel[channel]);
+        let upper = pixels.split_off(pixels.len() / 2);
+        boxes.push(pixels);
+        boxes.push(upper);
+    }
+
+    let mut palette: Vec<image::Rgb<u8>> = boxes.iter().map(|pixels| average(pixels)).collect();
+    palette.sort_by(|a, b| luminance(a).total_cmp(&luminance(b)));
+
+    let brightest = palette[palette.len() - 1];
+    palette.resize(count, brightest);
+    palette
+}
+
+/// Relative luminance of `color` as defined by ITU-R BT.709, in the range of 0.0 to 255.0.
+pub fn luminance(color: &image::Rgb<u8>) -> f64 {
+    let [r, g, b] = color.0;
+    0.2126 * r as f64 + 0.7152 * g as f64 + 0.0722 * b as f64
+}
+
+/// Channel with the largest difference between its minimum and maximum, and that difference.
+fn widest_channel(pixels: &[[u8; 3]]) -> (usize, u8) {
+    (0..3)
+        .map(|channel| {
+            let (min, max) = pixels.iter().fold((u8::MAX, u8::MIN), |(min, max), pixel| {
+                (min.min(pixel[channel]), max.max(pixel[channel]))
+            });
+            (channel, max - min)
+        })
+        .max_by_key(|(_, range)| *range)
+        .unwrap()
+}
+
+/// Squared euclidean distance between two colors.
+fn distance(a: &[u8; 3], b: &[u8; 3]) -> u32 {
+    a.iter()
+        .zip(b)
+        .map(|(a, b)| (*a as i32 - *b as i32).pow(2) as u32)
+        .sum()
+}
+
+fn average(pixels: &[[u8; 3]]) -> image::Rgb<u8> {
+    let mut sum = [0u64; 3];
+    for pixel in pixels {
+        for (sum, value) in sum.iter_mut().zip(pixel) {
+            *sum += *value as u64;
+        }
+    }
+
+    image::Rgb(sum.map(|sum| (sum / pixels.len() as u64) as u8))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use super::*;
+    use crate::generator::{self, GenParams};
+
+    fn distinct_colors(image: &[u8]) -> usize {
+        image.chunks_exact(3).collect::<HashSet<_>>().len()
+    }
+
+    #[test]
+    fn quantize_limits_distinct_colors() {
+        let mut image = vec![0; generator::buffer_len(120, 80).unwrap()];
+        generator::generate(&mut image, &GenParams::new(12345, 120, 80, 0.2, 0.1), None);
+        assert!(distinct_colors(&image) > 16);
+
+        for count in [1, 2, 5, 16] {
+            let mut quantized = image.clone();
+            quantize(&mut quantized, count);
+            let distinct = distinct_colors(&quantized);
+            assert!(
+                distinct >= 1 && distinct <= count,
+                "{distinct} colors for {count}"
+            );
+        }
+
+        let mut unchanged = image.clone();
+        quantize(&mut unchanged, 0);
+        assert_eq!(unchanged, image);
+    }
+
+    #[test]
+    fn quantize_keeps_few_colors() {
+        let mut image: Vec<u8> = [[10, 20, 30], [200, 100, 0], [10, 20, 30], [0, 0, 255]]
+            .concat()
+            .repeat(5);
+        let original = image.clone();
+
+        quantize(&mut image, 8);
+        assert_eq!(image, original);
+    }
+}
@@ -0,0 +1,514 @@
+//! Passes which modify a generated height field before it is colored.
+
+use std::{collections::VecDeque, str::FromStr};
+
+use crate::{
+    generator::{MAX_HEIGHT, MIN_HEIGHT},
+    gradient::{Gradient, TerrainKind},
+    hasher::mixed_hash,
+    heightfield::HeightField,
+};
+
+/// Level water is flattened to if not configured otherwise, the center of the default `Water` band.
+pub const DEFAULT_SEA_LEVEL: f64 = 0.5;
+
+/// Sets every height classified below [`TerrainKind::Shore`] to `sea_level`.
+///
+/// Land is left untouched. `sea_level` should itself be classified as water by `gradient`,
+/// otherwise the flattened sea turns into land.
+pub fn flatten_water(heights: &mut [f64], gradient: &Gradient, sea_level: f64) {
+    for height in heights.iter_mut() {
+        if is_water(gradient, *height) {
+            *height = sea_level;
+        }
+    }
+}
+
+/// Monotonic remap of heights through points, see [`remap_heights`].
+///
+/// The curve runs from `(0, 0)` through the given points to `(1, 1)` and is linear in between.
+/// Both coordinates of the points must ascend, so higher input always stays higher and no bands
+/// swap places.
+///
+/// # Examples
+/// Giving the lower 40 % of the input 70 % of the output stretches the ocean and compresses land.
+/// ```rust
+/// use map_generation::postprocess::DepthCurve;
+///
+/// let curve = DepthCurve::new(&[(0.4, 0.7)]).unwrap();
+/// assert!((curve.apply(0.2) - 0.35).abs() < 1e-9);
+/// assert!((curve.apply(0.7) - 0.85).abs() < 1e-9);
+///
+/// let heights: Vec<f64> = (0..=100).map(|step| curve.apply(step as f64 / 100.0)).collect();
+/// assert!(heights.windows(2).all(|pair| pair[0] < pair[1]));
+///
+/// assert!(DepthCurve::new(&[(0.4, 0.7), (0.6, 0.5)]).is_err());
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct DepthCurve {
+    /// input and output height of each point, including the ends
+    points: Vec<(f64, f64)>,
+}
+
+impl DepthCurve {
+    /// Curve through `points` of input and output height.
+    ///
+    /// # Errors
+    /// Fails if a coordinate is not strictly between 0 and 1 or the inputs or outputs do not
+    /// ascend.
+    pub fn new(points: &[(f64, f64)]) -> Result<Self, String> {
+        if let Some((input, output)) = points.iter().find(|(input, output)| {
+            !(*input > 0.0 && *input < 1.0 && *output > 0.0 && *output < 1.0)
+        }) {
+            return Err(format!(
+                "Depth curve point {input}:{output} must be between 0 and 1!"
+            ));
+        }
+
+        let points: Vec<(f64, f64)> = std::iter::once((0.0, 0.0))
+            .chain(points.iter().copied())
+            .chain(std::iter::once((1.0, 1.0)))
+            .collect();
+        if points
+            .windows(2)
+            .any(|pair| pair[0].0 >= pair[1].0 || pair[0].1 >= pair[1].1)
+        {
+            return Err("Depth curve points must be in ascending order!".to_string());
+        }
+
+        Ok(Self { points })
+    }
+
+    /// Remapped `height`, interpolated linearly between the neighboring points. Heights outside of
+    /// 0 and 1 are returned unchanged.
+    pub fn apply(&self, height: f64) -> f64 {
+        let upper = self.points.partition_point(|(input, _)| *input < height);
+        match (upper.checked_sub(1), self.points.get(upper)) {
+            (Some(lower), Some((end, end_output))) => {
+                let (start, start_output) = self.points[lower];
+                start_output + (end_output - start_output) * (height - start) / (end - start)
+            }
+            _ => height,
+        }
+    }
+}
+
+/// Remaps every height through `curve`, e.g. to give the ocean more of the range for bathymetric
+/// detail. Applied before classification, so the bands cover other parts of the generated
+/// heights afterwards.
+pub fn remap_heights(heights: &mut [f64], curve: &DepthCurve) {
+    for height in heights.iter_mut() {
+        *height = curve.apply(*height);
+    }
+}
+
+/// Outline of the landmass shaped by [`island_mask`].
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, Hash, Default, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "kebab-case")]
+pub enum IslandShape {
+    /// stretched to the image, so non-square images get an elliptic island
+    #[default]
+    Ellipse,
+    /// round regardless of the aspect ratio, leaving wider margins along the longer side
+    Circle,
+}
+
+impl FromStr for IslandShape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "ellipse" => Ok(IslandShape::Ellipse),
+            "circle" => Ok(IslandShape::Circle),
+            _ => Err(format!(
+                "{s} is not an island shape. Available are: ellipse, circle"
+            )),
+        }
+    }
+}
+
+/// Share of the radius of [`island_mask`] around the center in which the terrain is unchanged.
+pub const ISLAND_PLATEAU: f64 = 0.5;
+
+/// Lowers the terrain towards the edges, so the land forms an island surrounded by water.
+///
+/// Distances are relative to the radius of `shape`: half of each side for [`IslandShape::Ellipse`]
+/// and half of the shorter side on both axes for [`IslandShape::Circle`]. Terrain within
+/// [`ISLAND_PLATEAU`] of the radius is kept, further out heights are smoothly pulled towards
+/// [`MIN_HEIGHT`], which is all that is left beyond the radius.
+///
+/// # Examples
+/// On a 2:1 image, a circular island is as wide as it is high and has equal margins left and
+/// right as well as top and bottom.
+/// ```rust
+/// use map_generation::gradient::{Gradient, TerrainKind};
+/// use map_generation::heightfield::HeightField;
+/// use map_generation::postprocess::{island_mask, IslandShape};
+///
+/// let mut heights = HeightField::new(200, 100);
+/// heights.data.fill(0.9);
+/// island_mask(&mut heights, IslandShape::Circle);
+///
+/// let gradient = Gradient::default();
+/// let is_land = |x: u32, y: u32| {
+///     gradient.get_terrain_kind(heights.get(x, y)).unwrap() >= TerrainKind::Shore as usize
+/// };
+/// let land_columns: Vec<u32> = (0..200).filter(|x| (0..100).any(|y| is_land(*x, y))).collect();
+/// let land_rows: Vec<u32> = (0..100).filter(|y| (0..200).any(|x| is_land(x, *y))).collect();
+///
+/// let (left, right) = (land_columns[0], 199 - land_columns[land_columns.len() - 1]);
+/// let (top, bottom) = (land_rows[0], 99 - land_rows[land_rows.len() - 1]);
+/// assert_eq!(left, right);
+/// assert_eq!(top, bottom);
+/// assert_eq!(left - top, 50);
+/// ```
+pub fn island_mask(heights: &mut HeightField, shape: IslandShape) {
+    let (half_width, half_height) = (heights.width as f64 / 2.0, heights.height as f64 / 2.0);
+    let (radius_x, radius_y) = match shape {
+        IslandShape::Ellipse => (half_width, half_height),
+        IslandShape::Circle => {
+            let radius = half_width.min(half_height);
+            (radius, radius)
+        }
+    };
+    let width = heights.width.max(1) as usize;
+
+    for (idx, height) in heights.data.iter_mut().enumerate() {
+        // distance of the cell center from the image center relative to the radius
+        let dx = ((idx % width) as f64 + 0.5 - half_width) / radius_x;
+        let dy = ((idx / width) as f64 + 0.5 - half_height) / radius_y;
+        let distance = (dx * dx + dy * dy).sqrt();
+        let factor = 1.0 - smoothstep((distance - ISLAND_PLATEAU) / (1.0 - ISLAND_PLATEAU));
+
+        *height = MIN_HEIGHT + (*height - MIN_HEIGHT) * factor;
+    }
+}
+
+/// Profile of a feature placed by [`apply_stamps`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum StampShape {
+    /// peak falling off evenly towards the rim, like a volcano
+    Cone,
+    /// rounded hill, flat at the top
+    Dome,
+    /// bowl sunk below the surroundings, enclosed by a raised rim
+    Crater,
+}
+
+impl FromStr for StampShape {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "cone" => Ok(StampShape::Cone),
+            "dome" => Ok(StampShape::Dome),
+            "crater" => Ok(StampShape::Crater),
+            _ => Err(format!(
+                "{s} is not a stamp shape. Available are: cone, dome, crater"
+            )),
+        }
+    }
+}
+
+/// Share of the radius of a [`Stamp`] at which the rim of a [`StampShape::Crater`] is highest.
+pub const CRATER_RIM: f64 = 0.6;
+
+/// Share of the radius of a [`Stamp`] along its edge over which it fades into the terrain.
+pub const STAMP_FALLOFF: f64 = 0.3;
+
+/// Feature added to a height field by [`apply_stamps`], e.g. a hand placed mountain.
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct Stamp {
+    pub shape: StampShape,
+    /// horizontal position of the center in pixels
+    pub x: f64,
+    /// vertical position of the center in pixels
+    pub y: f64,
+    /// distance from the center in pixels beyond which the terrain is unchanged, must be positive
+    pub radius: f64,
+    /// height added at the center, or at the rim of a crater. Negative strengths dig pits.
+    pub strength: f64,
+}
+
+impl Stamp {
+    /// Share of `strength` added at `distance` pixels from the center.
+    ///
+    /// The last [`STAMP_FALLOFF`] of the radius smoothly fade to 0, so the terrain stays
+    /// continuous and level at the edge of the stamp.
+    pub fn profile(&self, distance: f64) -> f64 {
+        let d = distance / self.radius;
+        if d >= 1.0 {
+            return 0.0;
+        }
+
+        let height = match self.shape {
+            StampShape::Cone => 1.0 - d,
+            StampShape::Dome => 1.0 - smoothstep(d),
+            // the bowl sinks half as deep below the surroundings as the rim rises above them
+            StampShape::Crater if d < CRATER_RIM => -0.5 + 1.5 * (d / CRATER_RIM).powi(2),
+            StampShape::Crater => 1.0,
+        };
+        height * (1.0 - smoothstep((d - (1.0 - STAMP_FALLOFF)) / STAMP_FALLOFF))
+    }
+}
+
+/// Adds each stamp's profile to the heights around its center, times its strength.
+///
+/// Overlapping stamps accumulate. Stamps are given in pixels of an image `scale` times smaller
+/// than `heights`, e.g. the supersampling factor, and scaled to match. Heights are clamped to the
+/// valid range afterwards.
+///
+/// # Examples
+/// ```rust
+/// use map_generation::heightfield::HeightField;
+/// use map_generation::postprocess::{apply_stamps, Stamp, StampShape};
+///
+/// let mut heights = HeightField::new(100, 100);
+/// heights.data.fill(0.3);
+/// let crater = Stamp { shape: StampShape::Crater, x: 50.0, y: 50.0, radius: 40.0, strength: 0.2 };
+/// apply_stamps(&mut heights, &[crater], 1.0);
+///
+/// assert!(heights.get(50, 50) < 0.3);
+/// assert!(heights.get(50 + 24, 50) > 0.45);
+/// assert_eq!(heights.get(95, 50), 0.3);
+/// ```
+pub fn apply_stamps(heights: &mut HeightField, stamps: &[Stamp], scale: f64) {
+    let (width, height) = (heights.width as i64, heights.height as i64);
+
+    for stamp in stamps {
+        let (center_x, center_y) = (stamp.x * scale, stamp.y * scale);
+        let radius = stamp.radius * scale;
+        let scaled = Stamp { radius, ..*stamp };
+
+        // only the cells whose center lies within the radius are touched
+        let columns = ((center_x - radius - 0.5).floor() as i64).max(0)
+            ..((center_x + radius + 0.5).ceil() as i64).min(width);
+        let rows = ((center_y - radius - 0.5).floor() as i64).max(0)
+            ..((center_y + radius + 0.5).ceil() as i64).min(height);
+        for y in rows {
+            for x in columns.clone() {
+                let dx = x as f64 + 0.5 - center_x;
+                let dy = y as f64 + 0.5 - center_y;
+                let distance = (dx * dx + dy * dy).sqrt();
+                heights.data[(y * width + x) as usize] += scaled.profile(distance) * stamp.strength;
+            }
+        }
+    }
+
+    for height in heights.data.iter_mut() {
+        *height = height.clamp(MIN_HEIGHT, MAX_HEIGHT);
+    }
+}
+
+/// Smooth step from 0 at `t <= 0` to 1 at `t >= 1`.
+fn smoothstep(t: f64) -> f64 {
+    let t = t.clamp(0.0, 1.0);
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// Marks water which is not connected to the image border, i.e. lakes.
+///
+/// Water is everything classified below [`TerrainKind::Shore`]. Water cells are connected if they
+/// share an edge. Returns one entry per height, `true` for cells of enclosed water basins.
+pub fn find_lakes(heights: &HeightField, gradient: &Gradient) -> Vec<bool> {
+    let (width, height) = (heights.width as usize, heights.height as usize);
+    let is_water: Vec<bool> = heights
+        .data
+        .iter()
+        .map(|height| is_water(gradient, *height))
+        .collect();
+
+    // flood fill the ocean starting at all water cells on the border
+    let mut is_ocean = vec![false; is_water.len()];
+    let mut queue = VecDeque::new();
+    for y in 0..height {
+        for x in 0..width {
+            let idx = y * width + x;
+            let on_border = x == 0 || y == 0 || x == width - 1 || y == height - 1;
+            if on_border && is_water[idx] {
+                is_ocean[idx] = true;
+                queue.push_back(idx);
+            }
+        }
+    }
+
+    while let Some(idx) = queue.pop_front() {
+        let (x, y) = (idx % width, idx / width);
+        let neighbors = [
+            (x > 0).then(|| idx - 1),
+            (x + 1 < width).then_some(idx + 1),
+            (y > 0).then(|| idx - width),
+            (y + 1 < height).then_some(idx + width),
+        ];
+
+        for neighbor in neighbors.into_iter().flatten() {
+            if is_water[neighbor] && !is_ocean[neighbor] {
+                is_ocean[neighbor] = true;
+                queue.push_back(neighbor);
+            }
+        }
+    }
+
+    is_water
+        .iter()
+        .zip(is_ocean)
+        .map(|(water, ocean)| *water && !ocean)
+        .collect()
+}
+
+/// Sizes in cells of the connected land areas of the height field, largest first.
+///
+/// Land is everything classified as [`TerrainKind::Shore`] or higher. Land cells are connected if
+/// they share an edge.
+pub fn landmasses(heights: &HeightField, gradient: &Gradient) -> Vec<usize> {
+    let (width, height) = (heights.width as usize, heights.height as usize);
+    let mut unvisited: Vec<bool> = heights
+        .data
+        .iter()
+        .map(|height| !is_water(gradient, *height))
+        .collect();
+    let mut sizes = Vec::new();
+    let mut queue = VecDeque::new();
+
+    for start in 0..unvisited.len() {
+        if !unvisited[start] {
+            continue;
+        }
+
+        unvisited[start] = false;
+        queue.push_back(start);
+        let mut size = 0;
+
+        while let Some(idx) = queue.pop_front() {
+            size += 1;
+            let (x, y) = (idx % width, idx / width);
+            let neighbors = [
+                (x > 0).then(|| idx - 1),
+                (x + 1 < width).then_some(idx + 1),
+                (y > 0).then(|| idx - width),
+                (y + 1 < height).then_some(idx + width),
+            ];
+
+            for neighbor in neighbors.into_iter().flatten() {
+                if unvisited[neighbor] {
+                    unvisited[neighbor] = false;
+                    queue.push_back(neighbor);
+                }
+            }
+        }
+
+        sizes.push(size);
+    }
+
+    sizes.sort_unstable_by(|a, b| b.cmp(a));
+    sizes
+}
+
+/// Divides the height field into `count` regions, e.g. for a political map.
+///
+/// The region centers are scattered over the field depending on `seed` only, every cell belongs
+/// to the region of the closest center. Returns one entry per height with the index of its region,
+/// or `None` for water if `include_water` is not set.
+pub fn regions(
+    heights: &HeightField,
+    gradient: &Gradient,
+    seed: u64,
+    count: usize,
+    include_water: bool,
+) -> Vec<Option<usize>> {
+    let width = heights.width.max(1) as usize;
+    let to_unit = |hash: u64| (hash >> 11) as f64 / (1u64 << 53) as f64;
+    let centers: Vec<(f64, f64)> = (0..count as u64)
+        .map(|idx| {
+            (
+                to_unit(mixed_hash(&(seed, idx, 0u8))) * heights.width as f64,
+                to_unit(mixed_hash(&(seed, idx, 1u8))) * heights.height as f64,
+            )
+        })
+        .collect();
+
+    heights
+        .data
+        .iter()
+        .enumerate()
+        .map(|(idx, height)| {
+            if !include_water && is_water(gradient, *height) {
+                return None;
+            }
+
+            let (x, y) = ((idx % width) as f64 + 0.5, (idx / width) as f64 + 0.5);
+            centers
+                .iter()
+                .map(|(center_x, center_y)| (x - center_x).powi(2) + (y - center_y).powi(2))
+                .enumerate()
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .map(|(region, _)| region)
+        })
+        .collect()
+}
+
+/// Whether `height` falls into one of the bands below [`TerrainKind::Shore`].
+fn is_water(gradient: &Gradient, height: f64) -> bool {
+    gradient
+        .get_terrain_kind(height)
+        .is_some_and(|idx| idx < TerrainKind::Shore as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::generator::{self, GenParams};
+
+    #[test]
+    fn flattened_water_has_one_color() {
+        let gradient = Gradient::default();
+        let mut heights = vec![0.0; 80 * 60];
+        generator::generate_heightmap(&mut heights, &GenParams::new(12345, 80, 60, 0.2, 0.1))
+            .unwrap();
+        let original = heights.clone();
+
+        flatten_water(&mut heights, &gradient, DEFAULT_SEA_LEVEL);
+        let mut image = vec![0; heights.len() * 3];
+        generator::color_heights(&mut image, &heights, &gradient, None);
+
+        let sea = gradient.lerp_color(DEFAULT_SEA_LEVEL).0;
+        let mut water = 0;
+        for ((pixel, height), original) in image.chunks_exact(3).zip(&heights).zip(&original) {
+            if is_water(&gradient, *original) {
+                assert_eq!(pixel, sea);
+                water += 1;
+            } else {
+                assert_eq!(height, original);
+            }
+        }
+        assert!(water > 0 && water < heights.len());
+    }
+
+    #[test]
+    fn lake_inside_ring_of_mountains() {
+        // water everywhere, with a ring of mountains from 2 to 6 around the cells 3 to 5
+        let mut heights = HeightField::from_vec(vec![DEFAULT_SEA_LEVEL; 9 * 9], 9, 9).unwrap();
+        for y in 2..=6 {
+            for x in 2..=6 {
+                if x == 2 || x == 6 || y == 2 || y == 6 {
+                    heights.data[y * 9 + x] = 0.95;
+                }
+            }
+        }
+        let gradient = Gradient::default();
+
+        let lakes = find_lakes(&heights, &gradient);
+        for (idx, lake) in lakes.iter().enumerate() {
+            let (x, y) = (idx % 9, idx / 9);
+            let inside = (3..=5).contains(&x) && (3..=5).contains(&y);
+            assert_eq!(*lake, inside, "{x},{y}");
+        }
+
+        // a gap in the ring connects the lake to the ocean
+        heights.data[2 * 9 + 4] = DEFAULT_SEA_LEVEL;
+        assert!(!find_lakes(&heights, &gradient).contains(&true));
+    }
+}
@@ -0,0 +1,79 @@
+use std::str::FromStr;
+
+/// Continuous color maps which color heights directly instead of using terrain bands.
+///
+/// A ramp spans the full level range from 0.0 to 1.0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorRamp {
+    /// black at the lowest to white at the highest level
+    Grayscale,
+    /// perceptually uniform ramp from dark purple over teal to yellow
+    Viridis,
+    /// blue water over green lowlands and brown highlands to white peaks
+    Terrain,
+}
+
+const GRAYSCALE_STOPS: [(f64, [u8; 3]); 2] = [(0.0, [0, 0, 0]), (1.0, [255, 255, 255])];
+
+const VIRIDIS_STOPS: [(f64, [u8; 3]); 5] = [
+    (0.0, [68, 1, 84]),
+    (0.25, [59, 82, 139]),
+    (0.5, [33, 145, 140]),
+    (0.75, [94, 201, 98]),
+    (1.0, [253, 231, 37]),
+];
+
+const TERRAIN_STOPS: [(f64, [u8; 3]); 6] = [
+    (0.0, [51, 51, 153]),
+    (0.15, [0, 153, 255]),
+    (0.25, [0, 204, 102]),
+    (0.5, [255, 255, 153]),
+    (0.75, [128, 92, 84]),
+    (1.0, [255, 255, 255]),
+];
+
+impl ColorRamp {
+    /// Color of the given level, which is clamped to the range 0.0 to 1.0.
+    pub fn color(&self, level: f64) -> image::Rgb<u8> {
+        let stops: &[(f64, [u8; 3])] = match self {
+            ColorRamp::Grayscale => &GRAYSCALE_STOPS,
+            ColorRamp::Viridis => &VIRIDIS_STOPS,
+            ColorRamp::Terrain => &TERRAIN_STOPS,
+        };
+
+        let level = level.clamp(0.0, 1.0);
+        let upper = stops
+            .iter()
+            .position(|(stop, _)| *stop >= level)
+            .unwrap_or(stops.len() - 1)
+            .max(1);
+        let (start, start_color) = stops[upper - 1];
+        let (end, end_color) = stops[upper];
+
+        let factor = (level - start) / (end - start);
+        let mut color = [0; 3];
+        for channel in 0..3 {
+            color[channel] = (start_color[channel] as f64
+                + (end_color[channel] as f64 - start_color[channel] as f64) * factor)
+                .round() as u8;
+        }
+
+        image::Rgb(color)
+    }
+}
+
+impl FromStr for ColorRamp {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "grayscale" | "greyscale" => Ok(ColorRamp::Grayscale),
+            "viridis" => Ok(ColorRamp::Viridis),
+            "terrain" => Ok(ColorRamp::Terrain),
+            _ => Err(format!(
+                "{s} is not a color ramp. Available are: grayscale, viridis, terrain"
+            )),
+        }
+    }
+}
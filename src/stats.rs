@@ -0,0 +1,67 @@
+use crate::gradient::{Gradient, TerrainKind};
+
+/// Summary of a height field.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HeightStats {
+    pub min: f64,
+    pub max: f64,
+    pub mean: f64,
+    /// number of pixels per band of the gradient, indexed by band index. For the default gradient
+    /// this is `TerrainKind as usize`
+    pub terrain_counts: Vec<usize>,
+    pub pixel_count: usize,
+}
+
+impl HeightStats {
+    /// Computes the statistics of `heights`, classifying each height with `gradient`.
+    pub fn new(heights: &[f64], gradient: &Gradient) -> Self {
+        let mut min = f64::INFINITY;
+        let mut max = f64::NEG_INFINITY;
+        let mut sum = 0.0;
+        let mut terrain_counts = vec![0; gradient.band_count()];
+
+        for height in heights {
+            min = min.min(*height);
+            max = max.max(*height);
+            sum += height;
+
+            if let Some(idx) = gradient.get_terrain_kind(*height) {
+                terrain_counts[idx] += 1;
+            }
+        }
+
+        Self {
+            min,
+            max,
+            mean: sum / heights.len() as f64,
+            terrain_counts,
+            pixel_count: heights.len(),
+        }
+    }
+
+    /// Number of pixels classified as `kind`.
+    pub fn count(&self, kind: TerrainKind) -> usize {
+        self.terrain_counts.get(kind as usize).copied().unwrap_or(0)
+    }
+
+    /// Share of pixels classified as `kind` in percent.
+    pub fn percentage(&self, kind: TerrainKind) -> f64 {
+        self.count(kind) as f64 / self.pixel_count as f64 * 100.0
+    }
+}
+
+/// Number of heights in each of `buckets` equally wide ranges between 0.0 and 1.0, lowest first.
+/// Heights outside of that range are counted in the closest bucket.
+pub fn histogram(heights: &[f64], buckets: usize) -> Vec<usize> {
+    let mut counts = vec![0; buckets];
+    if buckets == 0 {
+        return counts;
+    }
+
+    for height in heights {
+        let bucket = (height * buckets as f64).clamp(0.0, (buckets - 1) as f64) as usize;
+        counts[bucket] += 1;
+    }
+
+    counts
+}